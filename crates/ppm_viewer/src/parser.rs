@@ -89,7 +89,7 @@ impl PPMImage {
             },
         ))
     }
-    fn parse_p6(input: &str) -> IResult<&str, Self> {
+    fn parse_p6(_input: &str) -> IResult<&str, Self> {
         todo!("Parse P6")
     }
 }
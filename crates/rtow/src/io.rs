@@ -1,3 +1,7 @@
 //! Module for dealing with input and output
 
-pub mod images;
\ No newline at end of file
+pub mod ies;
+pub mod images;
+pub mod mtl;
+pub mod obj;
+pub mod volumes;
\ No newline at end of file
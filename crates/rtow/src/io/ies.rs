@@ -0,0 +1,112 @@
+//! Reads IES LM-63 photometric files for [`IesLight`](crate::primitives::lights::ies::IesLight)s
+
+use crate::primitives::{lights::ies::IesProfile, Decimal};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn count(value: Decimal) -> io::Result<usize> {
+    if value < 0.0 || value.fract() != 0.0 {
+        return Err(invalid("bad count in IES file"));
+    }
+    Ok(value as usize)
+}
+
+///Reads an IES file with type C photometry.
+///
+///Candela values are multiplied by the file's candela multiplier and ballast factors. Tilt information is ignored.
+///
+/// # Errors
+/// If we fail to read from `reader`, the file is malformed, it uses type A or B photometry, or the tilt data is in another file
+pub fn read_ies(mut reader: impl BufRead) -> io::Result<IesProfile> {
+    //keywords, which we don't need, until the tilt line
+    let mut line = String::new();
+    let tilt = loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("missing IES TILT line"));
+        }
+        if let Some(tilt) = line.trim().strip_prefix("TILT=") {
+            break tilt.trim().to_string();
+        }
+    };
+
+    //everything else is just numbers, split over lines however the file likes
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest)?;
+    let mut numbers = rest.split_whitespace().map(|number| {
+        number
+            .parse::<Decimal>()
+            .map_err(|_| invalid("bad number in IES file"))
+    });
+    let mut next = || {
+        numbers
+            .next()
+            .unwrap_or_else(|| Err(invalid("IES file ended early")))
+    };
+
+    match tilt.as_str() {
+        "NONE" => {}
+        "INCLUDE" => {
+            //lamp-to-luminaire geometry, then angles and their multiplying factors
+            next()?;
+            let pairs = count(next()?)?;
+            for _ in 0..pairs * 2 {
+                next()?;
+            }
+        }
+        _ => return Err(invalid("IES tilt files are not supported")),
+    }
+
+    //number of lamps, lumens per lamp
+    next()?;
+    next()?;
+    let multiplier = next()?;
+    let vertical_count = count(next()?)?;
+    let horizontal_count = count(next()?)?;
+    if count(next()?)? != 1 {
+        return Err(invalid("only type C IES photometry is supported"));
+    }
+    //units, then the width, length and height of the opening
+    for _ in 0..4 {
+        next()?;
+    }
+    let ballast = next()?;
+    let ballast_lamp = next()?;
+    //input watts
+    next()?;
+
+    if vertical_count == 0 || horizontal_count == 0 {
+        return Err(invalid("IES file has no angles"));
+    }
+    let vertical = (0..vertical_count)
+        .map(|_| next())
+        .collect::<io::Result<Vec<_>>>()?;
+    let horizontal = (0..horizontal_count)
+        .map(|_| next())
+        .collect::<io::Result<Vec<_>>>()?;
+    if !vertical.is_sorted() || !horizontal.is_sorted() {
+        return Err(invalid("IES angles must be increasing"));
+    }
+
+    let scale = multiplier * ballast * ballast_lamp;
+    let candela = (0..vertical_count * horizontal_count)
+        .map(|_| next().map(|value| value * scale))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(IesProfile::new(vertical, horizontal, candela))
+}
+
+///Reads an IES file
+///
+/// # Errors
+/// If the file can't be opened, or isn't a valid IES file - see [`read_ies`]
+pub fn open_ies(path: impl AsRef<Path>) -> io::Result<IesProfile> {
+    read_ies(BufReader::new(File::open(path)?))
+}
@@ -1,10 +1,82 @@
 //! Module for dealing with images
 
-use crate::primitives::Decimal;
+use crate::primitives::{Colour, Decimal};
+use std::{fs::File, io, io::BufReader, path::Path};
 
+pub mod hdr;
+pub mod png;
 pub mod ppm;
 
 pub trait Pixel: Default + Clone {
     ///Should be from 0-1
     fn rgb (&self) -> [Decimal; 3];
-}
\ No newline at end of file
+}
+
+///How the values in an image file are encoded
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ColourSpace {
+    ///Gamma encoded, like most 8-bit images - needs decoding before we can do maths with it
+    Srgb,
+    ///Already linear, like HDR images
+    Linear,
+}
+
+///An image read from a file, with pixels stored in row-major configuration from the top left
+#[derive(Debug, Clone)]
+pub struct LoadedImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Colour>,
+    pub colour_space: ColourSpace,
+}
+
+impl LoadedImage {
+    ///Converts the pixels to linear values, if they aren't already
+    #[must_use]
+    pub fn into_linear(mut self) -> Self {
+        if self.colour_space == ColourSpace::Srgb {
+            for pixel in &mut self.pixels {
+                *pixel = Colour::new(
+                    srgb_to_linear(pixel.x()),
+                    srgb_to_linear(pixel.y()),
+                    srgb_to_linear(pixel.z()),
+                );
+            }
+            self.colour_space = ColourSpace::Linear;
+        }
+        self
+    }
+}
+
+///Decodes one sRGB channel from 0-1
+#[must_use]
+pub fn srgb_to_linear(value: Decimal) -> Decimal {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+///Reads a PNG, PPM or Radiance HDR file, based on its extension
+///
+/// # Errors
+/// If the file can't be opened, the extension isn't one we know, or the file isn't a valid image
+pub fn load(path: impl AsRef<Path>) -> io::Result<LoadedImage> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    let reader = BufReader::new(File::open(path)?);
+
+    match extension.as_deref() {
+        Some("png") => png::read_png(reader),
+        Some("ppm" | "pnm") => ppm::read_ppm(reader),
+        Some("hdr" | "pic") => hdr::read_hdr(reader),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown image format: {}", path.display()),
+        )),
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    io::images::{ColourSpace, LoadedImage},
+    primitives::{Colour, Decimal},
+};
+use std::io::{self, BufRead};
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+///Turns a shared exponent pixel into a colour
+fn rgbe_to_colour([red, green, blue, exponent]: [u8; 4]) -> Colour {
+    if exponent == 0 {
+        return Colour::default();
+    }
+
+    //each channel is a fraction out of 256, scaled by 2^(exponent - 128)
+    let scale = 2.0_f64.powi(i32::from(exponent) - 136);
+    Colour::new(
+        Decimal::from(red) * scale,
+        Decimal::from(green) * scale,
+        Decimal::from(blue) * scale,
+    )
+}
+
+///Reads one scanline, which is either flat or run length encoded one channel at a time
+fn read_scanline(reader: &mut impl BufRead, width: usize) -> io::Result<Vec<[u8; 4]>> {
+    let mut first = [0; 4];
+    reader.read_exact(&mut first)?;
+
+    let encoded = (8..32768).contains(&width) && first[0] == 2 && first[1] == 2 && first[2] < 128;
+    if !encoded {
+        let mut scanline = vec![first];
+        for _ in 1..width {
+            let mut pixel = [0; 4];
+            reader.read_exact(&mut pixel)?;
+            scanline.push(pixel);
+        }
+        return Ok(scanline);
+    }
+
+    if usize::from(u16::from_be_bytes([first[2], first[3]])) != width {
+        return Err(invalid("HDR scanline has the wrong width"));
+    }
+
+    let mut scanline = vec![[0; 4]; width];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0; 1];
+            reader.read_exact(&mut count)?;
+
+            if count[0] > 128 {
+                //a run of the same byte
+                let run = usize::from(count[0] - 128);
+                let mut value = [0; 1];
+                reader.read_exact(&mut value)?;
+                for pixel in scanline.iter_mut().skip(x).take(run) {
+                    pixel[channel] = value[0];
+                }
+                x += run;
+            } else {
+                //a run of different bytes
+                let run = usize::from(count[0]);
+                for pixel in scanline.iter_mut().skip(x).take(run) {
+                    let mut value = [0; 1];
+                    reader.read_exact(&mut value)?;
+                    pixel[channel] = value[0];
+                }
+                x += run;
+            }
+
+            if count[0] == 0 || count[0] == 128 || x > width {
+                return Err(invalid("bad HDR run length"));
+            }
+        }
+    }
+
+    Ok(scanline)
+}
+
+///Reads a Radiance RGBE (`.hdr`) image, in the standard `-Y height +X width` orientation
+///
+/// # Errors
+/// If we fail to read from `reader`, or it isn't a valid HDR
+pub fn read_hdr(mut reader: impl BufRead) -> io::Result<LoadedImage> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid("missing HDR magic number"));
+    }
+
+    //the header is a list of variables, ended by a blank line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid("only RGBE HDR images are supported"));
+            }
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+    let (height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["-Y", height, "+X", width] => (
+            height.parse().map_err(|_| invalid("bad HDR height"))?,
+            width.parse().map_err(|_| invalid("bad HDR width"))?,
+        ),
+        _ => return Err(invalid("unsupported HDR orientation")),
+    };
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for _ in 0..height {
+        pixels.extend(
+            read_scanline(&mut reader, width)?
+                .into_iter()
+                .map(rgbe_to_colour),
+        );
+    }
+
+    Ok(LoadedImage {
+        width,
+        height,
+        pixels,
+        colour_space: ColourSpace::Linear,
+    })
+}
@@ -0,0 +1,47 @@
+use crate::{
+    io::images::{ColourSpace, LoadedImage},
+    primitives::{Colour, Decimal},
+};
+use ::png::{BitDepth, ColorType, Decoder, Transformations};
+use std::io::{self, Read};
+
+///Reads a PNG - palettes and low bit depths are expanded, and alpha is ignored
+///
+/// # Errors
+/// If we fail to read from `reader`, or it isn't a valid PNG
+pub fn read_png(reader: impl Read) -> io::Result<LoadedImage> {
+    let mut decoder = Decoder::new(reader);
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info()?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    buffer.truncate(info.buffer_size());
+
+    let samples: Vec<Decimal> = match info.bit_depth {
+        BitDepth::Sixteen => buffer
+            .chunks_exact(2)
+            .map(|bytes| Decimal::from(u16::from_be_bytes([bytes[0], bytes[1]])) / 65535.0)
+            .collect(),
+        _ => buffer
+            .into_iter()
+            .map(|byte| Decimal::from(byte) / 255.0)
+            .collect(),
+    };
+
+    let channels = info.color_type.samples();
+    let pixels = samples
+        .chunks_exact(channels)
+        .map(|pixel| match info.color_type {
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Colour::splat(pixel[0]),
+            _ => Colour::new(pixel[0], pixel[1], pixel[2]),
+        })
+        .collect();
+
+    Ok(LoadedImage {
+        width: info.width as usize,
+        height: info.height as usize,
+        pixels,
+        colour_space: ColourSpace::Srgb,
+    })
+}
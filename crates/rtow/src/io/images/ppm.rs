@@ -1,15 +1,23 @@
 use crate::{
-    io::images::Pixel,
-    primitives::{camera::Camera, collisions::Hittable, Colour, Decimal, Vec3},
+    io::images::{ColourSpace, LoadedImage, Pixel},
+    primitives::{
+        camera::Camera,
+        integrators::{
+            aov::{Aov, AovSample},
+            Integrator,
+        },
+        scene::Scene,
+        Colour, Decimal, Vec3,
+    },
 };
 use indicatif::{ProgressBar, ProgressStyle};
 use rand::{thread_rng, Rng};
 use rayon::prelude::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 use std::{
     io,
-    io::Write,
+    io::{Read, Write},
     ops::{Index, IndexMut},
-    sync::mpsc::channel,
+    sync::{mpsc::channel, Mutex},
 };
 
 ///Struct to hold a PPM-Based Image
@@ -87,16 +95,61 @@ impl<P: Pixel> PPMImage<P> {
 
         Ok(())
     }
+
+    ///Writes the unclamped values as a PFM, the floating point version of a PPM, for buffers like depths and positions which don't fit in 0-1
+    ///
+    /// # Errors
+    /// If we fail to write to the object, we bubble it up
+    pub fn write_pfm(&self, mut w: impl Write, samples_per_pixel: usize) -> io::Result<()> {
+        //the negative scale means little-endian
+        writeln!(
+            w,
+            "PF\n{width} {height}\n-1.0",
+            width = self.width,
+            height = self.height
+        )?;
+
+        //rows go from the bottom up, like ours
+        for pixel in &self.pixels {
+            for value in pixel.rgb() {
+                let value = (value / samples_per_pixel as Decimal) as f32;
+                w.write_all(&value.to_le_bytes())?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl PPMImage<Vec3> {
+    ///Function to render the `scene` through the `camera` into this image, using the `integrator` for every sample
+    ///
+    /// # Panics
+    /// If the progress bar template is invalid, or if a worker thread is unable to send a pixel back
     pub fn fill(
         &mut self,
         camera: &Camera,
-        world: &dyn Hittable,
+        scene: &Scene,
+        integrator: &dyn Integrator,
         samples_per_pixel: usize,
-        max_depth: usize,
     ) {
+        self.fill_with_aovs(camera, scene, integrator, samples_per_pixel, &[]);
+    }
+
+    ///Like [`PPMImage::fill`], but also renders each of `aovs` in the same pass, giving back an image of each in the same order.
+    ///
+    ///`aovs` should come from [`Integrator::aovs`], and `--aovs` is refused for an integrator that has none. Any the integrator doesn't write stay black.
+    ///
+    /// # Panics
+    /// If the progress bar template is invalid, or if a worker thread is unable to send a pixel back
+    pub fn fill_with_aovs(
+        &mut self,
+        camera: &Camera,
+        scene: &Scene,
+        integrator: &dyn Integrator,
+        samples_per_pixel: usize,
+        aovs: &[Aov],
+    ) -> Vec<Self> {
         let no = (self.width * self.height) as u64;
         let progress_bar = ProgressBar::new(no); //make a new progress bar with the number of runs we expect to do
         progress_bar.set_style(
@@ -108,17 +161,28 @@ impl PPMImage<Vec3> {
         );
 
         let (tx, rx) = channel();
+        //light splatted anywhere on the image by the integrator, added up over every sample
+        let splatted: Mutex<Vec<Colour>> = Mutex::new(vec![]);
 
         (0..self.width)
             .into_par_iter()
             .chunks(self.width / 2)
-            .for_each_with((tx, world), |(tx, world), rows| {
-                let world = world.clone();
+            .for_each_with(tx, |tx, rows| {
                 let mut rng = thread_rng();
+                let mut splats = vec![];
+                let mut splat = |s: Decimal, t: Decimal, light: Colour| {
+                    if splats.is_empty() {
+                        splats = vec![Colour::default(); self.width * self.height];
+                    }
+                    let x = ((s * (self.width - 1) as Decimal) as usize).min(self.width - 1);
+                    let y = ((t * (self.height - 1) as Decimal) as usize).min(self.height - 1);
+                    splats[y * self.width + x] += light;
+                };
 
                 for x in rows {
                     for y in 0..self.height {
                         let mut colour = Colour::new(0.0, 0.0, 0.0);
+                        let mut outputs = vec![Colour::default(); aovs.len()];
 
                         for _ in 0..samples_per_pixel {
                             let u = (x as Decimal + rng.gen_range(0.0..=1.0))
@@ -126,24 +190,173 @@ impl PPMImage<Vec3> {
                             let v = (y as Decimal + rng.gen_range(0.0..=1.0))
                                 / (self.height - 1) as Decimal;
 
-                            let ray = camera.get_ray(u, v, &mut rng);
-                            colour += ray.colour(world, max_depth, &mut rng);
+                            let ray = camera.get_ray_with_differentials(
+                                u,
+                                v,
+                                1.0 / (self.width - 1) as Decimal,
+                                1.0 / (self.height - 1) as Decimal,
+                                &mut rng,
+                            );
+                            let mut media = camera.medium().into_iter().collect();
+                            let mut sample = AovSample::default();
+                            colour += integrator.colour_with_aovs(
+                                ray,
+                                scene,
+                                &mut media,
+                                &mut splat,
+                                &mut sample,
+                                &mut rng,
+                            );
+                            for (output, aov) in outputs.iter_mut().zip(aovs) {
+                                *output += aov.value(&sample);
+                            }
                         }
 
                         progress_bar.inc(1);
-                        tx.send((x, y, colour)).expect("unable to send");
+                        tx.send((x, y, colour, outputs)).expect("unable to send");
+                    }
+                }
+
+                if !splats.is_empty() {
+                    let mut splatted = splatted.lock().expect("unable to lock splats");
+                    if splatted.is_empty() {
+                        *splatted = splats;
+                    } else {
+                        for (total, light) in splatted.iter_mut().zip(splats) {
+                            *total += light;
+                        }
                     }
                 }
             });
 
+        let mut buffers: Vec<Self> = aovs
+            .iter()
+            .map(|_| Self::new(self.width, self.height))
+            .collect();
         let mut recv_count = 0;
         while recv_count < no {
-            for (x, y, colour) in rx.try_iter() {
+            for (x, y, colour, outputs) in rx.try_iter() {
                 self[(x, y)] = colour;
+                for (buffer, output) in buffers.iter_mut().zip(outputs) {
+                    buffer[(x, y)] = output;
+                }
                 recv_count += 1;
             }
         }
 
+        //the image seen by the camera spans one pixel fewer each way than it has, but every pixel traces paths which splat onto it
+        let scale = ((self.width - 1) * (self.height - 1)) as Decimal
+            / (self.width * self.height) as Decimal;
+        let splatted = splatted.into_inner().expect("unable to lock splats");
+        for (pixel, light) in self.pixels.iter_mut().zip(splatted) {
+            *pixel += light * scale;
+        }
+
         progress_bar.finish_and_clear();
+        buffers
+    }
+}
+
+///Reads the next whitespace separated header field, skipping `#` comments
+fn next_token<'a>(bytes: &'a [u8], position: &mut usize) -> io::Result<&'a str> {
+    loop {
+        match bytes.get(*position) {
+            Some(byte) if byte.is_ascii_whitespace() => *position += 1,
+            Some(b'#') => {
+                while bytes.get(*position).is_some_and(|byte| *byte != b'\n') {
+                    *position += 1;
+                }
+            }
+            Some(_) => break,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
     }
+
+    let start = *position;
+    while bytes
+        .get(*position)
+        .is_some_and(|byte| !byte.is_ascii_whitespace())
+    {
+        *position += 1;
+    }
+
+    std::str::from_utf8(&bytes[start..*position])
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn next_number(bytes: &[u8], position: &mut usize) -> io::Result<usize> {
+    next_token(bytes, position)?
+        .parse()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+///Reads a plain (`P3`) or binary (`P6`) PPM
+///
+/// # Errors
+/// If we fail to read from `reader`, or it isn't a valid PPM
+pub fn read_ppm(mut reader: impl Read) -> io::Result<LoadedImage> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+
+    let mut position = 0;
+    let binary = match next_token(&bytes, &mut position)? {
+        "P3" => false,
+        "P6" => true,
+        magic => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported PPM type {magic}"),
+            ))
+        }
+    };
+    let width = next_number(&bytes, &mut position)?;
+    let height = next_number(&bytes, &mut position)?;
+    let max_value = next_number(&bytes, &mut position)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "PPM max value must be from 1-65535",
+        ));
+    }
+
+    let samples: Vec<usize> = if binary {
+        //exactly one whitespace character separates the header from the data
+        let data = bytes
+            .get(position + 1..)
+            .ok_or(io::ErrorKind::UnexpectedEof)?;
+        if max_value < 256 {
+            data.iter().map(|byte| usize::from(*byte)).collect()
+        } else {
+            data.chunks_exact(2)
+                .map(|bytes| usize::from(u16::from_be_bytes([bytes[0], bytes[1]])))
+                .collect()
+        }
+    } else {
+        (0..width * height * 3)
+            .map(|_| next_number(&bytes, &mut position))
+            .collect::<io::Result<_>>()?
+    };
+
+    if samples.len() < width * height * 3 {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    let pixels = samples
+        .chunks_exact(3)
+        .take(width * height)
+        .map(|rgb| {
+            Colour::new(
+                rgb[0] as Decimal / max_value as Decimal,
+                rgb[1] as Decimal / max_value as Decimal,
+                rgb[2] as Decimal / max_value as Decimal,
+            )
+        })
+        .collect();
+
+    Ok(LoadedImage {
+        width,
+        height,
+        pixels,
+        colour_space: ColourSpace::Srgb,
+    })
 }
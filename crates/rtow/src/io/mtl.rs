@@ -0,0 +1,306 @@
+//! Reads Wavefront `.mtl` material libraries into [`Principled`] materials
+//!
+//!Supported statements are `newmtl`, `Kd`/`map_Kd` (base colour), `Ns` (turned into roughness if there's no `Pr`), `Ni` (ior),
+//!`d`/`Tr`/`map_d` (opacity, cut out like an alpha mask), `Ke` (emission), and the PBR extension's `Pr`/`map_Pr`, `Pm`/`map_Pm`,
+//!`Ps` (sheen), `Pc` and `Pcr` (clearcoat), `norm` (tangent space normal map), and `bump`/`map_Bump` (height map, scaled by `-bm`).
+//!Anything else is ignored.
+
+use crate::{
+    io::images::{self, ColourSpace},
+    primitives::{
+        materials::{principled::Principled, Material},
+        textures::{image::ImageTexture, Texture},
+        Colour, Decimal,
+    },
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+///One material while we're reading it, as some statements depend on others
+#[derive(Default)]
+struct Entry {
+    principled: Principled,
+    shininess: Option<Decimal>,
+    has_roughness: bool,
+    emission: Option<Colour>,
+    normal_map: Option<Arc<dyn Texture>>,
+    ///height map and strength
+    bump_map: Option<(Arc<dyn Texture>, Decimal)>,
+    ///[`None`] if it's opaque
+    opacity: Option<Arc<dyn Texture>>,
+}
+
+impl Entry {
+    fn finish(mut self) -> Material {
+        if let Some(emission) = self.emission.filter(|emission| !emission.near_zero()) {
+            return Material::DiffuseLight(emission.into());
+        }
+
+        if let (Some(shininess), false) = (self.shininess, self.has_roughness) {
+            //the phong exponent to a beckmann width is sqrt(2 / (n + 2)), and our roughness is the sqrt of the width
+            let roughness = (2.0 / (shininess.max(0.0) + 2.0)).powf(0.25);
+            self.principled.roughness = Colour::splat(roughness).into();
+        }
+        let mut material = Material::Principled(Box::new(self.principled));
+        if let Some((height, strength)) = self.bump_map {
+            material = Material::BumpMapped(height, strength, Box::new(material));
+        }
+        if let Some(map) = self.normal_map {
+            material = Material::NormalMapped(map, Box::new(material));
+        }
+        if let Some(opacity) = self.opacity {
+            //on the outside, as holes are found before anything else
+            material = Material::Cutout(opacity, Box::new(material));
+        }
+        material
+    }
+}
+
+fn invalid(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {line_number}: {message}"),
+    )
+}
+
+fn numbers(arguments: &[&str], line_number: usize) -> io::Result<Vec<Decimal>> {
+    arguments
+        .iter()
+        .map(|argument| {
+            argument
+                .parse()
+                .map_err(|_| invalid(line_number, "expected a number"))
+        })
+        .collect()
+}
+
+fn colour(arguments: &[&str], line_number: usize) -> io::Result<Colour> {
+    match numbers(arguments, line_number)?[..] {
+        [grey] => Ok(Colour::splat(grey)),
+        [red, green, blue, ..] => Ok(Colour::new(red, green, blue)),
+        _ => Err(invalid(line_number, "expected a colour")),
+    }
+}
+
+fn scalar(arguments: &[&str], line_number: usize) -> io::Result<Decimal> {
+    numbers(arguments, line_number)?
+        .first()
+        .copied()
+        .ok_or_else(|| invalid(line_number, "expected a number"))
+}
+
+///Loads the image at the end of a `map_` statement - options before the file name are ignored
+fn texture(
+    arguments: &[&str],
+    directory: &Path,
+    colour_space: ColourSpace,
+    line_number: usize,
+) -> io::Result<Arc<dyn Texture>> {
+    let file = arguments
+        .last()
+        .ok_or_else(|| invalid(line_number, "expected a file name"))?;
+    let mut image = images::load(directory.join(file))?;
+    if colour_space == ColourSpace::Linear {
+        //data like roughness is stored as is, even in formats which are usually sRGB
+        image.colour_space = ColourSpace::Linear;
+    }
+    Ok(Arc::new(ImageTexture::from_image(image)))
+}
+
+///The `-bm` option of a bump map statement, which defaults to 1
+fn bump_strength(arguments: &[&str], line_number: usize) -> io::Result<Decimal> {
+    arguments
+        .iter()
+        .position(|argument| *argument == "-bm")
+        .map_or(Ok(1.0), |index| {
+            scalar(arguments.get(index + 1..).unwrap_or_default(), line_number)
+        })
+}
+
+///A constant opacity for a [`Material::Cutout`], or [`None`] if it's opaque
+fn opacity(opacity: Decimal) -> Option<Arc<dyn Texture>> {
+    (opacity < 1.0).then(|| Colour::splat(opacity.max(0.0)).into())
+}
+
+///Reads every material in an MTL file, with texture paths relative to `directory`
+///
+/// # Errors
+/// If we fail to read from `reader`, a statement is malformed, or a texture can't be loaded
+pub fn parse_mtl(reader: impl BufRead, directory: &Path) -> io::Result<HashMap<String, Material>> {
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Entry)> = None;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let mut tokens = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, entry)) = current.take() {
+                materials.insert(name, entry.finish());
+            }
+            current = Some((arguments.join(" "), Entry::default()));
+            continue;
+        }
+
+        let Some((_, entry)) = current.as_mut() else {
+            return Err(invalid(line_number, "statement before newmtl"));
+        };
+        let principled = &mut entry.principled;
+
+        match keyword {
+            "Kd" => principled.base_colour = colour(&arguments, line_number)?.into(),
+            "map_Kd" => {
+                principled.base_colour =
+                    texture(&arguments, directory, ColourSpace::Srgb, line_number)?;
+            }
+            "Ns" => entry.shininess = Some(scalar(&arguments, line_number)?),
+            "Ni" => principled.ior = scalar(&arguments, line_number)?,
+            "d" => entry.opacity = opacity(scalar(&arguments, line_number)?),
+            "Tr" => entry.opacity = opacity(1.0 - scalar(&arguments, line_number)?),
+            "map_d" => {
+                entry.opacity = Some(texture(
+                    &arguments,
+                    directory,
+                    ColourSpace::Linear,
+                    line_number,
+                )?);
+            }
+            "Ke" => entry.emission = Some(colour(&arguments, line_number)?),
+            "Pr" => {
+                principled.roughness = Colour::splat(scalar(&arguments, line_number)?).into();
+                entry.has_roughness = true;
+            }
+            "map_Pr" => {
+                principled.roughness =
+                    texture(&arguments, directory, ColourSpace::Linear, line_number)?;
+                entry.has_roughness = true;
+            }
+            "Pm" => principled.metallic = Colour::splat(scalar(&arguments, line_number)?).into(),
+            "map_Pm" => {
+                principled.metallic =
+                    texture(&arguments, directory, ColourSpace::Linear, line_number)?;
+            }
+            "Ps" => principled.sheen = scalar(&arguments, line_number)?,
+            "Pc" => principled.clearcoat = scalar(&arguments, line_number)?,
+            "Pcr" => principled.clearcoat_roughness = scalar(&arguments, line_number)?,
+            "norm" => {
+                entry.normal_map = Some(texture(
+                    &arguments,
+                    directory,
+                    ColourSpace::Linear,
+                    line_number,
+                )?);
+            }
+            "bump" | "map_Bump" | "map_bump" => {
+                entry.bump_map = Some((
+                    texture(&arguments, directory, ColourSpace::Linear, line_number)?,
+                    bump_strength(&arguments, line_number)?,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, entry)) = current {
+        materials.insert(name, entry.finish());
+    }
+    Ok(materials)
+}
+
+///Reads every material in an MTL file, by name
+///
+/// # Errors
+/// If the file can't be read, a statement is malformed, or a texture can't be loaded
+pub fn read_mtl(path: impl AsRef<Path>) -> io::Result<HashMap<String, Material>> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_mtl(BufReader::new(File::open(path)?), directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_mtl;
+    use crate::primitives::{materials::Material, textures::Texture, Colour, Decimal, Point3};
+    use std::{collections::HashMap, io, path::Path};
+
+    fn parse(mtl: &str) -> io::Result<HashMap<String, Material>> {
+        parse_mtl(mtl.as_bytes(), Path::new(""))
+    }
+
+    ///The opacity of a [`Material::Cutout`], or 1 for anything else
+    fn opacity(material: &Material) -> Decimal {
+        match material {
+            Material::Cutout(opacity, _) => opacity.scalar(0.0, 0.0, Point3::default()),
+            _ => 1.0,
+        }
+    }
+
+    #[test]
+    fn dissolve_cuts_out() {
+        let materials = parse(
+            "newmtl half\nd 0.5\n\
+             newmtl quarter\nTr 0.75\n\
+             newmtl solid\nd 1.0\n\
+             newmtl clear\nd -1\n",
+        )
+        .expect("valid MTL");
+
+        assert!((opacity(&materials["half"]) - 0.5).abs() < 1e-9);
+        assert!((opacity(&materials["quarter"]) - 0.25).abs() < 1e-9);
+        assert!(matches!(materials["solid"], Material::Principled(_)));
+        assert!(matches!(materials["clear"], Material::Cutout(..)));
+        assert!(opacity(&materials["clear"]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn statements() {
+        let materials = parse(
+            "# a comment\n\
+             newmtl red paint\n\
+             Kd 0.8 0.1 0.1  # trailing comment\n\
+             Pm 1\n\
+             Ns 1000\n\
+             \n\
+             newmtl lamp\n\
+             Ke 4 4 4\n\
+             d 0.5\n",
+        )
+        .expect("valid MTL");
+        assert_eq!(materials.len(), 2);
+
+        let Material::Principled(principled) = &materials["red paint"] else {
+            panic!("{:?} should be principled", materials["red paint"]);
+        };
+        let at = |texture: &dyn Texture| texture.value(0.0, 0.0, Point3::default());
+        assert!(
+            (at(principled.base_colour.as_ref()) - Colour::new(0.8, 0.1, 0.1)).magnitude() < 1e-9
+        );
+        assert!((at(principled.metallic.as_ref()).x() - 1.0).abs() < 1e-9);
+        //a very shiny phong exponent is nearly smooth
+        assert!(at(principled.roughness.as_ref()).x() < 0.25);
+
+        //emission wins over everything else
+        assert!(matches!(materials["lamp"], Material::DiffuseLight(_)));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("Kd 1 1 1\n").is_err(), "statement before newmtl");
+        assert!(parse("newmtl a\nKd 1 1\n").is_err(), "not enough numbers");
+        assert!(parse("newmtl a\nd half\n").is_err(), "not a number");
+    }
+}
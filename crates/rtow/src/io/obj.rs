@@ -0,0 +1,335 @@
+//! Reads Wavefront `.obj` files into [`Mesh`]es, with materials from their `.mtl` libraries
+//!
+//!Supported statements are `v`, `vt`, `vn`, `f` (polygons are split into fans of triangles, and indices can be negative),
+//!`mtllib` and `usemtl`. Faces before any `usemtl`, or after one naming a material which isn't in the libraries, get a default [`Principled`](crate::primitives::materials::principled::Principled) material. Anything else is ignored.
+
+use crate::{
+    io::mtl::read_mtl,
+    primitives::{
+        collisions::mesh::{Mesh, Triangle},
+        materials::Material,
+        Decimal, Point3, Vec3,
+    },
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+fn invalid(line_number: usize, message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {line_number}: {message}"),
+    )
+}
+
+fn numbers<const N: usize>(arguments: &[&str], line_number: usize) -> io::Result<[Decimal; N]> {
+    if arguments.len() < N {
+        return Err(invalid(line_number, "not enough numbers"));
+    }
+    let mut numbers = [0.0; N];
+    for (number, argument) in numbers.iter_mut().zip(arguments) {
+        *number = argument
+            .parse()
+            .map_err(|_| invalid(line_number, "expected a number"))?;
+    }
+    Ok(numbers)
+}
+
+///An index from a face, which counts from 1, or back from the end if it's negative
+fn face_index(index: &str, count: usize, line_number: usize) -> io::Result<usize> {
+    let index: isize = index
+        .parse()
+        .map_err(|_| invalid(line_number, "expected an index"))?;
+    let index = match index {
+        1.. => index as usize - 1,
+        ..=-1 => count
+            .checked_sub(index.unsigned_abs())
+            .ok_or_else(|| invalid(line_number, "index out of range"))?,
+        0 => return Err(invalid(line_number, "indices start at 1")),
+    };
+    if index >= count {
+        return Err(invalid(line_number, "index out of range"));
+    }
+    Ok(index)
+}
+
+///The materials from the `mtllib`s, and the ones used so far in the order they were first used
+struct Materials {
+    libraries: HashMap<String, Material>,
+    used: Vec<Material>,
+    indices: HashMap<String, usize>,
+}
+
+impl Materials {
+    fn new() -> Self {
+        Self {
+            libraries: HashMap::new(),
+            used: vec![Material::Principled(Box::default())],
+            indices: HashMap::new(),
+        }
+    }
+
+    ///The index in [`Materials::used`] of the material called `name`, or the default if it isn't in any of the libraries
+    fn index(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        let index = self.libraries.remove(name).map_or(0, |material| {
+            self.used.push(material);
+            self.used.len() - 1
+        });
+        self.indices.insert(name.to_string(), index);
+        index
+    }
+}
+
+///Reads a whole OBJ file as one mesh, with material libraries relative to `directory`
+///
+/// # Errors
+/// If we fail to read from `reader`, a statement is malformed, or a library can't be read
+pub fn parse_obj(reader: impl BufRead, directory: &Path) -> io::Result<Mesh> {
+    let (mut positions, mut normals, mut uvs) = (vec![], vec![], vec![]);
+    let mut triangles = vec![];
+    let mut materials = Materials::new();
+    let mut material = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = index + 1;
+        let mut tokens = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let [x, y, z] = numbers(&arguments, line_number)?;
+                positions.push(Point3::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = numbers(&arguments, line_number)?;
+                normals.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                //the v coordinate is optional
+                let [u] = numbers(&arguments, line_number)?;
+                let v = numbers::<1>(arguments.get(1..).unwrap_or_default(), line_number)
+                    .map_or(0.0, |[v]| v);
+                uvs.push((u, v));
+            }
+            "f" => {
+                let mut corners = vec![];
+                for corner in &arguments {
+                    let mut parts = corner.split('/');
+                    let position = face_index(
+                        parts.next().unwrap_or_default(),
+                        positions.len(),
+                        line_number,
+                    )?;
+                    let uv = match parts.next() {
+                        None | Some("") => None,
+                        Some(uv) => Some(face_index(uv, uvs.len(), line_number)?),
+                    };
+                    let normal = match parts.next() {
+                        None | Some("") => None,
+                        Some(normal) => Some(face_index(normal, normals.len(), line_number)?),
+                    };
+                    corners.push((position, uv, normal));
+                }
+                if corners.len() < 3 {
+                    return Err(invalid(line_number, "faces need at least 3 corners"));
+                }
+
+                for pair in corners[1..].windows(2) {
+                    let triangle = [corners[0], pair[0], pair[1]];
+                    //only smooth or textured if every corner is
+                    let corner_uvs = triangle.map(|(_, uv, _)| uv);
+                    let corner_normals = triangle.map(|(_, _, normal)| normal);
+                    triangles.push(Triangle {
+                        positions: triangle.map(|(position, _, _)| position),
+                        normals: corner_normals
+                            .iter()
+                            .all(Option::is_some)
+                            .then(|| corner_normals.map(Option::unwrap_or_default)),
+                        uvs: corner_uvs
+                            .iter()
+                            .all(Option::is_some)
+                            .then(|| corner_uvs.map(Option::unwrap_or_default)),
+                        material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for library in &arguments {
+                    materials
+                        .libraries
+                        .extend(read_mtl(directory.join(library))?);
+                }
+            }
+            "usemtl" => material = materials.index(&arguments.join(" ")),
+            _ => {}
+        }
+    }
+
+    Ok(Mesh::new(
+        positions,
+        normals,
+        uvs,
+        triangles,
+        materials.used,
+    ))
+}
+
+///Reads a whole OBJ file as one mesh
+///
+/// # Errors
+/// If the file can't be read, a statement is malformed, or a library can't be read
+pub fn read_obj(path: impl AsRef<Path>) -> io::Result<Mesh> {
+    let path = path.as_ref();
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    parse_obj(BufReader::new(File::open(path)?), directory)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_obj;
+    use crate::primitives::{
+        collisions::{
+            mesh::{Mesh, Triangle},
+            Hittable,
+        },
+        materials::Material,
+        Point3,
+    };
+    use std::{fs, io, path::Path};
+
+    fn parse(obj: &str) -> io::Result<Mesh> {
+        parse_obj(obj.as_bytes(), Path::new(""))
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    fn positions(mesh: &Mesh) -> Vec<[usize; 3]> {
+        mesh.triangles()
+            .iter()
+            .map(|triangle| triangle.positions)
+            .collect()
+    }
+
+    #[test]
+    fn fan_triangulation() {
+        let pentagon = parse(&format!("{SQUARE}v -1 0.5 0\nf 1 2 3 4 5\n")).expect("valid OBJ");
+        assert_eq!(positions(&pentagon), [[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+    }
+
+    #[test]
+    fn negative_indices() {
+        //counting back from the last vertex read so far, not the end of the file
+        let mesh =
+            parse(&format!("{SQUARE}f -4 -3 -2 -1\nv 2 2 2\nf -1 -2 -3\n")).expect("valid OBJ");
+        assert_eq!(positions(&mesh), [[0, 1, 2], [0, 2, 3], [4, 3, 2]]);
+    }
+
+    #[test]
+    fn texture_coordinates_and_normals() {
+        let mesh = parse(&format!(
+            "{SQUARE}vt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 1\n\
+             f 1/1/1 2/2/1 3/3/1\n\
+             f 1//1 3//1 4//1\n\
+             f 1/1 3/3 4\n"
+        ))
+        .expect("valid OBJ");
+
+        let triangles = mesh.triangles();
+        assert_eq!(
+            triangles[0],
+            Triangle {
+                positions: [0, 1, 2],
+                normals: Some([0, 0, 0]),
+                uvs: Some([0, 1, 2]),
+                material: 0,
+            }
+        );
+        assert_eq!(
+            (triangles[1].uvs, triangles[1].normals),
+            (None, Some([0, 0, 0]))
+        );
+        //only textured if every corner is
+        assert_eq!((triangles[2].uvs, triangles[2].normals), (None, None));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse("v 0 0 0\nf 0 1 1\n").is_err(), "indices start at 1");
+        assert!(
+            parse(&format!("{SQUARE}f 1 2 5\n")).is_err(),
+            "past the end"
+        );
+        assert!(
+            parse(&format!("{SQUARE}f -5 1 2\n")).is_err(),
+            "before the start"
+        );
+        assert!(
+            parse(&format!("{SQUARE}f 1 2\n")).is_err(),
+            "not enough corners"
+        );
+        assert!(parse("v 0 0\n").is_err(), "not enough numbers");
+    }
+
+    #[test]
+    fn unknown_material_is_default() {
+        let mesh = parse(&format!(
+            "{SQUARE}usemtl missing\nf 1 2 3\nusemtl missing\nf 1 3 4\n"
+        ))
+        .expect("unknown materials aren't an error");
+        assert!(mesh
+            .triangles()
+            .iter()
+            .all(|triangle| triangle.material == 0));
+        assert_eq!(mesh.materials().len(), 1);
+    }
+
+    #[test]
+    fn materials_from_library() {
+        let directory = std::env::temp_dir().join(format!("rtow-obj-test-{}", std::process::id()));
+        fs::create_dir_all(&directory).expect("can make a temporary directory");
+        fs::write(
+            directory.join("leaves.mtl"),
+            "newmtl leaf\nKd 0.1 0.5 0.1\nd 0.25\nnewmtl bark\nKd 0.3 0.2 0.1\nTr 0\n",
+        )
+        .expect("can write a temporary file");
+
+        let mesh = parse_obj(
+            format!("mtllib leaves.mtl\n{SQUARE}f 1 2 3\nusemtl bark\nf 1 3 4\nusemtl leaf\nf 2 3 4\nusemtl bark\nf 1 2 4\n")
+                .as_bytes(),
+            &directory,
+        );
+        fs::remove_dir_all(&directory).expect("can clean up");
+        let mesh = mesh.expect("valid OBJ");
+
+        //the default, then each in the order they're first used
+        let used: Vec<usize> = mesh
+            .triangles()
+            .iter()
+            .map(|triangle| triangle.material)
+            .collect();
+        assert_eq!(used, [0, 1, 2, 1]);
+
+        let materials = mesh.materials();
+        assert!(matches!(materials[0], Material::Principled(_)));
+        assert!(matches!(materials[1], Material::Principled(_)));
+        let Material::Cutout(opacity, leaf) = materials[2] else {
+            panic!("{:?} should be cut out", materials[2]);
+        };
+        assert!((opacity.scalar(0.0, 0.0, Point3::default()) - 0.25).abs() < 1e-9);
+        assert!(matches!(leaf.as_ref(), Material::Principled(_)));
+    }
+}
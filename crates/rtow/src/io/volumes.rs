@@ -0,0 +1,164 @@
+//! Reads voxel grids for [`Heterogeneous`](crate::primitives::media::grid::Heterogeneous) media
+
+use crate::primitives::{media::grid::DensityGrid, Decimal};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+};
+
+///How each voxel is stored in a file
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum VoxelType {
+    ///0-255, read as 0-1
+    U8,
+    ///0-65535, read as 0-1
+    U16,
+    F32,
+    F64,
+}
+
+impl VoxelType {
+    const fn bytes(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 => 2,
+            Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+
+    fn decode(self, bytes: &[u8], big_endian: bool) -> Decimal {
+        macro_rules! number {
+            ($type:ty) => {{
+                let bytes = bytes.try_into().unwrap_or_default();
+                if big_endian {
+                    <$type>::from_be_bytes(bytes)
+                } else {
+                    <$type>::from_le_bytes(bytes)
+                }
+            }};
+        }
+
+        match self {
+            Self::U8 => Decimal::from(bytes[0]) / Decimal::from(u8::MAX),
+            Self::U16 => Decimal::from(number!(u16)) / Decimal::from(u16::MAX),
+            Self::F32 => Decimal::from(number!(f32)),
+            Self::F64 => number!(f64),
+        }
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_voxels(
+    mut reader: impl Read,
+    size: [usize; 3],
+    voxel_type: VoxelType,
+    big_endian: bool,
+) -> io::Result<DensityGrid> {
+    let count: usize = size.iter().product();
+    let mut bytes = vec![0; count * voxel_type.bytes()];
+    reader.read_exact(&mut bytes)?;
+
+    let values = bytes
+        .chunks_exact(voxel_type.bytes())
+        .map(|voxel| voxel_type.decode(voxel, big_endian))
+        .collect();
+    Ok(DensityGrid::new(size, values))
+}
+
+///Reads a headerless grid of little-endian voxels, with x changing fastest, then y, then z
+///
+/// # Errors
+/// If `reader` runs out before every voxel has been read
+pub fn read_raw(
+    reader: impl Read,
+    size: [usize; 3],
+    voxel_type: VoxelType,
+) -> io::Result<DensityGrid> {
+    read_voxels(reader, size, voxel_type, false)
+}
+
+///Reads a 3D NRRD file with the voxels in the same file, with `raw` encoding
+///
+/// # Errors
+/// If we fail to read from `reader`, the header is malformed, or it uses a feature we don't support (like compression or detached data)
+pub fn read_nrrd(mut reader: impl BufRead) -> io::Result<DensityGrid> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("NRRD") {
+        return Err(invalid("missing NRRD magic number"));
+    }
+
+    let mut size = None;
+    let mut voxel_type = None;
+    let mut big_endian = false;
+
+    //the header is a list of fields, ended by a blank line
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+        //key-value pairs use := instead of :, which we don't need
+        let Some((field, value)) = line.split_once(": ") else {
+            continue;
+        };
+
+        match field {
+            "dimension" if value != "3" => return Err(invalid("only 3D NRRD files are supported")),
+            "sizes" => {
+                let sizes = value
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| invalid("bad NRRD sizes"))?;
+                size = Some(
+                    <[usize; 3]>::try_from(sizes).map_err(|_| invalid("expected 3 NRRD sizes"))?,
+                );
+            }
+            "type" => {
+                voxel_type = Some(match value {
+                    "uchar" | "unsigned char" | "uint8" | "uint8_t" => VoxelType::U8,
+                    "ushort" | "unsigned short" | "unsigned short int" | "uint16" | "uint16_t" => {
+                        VoxelType::U16
+                    }
+                    "float" => VoxelType::F32,
+                    "double" => VoxelType::F64,
+                    _ => return Err(invalid("unsupported NRRD type")),
+                });
+            }
+            "encoding" if value != "raw" => {
+                return Err(invalid("only raw NRRD encoding is supported"));
+            }
+            "endian" => big_endian = value == "big",
+            "data file" | "datafile" => return Err(invalid("detached NRRD data is not supported")),
+            _ => {}
+        }
+    }
+
+    read_voxels(
+        reader,
+        size.ok_or_else(|| invalid("missing NRRD sizes"))?,
+        voxel_type.ok_or_else(|| invalid("missing NRRD type"))?,
+        big_endian,
+    )
+}
+
+///Reads an NRRD file
+///
+/// # Errors
+/// If the file can't be opened, or isn't a valid NRRD - see [`read_nrrd`]
+pub fn open_nrrd(path: impl AsRef<Path>) -> io::Result<DensityGrid> {
+    read_nrrd(BufReader::new(File::open(path)?))
+}
@@ -1,27 +1,99 @@
 #![warn(clippy::all, clippy::pedantic, clippy::nursery)] //add extra lints
-#![allow(clippy::cast_sign_loss, clippy::cast_precision_loss)]
+#![allow(
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::suboptimal_flops
+)]
 
 use crate::{
     io::images::ppm::PPMImage,
     primitives::{
         camera::Camera,
-        collisions::{sphere::Sphere, HittableList},
+        collisions::{csg::Csg, plane::Plane, sphere::Sphere, HittableList},
+        integrators::{
+            ambient_occlusion::AmbientOcclusion, aov::Aov, bidirectional::BidirectionalPathTracer,
+            debug::DebugView, direct::DirectLighting, path::PathTracer,
+            photon_mapping::PhotonMapper, whitted::Whitted, Integrator,
+        },
         materials::Material,
+        scene::Scene,
         Colour, Decimal, Vec3,
     },
 };
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
 use std::{fs::File, sync::Arc};
 
 pub mod io;
 pub mod primitives;
 
+const MAX_DEPTH: usize = 128;
+
+#[derive(Parser)]
+pub struct Args {
+    #[arg(short, long, value_enum, default_value_t = IntegratorChoice::Path)]
+    ///How to work out the light coming back along camera rays
+    pub integrator: IntegratorChoice,
+    #[arg(long)]
+    ///Also writes every AOV to its own PFM, like `out_albedo.pfm` - only the path tracer can render them
+    pub aovs: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum IntegratorChoice {
+    ///Path tracing, for final images
+    Path,
+    ///Bidirectional path tracing, for light through small openings and caustics
+    Bidirectional,
+    ///Photon mapping, for smooth caustics
+    Photons,
+    ///How enclosed each point is, without any lighting
+    AmbientOcclusion,
+    ///Lighting straight from lights, without any bounces
+    Direct,
+    ///Follows mirrors and glass, and lights everything else directly
+    Whitted,
+    ///Shading normals
+    Normals,
+    ///Surface coordinates
+    Uvs,
+    ///Distance from the camera
+    Depth,
+    ///A different colour for each material
+    MaterialId,
+    ///A heatmap of how many primitives each ray was tested against
+    Cost,
+}
+
+impl IntegratorChoice {
+    ///`camera` and `scene` are the ones the image is rendered with
+    fn integrator(self, camera: &Camera, scene: &Scene) -> Box<dyn Integrator> {
+        match self {
+            Self::Path => Box::new(PathTracer::new(MAX_DEPTH)),
+            Self::Bidirectional => {
+                Box::new(BidirectionalPathTracer::new(camera.clone(), MAX_DEPTH))
+            }
+            Self::Photons => Box::new(PhotonMapper::new(scene, 8, 100_000, 0.05, MAX_DEPTH)),
+            Self::AmbientOcclusion => Box::new(AmbientOcclusion::new(1.0)),
+            Self::Direct => Box::new(DirectLighting),
+            Self::Whitted => Box::new(Whitted::new(MAX_DEPTH)),
+            Self::Normals => Box::new(DebugView::Normals),
+            Self::Uvs => Box::new(DebugView::Uvs),
+            Self::Depth => Box::new(DebugView::Depth(10.0)),
+            Self::MaterialId => Box::new(DebugView::MaterialId),
+            Self::Cost => Box::new(DebugView::IntersectionCost(20.0)),
+        }
+    }
+}
+
 fn main() {
     const ASPECT_RATIO: Decimal = 16.0 / 9.0;
     const WIDTH: usize = 2160;
     const HEIGHT: usize = (WIDTH as Decimal / ASPECT_RATIO) as usize;
 
     const SAMPLES_PER_PIXEL: usize = 512;
-    const MAX_DEPTH: usize = 128;
+
+    let args = Args::parse();
 
     let look_from = Vec3::new(3.5, 3.0, 2.0);
     let look_at = Vec3::new(0.0, 0.0, -1.0);
@@ -34,15 +106,16 @@ fn main() {
         2.0,
         (look_from - look_at).magnitude(),
     );
-    let ground_mat = Material::LambertianDiffuse(Colour::new(0.8, 0.8, 0.0));
-    let centre_mat = Material::LambertianDiffuse(Colour::new(0.1, 0.2, 0.5));
-    let left_mat = Material::DielectricRefraction(1.5);
-    let right_mat = Material::MetalReflection(Colour::new(0.8, 0.6, 0.2), 0.0);
+    let ground_mat = Material::LambertianDiffuse(Colour::new(0.8, 0.8, 0.0).into());
+    let centre_mat = Material::LambertianDiffuse(Colour::new(0.1, 0.2, 0.5).into());
+    let left_mat = Material::DielectricRefraction(1.5.into());
+    let right_mat =
+        Material::MetalReflection(Colour::new(0.8, 0.6, 0.2).into(), Colour::splat(0.0).into());
 
     let mut world = HittableList::default();
-    world.add(Arc::new(Box::new(Sphere::new(
-        Vec3::new(0.0, -100.5, -1.0),
-        100.0,
+    world.add(Arc::new(Box::new(Plane::new(
+        Vec3::new(0.0, -0.5, 0.0),
+        Vec3::UP,
         ground_mat,
     ))));
     world.add(Arc::new(Box::new(Sphere::new(
@@ -50,15 +123,17 @@ fn main() {
         0.5,
         centre_mat,
     ))));
-    world.add(Arc::new(Box::new(Sphere::new(
-        Vec3::new(-1.0, 0.0, -1.0),
-        0.5,
-        left_mat,
-    ))));
-    world.add(Arc::new(Box::new(Sphere::new(
-        Vec3::new(-1.0, 0.0, -1.0),
-        -0.45, //inverse, so reverse reflections etc, so makes the left bubble hollow
-        left_mat,
+    world.add(Arc::new(Box::new(Csg::difference(
+        Arc::new(Box::new(Sphere::new(
+            Vec3::new(-1.0, 0.0, -1.0),
+            0.5,
+            left_mat.clone(),
+        ))),
+        Arc::new(Box::new(Sphere::new(
+            Vec3::new(-1.0, 0.0, -1.0),
+            0.45,
+            left_mat,
+        ))),
     ))));
     world.add(Arc::new(Box::new(Sphere::new(
         Vec3::new(1.0, 0.0, -1.0),
@@ -66,12 +141,39 @@ fn main() {
         right_mat,
     ))));
 
+    let scene = Scene::new(world);
+    let integrator = args.integrator.integrator(&cam, &scene);
+    let aovs: &[Aov] = if args.aovs { integrator.aovs() } else { &[] };
+    if args.aovs && aovs.is_empty() {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "the {} integrator can't render AOVs",
+                    args.integrator
+                        .to_possible_value()
+                        .expect("every integrator is a possible value")
+                        .get_name()
+                ),
+            )
+            .exit();
+    }
+
     let mut image: PPMImage<Vec3> = PPMImage::new(WIDTH, HEIGHT);
-    image.fill(&cam, &world, SAMPLES_PER_PIXEL, MAX_DEPTH);
+    let buffers = image.fill_with_aovs(&cam, &scene, integrator.as_ref(), SAMPLES_PER_PIXEL, aovs);
     image
         .write(
             File::create("out.ppm").expect("unable to get file"),
             SAMPLES_PER_PIXEL,
         )
         .expect("unable to write to file");
+
+    for (aov, buffer) in aovs.iter().zip(buffers) {
+        buffer
+            .write_pfm(
+                File::create(format!("out_{}.pfm", aov.name())).expect("unable to get file"),
+                SAMPLES_PER_PIXEL,
+            )
+            .expect("unable to write to file");
+    }
 }
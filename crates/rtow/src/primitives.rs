@@ -1,15 +1,26 @@
 pub mod camera;
 pub mod collisions;
+pub mod environment;
+pub mod integrators;
+pub mod lights;
+mod mat4;
 pub mod materials;
+pub mod media;
 mod ray;
+pub mod scene;
+pub mod spectrum;
+pub mod textures;
+mod transform;
 mod vec3;
 
-pub use ray::Ray;
+pub use mat4::Mat4;
+pub use ray::{Ray, RayDifferentials};
+pub use transform::Transform;
 pub use vec3::Vec3;
 
 pub type Decimal = f64;
 pub mod decimal_consts {
-    pub use std::f32::consts::*;
+    pub use std::f64::consts::*;
 }
 
 pub type Point3 = Vec3;
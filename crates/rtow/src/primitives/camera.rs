@@ -1,7 +1,11 @@
-use crate::primitives::{Decimal, Point3, Ray, Vec3};
+use crate::primitives::{
+    decimal_consts::PI, media::Medium, spectrum::Wavelengths, Decimal, Point3, Ray,
+    RayDifferentials, Vec3,
+};
 use rand::rngs::ThreadRng;
+use std::sync::Arc;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Camera {
     origin: Point3,
     lower_left_corner: Point3,
@@ -9,8 +13,14 @@ pub struct Camera {
     vertical: Vec3,
     u: Vec3,
     v: Vec3,
+    ///pointing backwards, away from what the camera is looking at
     w: Vec3,
     lens_radius: Decimal,
+    focus_distance: Decimal,
+    ///the medium the camera is in, which fills all of the space outside of other volumes
+    medium: Option<Arc<dyn Medium>>,
+    ///whether rays carry wavelengths rather than RGB
+    spectral: bool,
 }
 
 impl Camera {
@@ -34,29 +44,155 @@ impl Camera {
         let v = w.cross(u);
 
         let origin = look_from;
-        let horizontal = viewport_width * u;
-        let vertical = viewport_width * v;
+        let horizontal = focus_distance * viewport_width * u;
+        let vertical = focus_distance * viewport_height * v;
 
         Self {
             origin,
-            lower_left_corner: origin - horizontal / 2.0 - vertical / 2.0 - w,
+            lower_left_corner: origin - horizontal / 2.0 - vertical / 2.0 - focus_distance * w,
             horizontal,
             vertical,
             lens_radius: aperture / 2.0,
+            focus_distance,
             u,
             v,
             w,
+            medium: None,
+            spectral: false,
         }
     }
 
+    ///Fills the whole scene with `medium`, for fog.
+    ///
+    ///Rays which don't hit anything leave the fog straight away, otherwise it would block out all of the light from the sky.
+    #[must_use]
+    pub fn with_medium(mut self, medium: Arc<dyn Medium>) -> Self {
+        self.medium = Some(medium);
+        self
+    }
+    #[must_use]
+    pub fn medium(&self) -> Option<&dyn Medium> {
+        self.medium.as_deref()
+    }
+
+    ///Makes rays which trace random wavelengths rather than RGB, for dispersion and spectral lights - it's slower and noisier
+    #[must_use]
+    pub const fn with_spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+        self
+    }
+    #[must_use]
+    pub const fn is_spectral(&self) -> bool {
+        self.spectral
+    }
+
+    fn with_wavelengths(&self, ray: Ray, rng: &mut ThreadRng) -> Ray {
+        if self.spectral {
+            ray.with_wavelengths(Wavelengths::sample(rng))
+        } else {
+            ray
+        }
+    }
+
+    ///Uniformly distributed over the lens, so [`Camera::importance`] is right
+    fn lens_offset(&self, rng: &mut ThreadRng) -> Vec3 {
+        let radius = self.lens_radius * Vec3::random_in_unit_disk(rng);
+        self.u * radius.x() + self.v * radius.y()
+    }
+
+    ///A uniformly distributed point on the lens, for connecting points in the scene to the camera
+    #[must_use]
+    pub fn sample_lens(&self, rng: &mut ThreadRng) -> Point3 {
+        self.origin + self.lens_offset(rng)
+    }
+    ///The area of the lens, or 1 for a pinhole camera where every ray starts from the same point
+    #[must_use]
+    pub fn lens_area(&self) -> Decimal {
+        if self.lens_radius > 0.0 {
+            PI * self.lens_radius * self.lens_radius
+        } else {
+            1.0
+        }
+    }
+    ///The direction the camera is looking in
+    #[must_use]
+    pub fn forward(&self) -> Vec3 {
+        -self.w
+    }
+    ///The area of the image one unit in front of the lens
+    fn image_area(&self) -> Decimal {
+        self.horizontal.magnitude() * self.vertical.magnitude() / self.focus_distance.powi(2)
+    }
+
+    ///Where a ray leaving the lens at `lens_point` along `direction` lands on the image (as the `s` and `t` given to [`Camera::get_ray`]), and its importance - how much the light coming back along it adds to the image.
+    ///
+    ///The importance adds up to 1 over the whole image. Returns [`None`] if the ray misses the image.
+    #[must_use]
+    pub fn importance(
+        &self,
+        lens_point: Point3,
+        direction: Vec3,
+    ) -> Option<(Decimal, Decimal, Decimal)> {
+        let direction = direction.unit();
+        let cos_theta = -direction.dot(self.w);
+        if cos_theta <= 0.0 {
+            return None;
+        }
+
+        //every ray through the same point on the plane in focus lands on the same point on the image
+        let in_focus = lens_point + direction * (self.focus_distance / cos_theta);
+        let offset = in_focus - self.lower_left_corner;
+        let s = offset.dot(self.horizontal) / self.horizontal.magnitude_squared();
+        let t = offset.dot(self.vertical) / self.vertical.magnitude_squared();
+        if !(0.0..=1.0).contains(&s) || !(0.0..=1.0).contains(&t) {
+            return None;
+        }
+
+        let importance = 1.0 / (self.image_area() * self.lens_area() * cos_theta.powi(4));
+        Some((s, t, importance))
+    }
+
+    ///The probability density (with respect to solid angle) of a ray across the image from `lens_point` going along `direction`
+    #[must_use]
+    pub fn pdf_direction(&self, lens_point: Point3, direction: Vec3) -> Decimal {
+        self.importance(lens_point, direction).map_or(0.0, |_| {
+            let cos_theta = -direction.unit().dot(self.w);
+            1.0 / (self.image_area() * cos_theta.powi(3))
+        })
+    }
+
     #[must_use]
     pub fn get_ray(&self, s: Decimal, t: Decimal, rng: &mut ThreadRng) -> Ray {
-        let radius = self.lens_radius * Vec3::random_in_unit_sphere(rng);
-        let offset = self.u * radius.x() + self.v * radius.y();
+        let offset = self.lens_offset(rng);
+
+        let ray = Ray::new(
+            self.origin + offset,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
+        );
+        self.with_wavelengths(ray, rng)
+    }
+
+    ///Like [`Camera::get_ray`], but with [`RayDifferentials`] for the neighbouring pixels, which are `pixel_width` and `pixel_height` away in `s` and `t`
+    #[must_use]
+    pub fn get_ray_with_differentials(
+        &self,
+        s: Decimal,
+        t: Decimal,
+        pixel_width: Decimal,
+        pixel_height: Decimal,
+        rng: &mut ThreadRng,
+    ) -> Ray {
+        let offset = self.lens_offset(rng);
+        let origin = self.origin + offset;
+        let direction =
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset;
 
-        Ray::new(
-            self.origin,
-            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
-        )
+        let ray = Ray::new(origin, direction).with_differentials(RayDifferentials {
+            x_origin: origin,
+            x_direction: direction + pixel_width * self.horizontal,
+            y_origin: origin,
+            y_direction: direction + pixel_height * self.vertical,
+        });
+        self.with_wavelengths(ray, rng)
     }
 }
@@ -1,14 +1,195 @@
+pub mod cone;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod disk;
+pub mod instance;
+pub mod mesh;
+pub mod paraboloid;
+pub mod plane;
+pub mod quad;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
 
+mod aabb;
 mod hit_record;
+mod solvers;
 
-use crate::primitives::{Decimal, Ray};
+use crate::primitives::{materials::Material, Decimal, Point3, Ray, Vec3};
+pub use aabb::Aabb;
 pub use hit_record::HitRecord;
-use std::fmt::Debug;
+use rand::rngs::ThreadRng;
+use std::{cell::Cell, fmt::Debug};
 
 mod hittable_list;
 pub use hittable_list::HittableList;
 
+thread_local! {
+    ///how many primitives rays on this thread have been tested against
+    static PRIMITIVE_TESTS: Cell<usize> = const { Cell::new(0) };
+}
+
+///Counts a ray being tested against a single primitive, like a sphere or a triangle
+pub(crate) fn count_primitive_test() {
+    PRIMITIVE_TESTS.set(PRIMITIVE_TESTS.get().wrapping_add(1));
+}
+
+///How many primitives rays on this thread have been tested against so far - the cost of intersecting them, without timing anything
+#[must_use]
+pub fn primitive_tests() -> usize {
+    PRIMITIVE_TESTS.get()
+}
+
 pub trait Hittable: Debug + Send + Sync {
-    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord>;
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>>;
+    ///Like [`Hittable::hit`], but also gives the index of the object inside this which was hit - only lists have more than one
+    fn hit_object(
+        &self,
+        ray: Ray,
+        t_min: Decimal,
+        t_max: Decimal,
+    ) -> Option<(usize, HitRecord<'_>)> {
+        self.hit(ray, t_min, t_max).map(|hit| (0, hit))
+    }
+    ///The box that the object fits inside, or [`None`] if it is unbounded (eg. an infinite plane)
+    fn bounding_box(&self) -> Option<Aabb>;
+    ///The materials on the surfaces of this, for giving them IDs
+    fn materials(&self) -> Vec<&Material>;
+}
+
+///Surfaces which can be uniformly sampled by area, so they can be used as area lights
+pub trait Sampleable: Hittable {
+    fn area(&self) -> Decimal;
+    ///A uniformly distributed point on the surface, and the outward unit normal at that point
+    fn sample_point(&self, rng: &mut ThreadRng) -> (Point3, Vec3);
+
+    ///A direction from `origin` towards a uniformly distributed point on the surface
+    fn sample_direction(&self, origin: Point3, rng: &mut ThreadRng) -> Vec3 {
+        self.sample_point(rng).0 - origin
+    }
+    ///The probability density (with respect to solid angle) of [`Self::sample_direction`] giving `direction` from `origin`
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> Decimal {
+        self.hit(Ray::new(origin, direction), 0.00001, Decimal::INFINITY)
+            .map_or(0.0, |hit| {
+                let distance_squared = hit.time * hit.time * direction.magnitude_squared();
+                let cosine = (direction.dot(hit.normal) / direction.magnitude()).abs();
+                distance_squared / (cosine * self.area())
+            })
+    }
+}
+
+///A section of a ray which is inside a [`Solid`], with the surfaces where it goes in and comes back out
+#[derive(Debug, Copy, Clone)]
+pub struct Span<'a> {
+    pub enter: HitRecord<'a>,
+    pub exit: HitRecord<'a>,
+}
+
+///Closed objects with a well-defined inside, which can be combined with [`Csg`](csg::Csg)
+pub trait Solid: Hittable {
+    ///Every span along the whole ray (ignoring the ray's origin) that is inside the object, in order.
+    ///
+    ///By default, this walks along the ray collecting every surface - entering on front faces and leaving on back faces.
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        //nudge to get past the surface we just hit
+        const STEP: Decimal = 1e-7;
+
+        let mut spans = vec![];
+        let mut enter: Option<HitRecord<'_>> = None;
+        let mut t_min = Decimal::NEG_INFINITY;
+
+        while let Some(hit) = self.hit(ray, t_min, Decimal::INFINITY) {
+            t_min = hit.time + STEP;
+            match enter {
+                None if hit.front_face => enter = Some(hit),
+                Some(entered) if !hit.front_face => {
+                    spans.push(Span {
+                        enter: entered,
+                        exit: hit,
+                    });
+                    enter = None;
+                }
+                //a grazing hit or a precision issue got us out of sync, so skip it
+                _ => {}
+            }
+        }
+
+        spans
+    }
+}
+
+///Checks shared by the tests for each shape
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::Hittable;
+    use crate::primitives::{materials::Material, Decimal, Point3, Ray, Vec3};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    ///How far a hit point can be from the exact surface, and its normal from the exact direction
+    pub const TOLERANCE: Decimal = 1e-6;
+
+    pub fn material() -> Material {
+        Material::LambertianDiffuse(Vec3::splat(0.5).into())
+    }
+
+    ///Rays from all around a shape near the origin, through points close to it - the same ones every time for the same `seed`
+    pub fn random_rays(seed: u64) -> impl Iterator<Item = Ray> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut point = move |size: Decimal| {
+            Vec3::new(
+                rng.gen_range(-size..size),
+                rng.gen_range(-size..size),
+                rng.gen_range(-size..size),
+            )
+        };
+        (0..10_000).map(move |_| {
+            let origin = point(4.0);
+            Ray::new(origin, point(1.5) - origin)
+        })
+    }
+
+    ///Fires `rays` at `shape`, and checks that every hit is on its surface, inside its bounding box, and has a normal along the surface's gradient.
+    ///
+    ///`surface` gives how far off the surface a point is (0 on it), and the gradient there - a bit like a signed distance. Returns the points that were hit.
+    pub fn assert_hits_on_surface(
+        shape: &dyn Hittable,
+        rays: impl IntoIterator<Item = Ray>,
+        surface: impl Fn(Point3) -> (Decimal, Vec3),
+    ) -> Vec<Point3> {
+        let bounds = shape.bounding_box().expect("shapes are bounded");
+        let inside = |point: Point3| {
+            (0..3).all(|axis| {
+                (bounds.min()[axis] - TOLERANCE..=bounds.max()[axis] + TOLERANCE)
+                    .contains(&point[axis])
+            })
+        };
+
+        let mut points = vec![];
+        for ray in rays {
+            let Some(hit) = shape.hit(ray, 0.00001, Decimal::INFINITY) else {
+                continue;
+            };
+            let (error, gradient) = surface(hit.point);
+            assert!(
+                error.abs() < TOLERANCE,
+                "{ray:?} hit {:?}, which is {error} off the surface",
+                hit.point
+            );
+            assert!(
+                inside(hit.point),
+                "{ray:?} hit {:?}, which is outside {bounds:?}",
+                hit.point
+            );
+            let across = hit.normal.unit().cross(gradient.unit()).magnitude();
+            assert!(
+                across < 10.0 * TOLERANCE,
+                "{ray:?} hit {:?} with the normal {:?}, but the surface is along {gradient:?}",
+                hit.point,
+                hit.normal
+            );
+            points.push(hit.point);
+        }
+        points
+    }
 }
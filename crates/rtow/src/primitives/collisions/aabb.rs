@@ -0,0 +1,129 @@
+use crate::primitives::{Decimal, Point3, Ray, Transform, Vec3};
+
+///An axis-aligned bounding box
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Aabb {
+    min: Point3,
+    max: Point3,
+}
+
+impl Aabb {
+    ///Smallest thickness we allow on any axis, so flat objects still get hit
+    const MIN_THICKNESS: Decimal = 0.0001;
+
+    ///Makes a box from any two opposite corners
+    #[must_use]
+    pub fn new(a: Point3, b: Point3) -> Self {
+        Self {
+            min: Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+        .padded()
+    }
+    ///Makes the smallest box containing all of the points
+    ///
+    ///Returns [`None`] if there are no points
+    #[must_use]
+    pub fn from_points(points: impl IntoIterator<Item = Point3>) -> Option<Self> {
+        points
+            .into_iter()
+            .map(|p| Self::new(p, p))
+            .reduce(|acc, p| acc.surrounding(p))
+    }
+
+    #[must_use]
+    pub const fn min(&self) -> Point3 {
+        self.min
+    }
+    #[must_use]
+    pub const fn max(&self) -> Point3 {
+        self.max
+    }
+    #[must_use]
+    pub fn centre(&self) -> Point3 {
+        (self.min + self.max) / 2.0
+    }
+    #[must_use]
+    pub fn diagonal(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    fn padded(mut self) -> Self {
+        for axis in 0..3 {
+            if self.max[axis] - self.min[axis] < Self::MIN_THICKNESS {
+                self.min[axis] -= Self::MIN_THICKNESS / 2.0;
+                self.max[axis] += Self::MIN_THICKNESS / 2.0;
+            }
+        }
+        self
+    }
+
+    #[must_use]
+    pub fn surrounding(&self, other: Self) -> Self {
+        Self::new(
+            Point3::new(
+                self.min.x().min(other.min.x()),
+                self.min.y().min(other.min.y()),
+                self.min.z().min(other.min.z()),
+            ),
+            Point3::new(
+                self.max.x().max(other.max.x()),
+                self.max.y().max(other.max.y()),
+                self.max.z().max(other.max.z()),
+            ),
+        )
+    }
+
+    ///The box around this one after it has been transformed
+    #[must_use]
+    pub fn transformed(&self, transform: &Transform) -> Self {
+        let corner = |i: usize| {
+            let pick = |bit: usize, axis: usize| {
+                if i & bit == 0 {
+                    self.min[axis]
+                } else {
+                    self.max[axis]
+                }
+            };
+            transform.point(Point3::new(pick(1, 0), pick(2, 1), pick(4, 2)))
+        };
+
+        (1..8).fold(Self::new(corner(0), corner(0)), |acc, i| {
+            acc.surrounding(Self::new(corner(i), corner(i)))
+        })
+    }
+
+    ///Slab test, to see whether the ray goes through the box between `t_min` and `t_max`
+    #[must_use]
+    pub fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> bool {
+        self.interval(ray, t_min, t_max).is_some()
+    }
+    ///The section of the ray between `t_min` and `t_max` that is inside the box, if there is any
+    #[must_use]
+    pub fn interval(
+        &self,
+        ray: Ray,
+        mut t_min: Decimal,
+        mut t_max: Decimal,
+    ) -> Option<(Decimal, Decimal)> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let mut t_0 = (self.min[axis] - origin[axis]) * inv_d;
+            let mut t_1 = (self.max[axis] - origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t_0, &mut t_1);
+            }
+
+            t_min = t_min.max(t_0);
+            t_max = t_max.min(t_1);
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+}
@@ -0,0 +1,214 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, solvers, Aabb, HitRecord, Hittable, Solid},
+    decimal_consts::{PI, TAU},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+
+///A cone with its base centred on `base`, and its tip `height` up the y-axis - rotate it with an [`Instance`](super::instance::Instance).
+///
+///UVs on the side go around (`u`) and up (`v`), and UVs on the base are the x and z positions mapped to 0-1.
+#[derive(Clone, Debug)]
+pub struct Cone {
+    base: Point3,
+    radius: Decimal,
+    height: Decimal,
+    capped: bool,
+    material: Material,
+}
+
+impl Cone {
+    ///A closed cone, with a disk on the base
+    #[must_use]
+    pub const fn new(base: Point3, radius: Decimal, height: Decimal, material: Material) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+            capped: true,
+            material,
+        }
+    }
+    ///An open cone, with no base - this isn't closed, so it shouldn't be used as a [`Solid`]
+    #[must_use]
+    pub const fn uncapped(
+        base: Point3,
+        radius: Decimal,
+        height: Decimal,
+        material: Material,
+    ) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+            capped: false,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let local = Ray::new(ray.origin() - self.base, ray.direction());
+        let (origin, direction) = (local.origin(), local.direction());
+
+        //x^2 + z^2 = k (h - y)^2
+        let k = (self.radius / self.height).powi(2);
+        let below_tip = self.height - origin.y();
+        let side = solvers::quadratic(
+            direction.x() * direction.x() + direction.z() * direction.z()
+                - k * direction.y() * direction.y(),
+            2.0 * (origin.x() * direction.x()
+                + origin.z() * direction.z()
+                + k * below_tip * direction.y()),
+            origin.x() * origin.x() + origin.z() * origin.z() - k * below_tip * below_tip,
+        )
+        .into_iter()
+        .find(|&t| {
+            //the equation also describes the mirrored cone above the tip, so we need to check the height
+            (t_min..=t_max).contains(&t) && (0.0..=self.height).contains(&local.at(t).y())
+        });
+
+        let cap = if self.capped {
+            solvers::horizontal_disk(local, 0.0, self.radius, t_min, side.unwrap_or(t_max))
+        } else {
+            None
+        };
+
+        if let Some(t) = cap {
+            let point = local.at(t);
+            return Some(
+                HitRecord::new(t, ray, |_| -Vec3::UP, &self.material)
+                    .with_uv(
+                        0.5 + point.x() / (2.0 * self.radius),
+                        0.5 + point.z() / (2.0 * self.radius),
+                    )
+                    .with_derivatives(
+                        Vec3::new(2.0 * self.radius, 0.0, 0.0),
+                        Vec3::new(0.0, 0.0, 2.0 * self.radius),
+                    ),
+            );
+        }
+
+        let t = side?;
+        let point = local.at(t);
+        let normal = Vec3::new(point.x(), k * (self.height - point.y()), point.z());
+        let normal = if normal.near_zero() {
+            //at the very tip
+            Vec3::UP
+        } else {
+            normal.unit()
+        };
+        let u = (point.z().atan2(point.x()) + PI) / TAU;
+        //going up the side moves in towards the tip
+        let ring = point.x().hypot(point.z()).max(1e-12);
+
+        Some(
+            HitRecord::new(t, ray, |_| normal, &self.material)
+                .with_uv(u, point.y() / self.height)
+                .with_derivatives(
+                    TAU * Vec3::new(-point.z(), 0.0, point.x()),
+                    Vec3::new(
+                        -self.radius * point.x() / ring,
+                        self.height,
+                        -self.radius * point.z() / ring,
+                    ),
+                ),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.base - Vec3::new(self.radius, 0.0, self.radius),
+            self.base + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Solid for Cone {}
+
+#[cfg(test)]
+mod tests {
+    use super::Cone;
+    use crate::primitives::{
+        collisions::{
+            tests::{assert_hits_on_surface, material, random_rays, TOLERANCE},
+            Hittable,
+        },
+        Decimal, Point3, Ray, Vec3,
+    };
+
+    ///The side of a cone with a base of radius 1 at y = -1, and its tip at y = 1
+    fn side(point: Point3) -> (Decimal, Vec3) {
+        let ring = point.x().hypot(point.z());
+        (
+            ring - (1.0 - point.y()) / 2.0,
+            Vec3::new(point.x(), ring / 2.0, point.z()),
+        )
+    }
+
+    ///[`side`], with the base
+    fn closed(point: Point3) -> (Decimal, Vec3) {
+        let (side, gradient) = side(point);
+        let base = -1.0 - point.y();
+        if side > base {
+            (side, gradient)
+        } else {
+            (base, Vec3::new(0.0, -1.0, 0.0))
+        }
+    }
+
+    ///Rays which only just touch the cone, or go alongside its axis - there's no normal at the tip, so it's left out
+    fn grazing() -> [Ray; 3] {
+        [
+            //tangent to the side where it's 1/2 wide
+            Ray::new(Point3::new(0.5, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)),
+            //through the tip and along the side
+            Ray::new(Point3::new(-2.0, 5.0, 0.0), Vec3::new(1.0, -2.0, 0.0)),
+            //parallel to the axis
+            Ray::new(Point3::new(0.3, -5.0, 0.2), Vec3::new(0.0, 1.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn open_hits_on_surface() {
+        let cone = Cone::uncapped(Point3::new(0.0, -1.0, 0.0), 1.0, 2.0, material());
+        let hits = assert_hits_on_surface(&cone, random_rays(3).chain(grazing()), side);
+        assert!(hits.len() > 1000, "only {} rays hit", hits.len());
+
+        //down the axis hits the tip
+        let axis = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = cone
+            .hit(axis, 0.00001, Decimal::INFINITY)
+            .expect("the axis goes through the tip");
+        assert!((hit.point - Point3::new(0.0, 1.0, 0.0)).magnitude() < TOLERANCE);
+    }
+
+    #[test]
+    fn closed_hits_on_surface() {
+        let cone = Cone::new(Point3::new(0.0, -1.0, 0.0), 1.0, 2.0, material());
+        let hits = assert_hits_on_surface(&cone, random_rays(4).chain(grazing()), closed);
+        let on_base = hits
+            .iter()
+            .filter(|point| (point.y() + 1.0).abs() < TOLERANCE)
+            .count();
+        assert!(
+            on_base > 500 && hits.len() - on_base > 500,
+            "{on_base} of {} hits were on the base",
+            hits.len()
+        );
+
+        //up the axis hits the middle of the base
+        let axis = Ray::new(Point3::new(0.0, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+        let hit = cone
+            .hit(axis, 0.00001, Decimal::INFINITY)
+            .expect("the axis goes through the base");
+        assert!((hit.point - Point3::new(0.0, -1.0, 0.0)).magnitude() < TOLERANCE);
+        assert!((hit.normal - Vec3::new(0.0, -1.0, 0.0)).magnitude() < TOLERANCE);
+    }
+}
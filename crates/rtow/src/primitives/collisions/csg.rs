@@ -0,0 +1,132 @@
+use crate::primitives::{
+    collisions::{Aabb, HitRecord, Hittable, Solid, Span},
+    materials::Material,
+    Decimal, Ray,
+};
+use std::sync::Arc;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Operation {
+    ///inside either
+    Union,
+    ///inside both
+    Intersection,
+    ///inside the left, but not the right
+    Difference,
+}
+
+impl Operation {
+    const fn inside(self, left: bool, right: bool) -> bool {
+        match self {
+            Self::Union => left || right,
+            Self::Intersection => left && right,
+            Self::Difference => left && !right,
+        }
+    }
+}
+
+///Constructive solid geometry - combines two [`Solid`]s by working out where along each ray we are inside the result.
+///
+///Surfaces keep the material of the solid they came from, so a bored out hole has the material of the cutter.
+#[derive(Debug, Clone)]
+pub struct Csg {
+    left: Arc<Box<dyn Solid>>,
+    right: Arc<Box<dyn Solid>>,
+    operation: Operation,
+}
+
+impl Csg {
+    #[must_use]
+    pub const fn new(
+        left: Arc<Box<dyn Solid>>,
+        right: Arc<Box<dyn Solid>>,
+        operation: Operation,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            operation,
+        }
+    }
+    #[must_use]
+    pub const fn union(left: Arc<Box<dyn Solid>>, right: Arc<Box<dyn Solid>>) -> Self {
+        Self::new(left, right, Operation::Union)
+    }
+    #[must_use]
+    pub const fn intersection(left: Arc<Box<dyn Solid>>, right: Arc<Box<dyn Solid>>) -> Self {
+        Self::new(left, right, Operation::Intersection)
+    }
+    #[must_use]
+    pub const fn difference(left: Arc<Box<dyn Solid>>, right: Arc<Box<dyn Solid>>) -> Self {
+        Self::new(left, right, Operation::Difference)
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        self.spans(ray)
+            .into_iter()
+            .flat_map(|Span { enter, exit }| [enter, exit])
+            .find(|hit| hit.time >= t_min && hit.time <= t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let left = self.left.bounding_box();
+        match self.operation {
+            Operation::Union => Some(left?.surrounding(self.right.bounding_box()?)),
+            //the result can't be any bigger than the left side
+            Operation::Intersection | Operation::Difference => left,
+        }
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        [self.left.materials(), self.right.materials()].concat()
+    }
+}
+
+impl Solid for Csg {
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        //every surface along the ray, marked with which side it came from and whether it goes in or out
+        let mut boundaries: Vec<(HitRecord, bool, bool)> = self
+            .left
+            .spans(ray)
+            .into_iter()
+            .flat_map(|Span { enter, exit }| [(enter, true, true), (exit, true, false)])
+            .chain(
+                self.right
+                    .spans(ray)
+                    .into_iter()
+                    .flat_map(|Span { enter, exit }| [(enter, false, true), (exit, false, false)]),
+            )
+            .collect();
+        boundaries.sort_by(|(a, ..), (b, ..)| a.time.total_cmp(&b.time));
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut entered: Option<HitRecord<'_>> = None;
+        let mut spans = vec![];
+
+        for (mut hit, is_left, entering) in boundaries {
+            if is_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+            }
+
+            if !is_left && self.operation == Operation::Difference {
+                //the cut out solid is turned inside out
+                hit.front_face = !hit.front_face;
+            }
+
+            match (entered, self.operation.inside(in_left, in_right)) {
+                (None, true) => entered = Some(hit),
+                (Some(enter), false) => {
+                    spans.push(Span { enter, exit: hit });
+                    entered = None;
+                }
+                _ => {}
+            }
+        }
+
+        spans
+    }
+}
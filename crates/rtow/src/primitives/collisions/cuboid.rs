@@ -0,0 +1,89 @@
+use crate::primitives::{
+    collisions::{quad::Quad, Aabb, HitRecord, Hittable, HittableList, Solid},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+use std::sync::Arc;
+
+///An axis-aligned box, made out of six [`Quad`]s - rotate it with an [`Instance`](super::instance::Instance).
+///
+///Each face has its own UV coordinates from 0-1.
+#[derive(Clone, Debug)]
+pub struct Cuboid {
+    sides: HittableList,
+    bounding_box: Aabb,
+}
+
+impl Cuboid {
+    ///Makes a box from any two opposite corners
+    #[must_use]
+    pub fn new(a: Point3, b: Point3, material: Material) -> Self {
+        let min = Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z()));
+        let max = Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z()));
+
+        let dx = Vec3::new(max.x() - min.x(), 0.0, 0.0);
+        let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
+        let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
+
+        let mut sides = HittableList::default();
+        for side in [
+            Quad::new(
+                Point3::new(min.x(), min.y(), max.z()),
+                dx,
+                dy,
+                material.clone(),
+            ), //front
+            Quad::new(
+                Point3::new(max.x(), min.y(), max.z()),
+                -dz,
+                dy,
+                material.clone(),
+            ), //right
+            Quad::new(
+                Point3::new(max.x(), min.y(), min.z()),
+                -dx,
+                dy,
+                material.clone(),
+            ), //back
+            Quad::new(
+                Point3::new(min.x(), min.y(), min.z()),
+                dz,
+                dy,
+                material.clone(),
+            ), //left
+            Quad::new(
+                Point3::new(min.x(), max.y(), max.z()),
+                dx,
+                -dz,
+                material.clone(),
+            ), //top
+            Quad::new(Point3::new(min.x(), min.y(), min.z()), dx, dz, material), //bottom
+        ] {
+            sides.add(Arc::new(Box::new(side)));
+        }
+
+        Self {
+            sides,
+            bounding_box: Aabb::new(min, max),
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        if !self.bounding_box.hit(ray, t_min, t_max) {
+            return None;
+        }
+        self.sides.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounding_box)
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        self.sides.materials()
+    }
+}
+
+impl Solid for Cuboid {}
@@ -0,0 +1,213 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, solvers, Aabb, HitRecord, Hittable, Solid},
+    decimal_consts::{PI, TAU},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+
+///A cylinder going up the y-axis from `base` - rotate it with an [`Instance`](super::instance::Instance).
+///
+///UVs on the side go around (`u`) and up (`v`), and UVs on the caps are the x and z positions mapped to 0-1.
+#[derive(Clone, Debug)]
+pub struct Cylinder {
+    base: Point3,
+    radius: Decimal,
+    height: Decimal,
+    capped: bool,
+    material: Material,
+}
+
+impl Cylinder {
+    ///A closed cylinder, with disks on both ends
+    #[must_use]
+    pub const fn new(base: Point3, radius: Decimal, height: Decimal, material: Material) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+            capped: true,
+            material,
+        }
+    }
+    ///An open tube, with no caps - this isn't closed, so it shouldn't be used as a [`Solid`]
+    #[must_use]
+    pub const fn uncapped(
+        base: Point3,
+        radius: Decimal,
+        height: Decimal,
+        material: Material,
+    ) -> Self {
+        Self {
+            base,
+            radius,
+            height,
+            capped: false,
+            material,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let local = Ray::new(ray.origin() - self.base, ray.direction());
+        let (origin, direction) = (local.origin(), local.direction());
+
+        let side = solvers::quadratic(
+            direction.x() * direction.x() + direction.z() * direction.z(),
+            2.0 * (origin.x() * direction.x() + origin.z() * direction.z()),
+            origin.x() * origin.x() + origin.z() * origin.z() - self.radius * self.radius,
+        )
+        .into_iter()
+        .find(|&t| (t_min..=t_max).contains(&t) && (0.0..=self.height).contains(&local.at(t).y()));
+
+        let cap = if self.capped {
+            let max = side.unwrap_or(t_max);
+            let bottom = solvers::horizontal_disk(local, 0.0, self.radius, t_min, max);
+            let top = solvers::horizontal_disk(local, self.height, self.radius, t_min, max);
+            match (bottom, top) {
+                (Some(b), Some(t)) => Some(b.min(t)),
+                (b, t) => b.or(t),
+            }
+        } else {
+            None
+        };
+
+        if let Some(t) = cap {
+            let point = local.at(t);
+            let normal = if point.y() > self.height / 2.0 {
+                Vec3::UP
+            } else {
+                -Vec3::UP
+            };
+            return Some(
+                HitRecord::new(t, ray, |_| normal, &self.material)
+                    .with_uv(
+                        0.5 + point.x() / (2.0 * self.radius),
+                        0.5 + point.z() / (2.0 * self.radius),
+                    )
+                    .with_derivatives(
+                        Vec3::new(2.0 * self.radius, 0.0, 0.0),
+                        Vec3::new(0.0, 0.0, 2.0 * self.radius),
+                    ),
+            );
+        }
+
+        let t = side?;
+        let point = local.at(t);
+        let u = (point.z().atan2(point.x()) + PI) / TAU;
+        Some(
+            HitRecord::new(
+                t,
+                ray,
+                |_| Vec3::new(point.x(), 0.0, point.z()) / self.radius,
+                &self.material,
+            )
+            .with_uv(u, point.y() / self.height)
+            .with_derivatives(
+                TAU * Vec3::new(-point.z(), 0.0, point.x()),
+                Vec3::new(0.0, self.height, 0.0),
+            ),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.base - Vec3::new(self.radius, 0.0, self.radius),
+            self.base + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Solid for Cylinder {}
+
+#[cfg(test)]
+mod tests {
+    use super::Cylinder;
+    use crate::primitives::{
+        collisions::{
+            tests::{assert_hits_on_surface, material, random_rays, TOLERANCE},
+            Hittable,
+        },
+        Decimal, Point3, Ray, Vec3,
+    };
+
+    ///The side of a cylinder of radius 1 from y = -1 to 1
+    fn side(point: Point3) -> (Decimal, Vec3) {
+        (
+            point.x().hypot(point.z()) - 1.0,
+            Vec3::new(point.x(), 0.0, point.z()),
+        )
+    }
+
+    ///[`side`], with the caps on the ends
+    fn closed(point: Point3) -> (Decimal, Vec3) {
+        let (side, gradient) = side(point);
+        let cap = point.y().abs() - 1.0;
+        if side > cap {
+            (side, gradient)
+        } else {
+            (cap, Vec3::new(0.0, point.y(), 0.0))
+        }
+    }
+
+    ///Rays which only just touch the cylinder, or go along its axis
+    fn grazing() -> [Ray; 5] {
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        [
+            //tangent to the side
+            Ray::new(Point3::new(1.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)),
+            //along the side
+            Ray::new(Point3::new(1.0, 5.0, 0.0), down),
+            //along the axis, and parallel to it
+            Ray::new(Point3::new(0.0, 5.0, 0.0), down),
+            Ray::new(Point3::new(0.5, -5.0, 0.2), -down),
+            //skimming the top, so it only just hits the middle of the cap
+            Ray::new(Point3::new(-5.0, 1.001, 0.0), Vec3::new(5.0, -0.001, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn open_hits_on_surface() {
+        let cylinder = Cylinder::uncapped(Point3::new(0.0, -1.0, 0.0), 1.0, 2.0, material());
+        let hits = assert_hits_on_surface(&cylinder, random_rays(1).chain(grazing()), side);
+        assert!(hits.len() > 1000, "only {} rays hit", hits.len());
+
+        //straight down the middle of a tube doesn't touch it
+        let axis = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(cylinder.hit(axis, 0.00001, Decimal::INFINITY).is_none());
+    }
+
+    #[test]
+    fn closed_hits_on_surface() {
+        let cylinder = Cylinder::new(Point3::new(0.0, -1.0, 0.0), 1.0, 2.0, material());
+        let hits = assert_hits_on_surface(&cylinder, random_rays(2).chain(grazing()), closed);
+        let on_caps = hits
+            .iter()
+            .filter(|point| (point.y().abs() - 1.0).abs() < TOLERANCE)
+            .count();
+        assert!(
+            on_caps > 500 && hits.len() - on_caps > 500,
+            "{on_caps} of {} hits were on the caps",
+            hits.len()
+        );
+
+        //the axis goes through the middle of both caps
+        for (origin, direction) in [(5.0, -1.0), (-5.0, 1.0)] {
+            let axis = Ray::new(
+                Point3::new(0.0, origin, 0.0),
+                Vec3::new(0.0, direction, 0.0),
+            );
+            let hit = cylinder
+                .hit(axis, 0.00001, Decimal::INFINITY)
+                .expect("the axis goes through the caps");
+            assert!((hit.point - Point3::new(0.0, -direction, 0.0)).magnitude() < TOLERANCE);
+            assert!((hit.normal - Vec3::new(0.0, -direction, 0.0)).magnitude() < TOLERANCE);
+            assert!(hit.front_face);
+        }
+    }
+}
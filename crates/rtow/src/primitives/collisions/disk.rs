@@ -0,0 +1,109 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, Aabb, HitRecord, Hittable, Sampleable},
+    decimal_consts::{PI, TAU},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+
+///A flat circle facing along `normal`.
+///
+///UV coordinates are polar - `u` is the angle around the centre from 0-1, and `v` is the distance from the centre from 0-1.
+#[derive(Clone, Debug)]
+pub struct Disk {
+    centre: Point3,
+    normal: Vec3,
+    radius: Decimal,
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Material,
+}
+
+impl Disk {
+    #[must_use]
+    pub fn new(centre: Point3, normal: Vec3, radius: Decimal, material: Material) -> Self {
+        let normal = normal.unit();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+
+        Self {
+            centre,
+            normal,
+            radius,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.centre - ray.origin()) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let planar = ray.at(t) - self.centre;
+        let distance_squared = planar.magnitude_squared();
+        if distance_squared > self.radius * self.radius {
+            return None;
+        }
+
+        let phi = planar.dot(self.bitangent).atan2(planar.dot(self.tangent));
+        let u = (phi + PI) / TAU;
+        let v = distance_squared.sqrt() / self.radius;
+
+        //going around is perpendicular to the direction out from the centre
+        let around =
+            planar.dot(self.tangent) * self.bitangent - planar.dot(self.bitangent) * self.tangent;
+        let out = if distance_squared > 0.0 {
+            planar / distance_squared.sqrt()
+        } else {
+            self.tangent
+        };
+
+        Some(
+            HitRecord::new(t, ray, |_| self.normal, &self.material)
+                .with_uv(u, v)
+                .with_derivatives(TAU * around, self.radius * out),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        //extent of the disk along each axis is radius * sin(angle between normal and axis)
+        let extent = Vec3::new(
+            (1.0 - self.normal.x() * self.normal.x()).max(0.0).sqrt(),
+            (1.0 - self.normal.y() * self.normal.y()).max(0.0).sqrt(),
+            (1.0 - self.normal.z() * self.normal.z()).max(0.0).sqrt(),
+        ) * self.radius;
+
+        Some(Aabb::new(self.centre - extent, self.centre + extent))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Sampleable for Disk {
+    fn area(&self) -> Decimal {
+        PI * self.radius * self.radius
+    }
+
+    fn sample_point(&self, rng: &mut ThreadRng) -> (Point3, Vec3) {
+        //sqrt so points are uniform by area, rather than bunched in the centre
+        let r = self.radius * rng.gen_range(0.0..=1.0_f64).sqrt();
+        let (sin, cos) = rng.gen_range(0.0..TAU).sin_cos();
+
+        (
+            self.centre + r * cos * self.tangent + r * sin * self.bitangent,
+            self.normal,
+        )
+    }
+}
@@ -1,20 +1,31 @@
-use crate::primitives::{materials::Material, Decimal, Point3, Ray, Vec3};
+use crate::primitives::{materials::Material, textures::Footprint, Decimal, Point3, Ray, Vec3};
 
 #[derive(Debug, Copy, Clone)]
-pub struct HitRecord {
+pub struct HitRecord<'a> {
     pub point: Point3,
+    ///the shading normal, which always faces against the ray - normal and bump maps change this one
     pub normal: Vec3,
-    pub material: Material,
+    ///the normal of the actual surface, on the same side as `normal`
+    pub geometric_normal: Vec3,
+    pub material: &'a Material,
     pub time: Decimal,
     pub front_face: bool,
+    ///surface coordinates, each from 0-1 on bounded surfaces
+    pub u: Decimal,
+    pub v: Decimal,
+    ///how the point moves as `u` and `v` change - zero if the surface doesn't have UVs
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
+    ///how much of the UV space the pixel covers, filled in by [`HitRecord::set_footprint`]
+    pub footprint: Footprint,
 }
 
-impl HitRecord {
+impl<'a> HitRecord<'a> {
     pub fn new(
         time: Decimal,
         ray: Ray,
         calc_normal: impl Fn(Vec3) -> Vec3,
-        material: Material,
+        material: &'a Material,
     ) -> Self {
         let point = ray.at(time);
         let normal = calc_normal(point);
@@ -24,12 +35,104 @@ impl HitRecord {
             time,
             material,
             normal,
+            geometric_normal: normal,
             front_face: false,
+            u: 0.0,
+            v: 0.0,
+            dpdu: Vec3::default(),
+            dpdv: Vec3::default(),
+            footprint: Footprint::default(),
         };
         s.set_front_face(ray, normal);
         s
     }
 
+    #[must_use]
+    pub const fn with_uv(mut self, u: Decimal, v: Decimal) -> Self {
+        self.u = u;
+        self.v = v;
+        self
+    }
+
+    #[must_use]
+    pub const fn with_derivatives(mut self, along_u: Vec3, along_v: Vec3) -> Self {
+        self.dpdu = along_u;
+        self.dpdv = along_v;
+        self
+    }
+
+    ///Works out the [`Footprint`] by intersecting the ray's differentials with the tangent plane, and projecting the offsets onto `dpdu` and `dpdv`.
+    ///
+    ///The footprint is left empty if the ray has no differentials.
+    pub fn set_footprint(&mut self, ray: Ray) {
+        self.footprint = Footprint::default();
+        let Some(differentials) = ray.differentials() else {
+            return;
+        };
+
+        let normal = self.geometric_normal;
+        let distance = normal.dot(self.point);
+        let offset = |origin: Point3, direction: Vec3| {
+            let denominator = normal.dot(direction);
+            (denominator.abs() > 1e-12).then(|| {
+                let t = (distance - normal.dot(origin)) / denominator;
+                origin + t * direction - self.point
+            })
+        };
+        let (Some(x_offset), Some(y_offset)) = (
+            offset(differentials.x_origin, differentials.x_direction),
+            offset(differentials.y_origin, differentials.y_direction),
+        ) else {
+            return;
+        };
+
+        //least squares solution to dpdu * du + dpdv * dv = dp
+        let (u_squared, v_squared, shared) = (
+            self.dpdu.magnitude_squared(),
+            self.dpdv.magnitude_squared(),
+            self.dpdu.dot(self.dpdv),
+        );
+        let determinant = u_squared.mul_add(v_squared, -shared * shared);
+        if determinant.abs() < 1e-20 {
+            return;
+        }
+        let solve = |offset: Vec3| {
+            let (along_u, along_v) = (self.dpdu.dot(offset), self.dpdv.dot(offset));
+            (
+                (v_squared * along_u - shared * along_v) / determinant,
+                (u_squared * along_v - shared * along_u) / determinant,
+            )
+        };
+
+        let (x, y) = (solve(x_offset), solve(y_offset));
+        self.footprint = Footprint {
+            dudx: x.0,
+            dvdx: x.1,
+            dudy: y.0,
+            dvdy: y.1,
+        };
+    }
+
+    ///A unit tangent and bitangent perpendicular to the shading normal, with the tangent following `u` and the bitangent following `v`.
+    ///
+    ///Surfaces without UV derivatives get an arbitrary frame.
+    #[must_use]
+    pub fn tangent_frame(&self) -> (Vec3, Vec3) {
+        //Gram-Schmidt, as dpdu isn't always perpendicular to the normal, especially after normal mapping
+        let tangent = self.dpdu - self.normal * self.normal.dot(self.dpdu);
+        if tangent.magnitude_squared() < 1e-20 {
+            return self.normal.orthonormal_basis();
+        }
+        let tangent = tangent.unit();
+
+        let bitangent = self.normal.cross(tangent);
+        if bitangent.dot(self.dpdv) < 0.0 {
+            (tangent, -bitangent)
+        } else {
+            (tangent, bitangent)
+        }
+    }
+
     pub fn set_front_face(&mut self, ray: Ray, outward_normal: Vec3) {
         self.front_face = ray.direction().dot(outward_normal) < 0.0;
         self.normal = if self.front_face {
@@ -37,5 +140,6 @@ impl HitRecord {
         } else {
             -outward_normal
         };
+        self.geometric_normal = self.normal;
     }
 }
@@ -1,5 +1,6 @@
 use crate::primitives::{
-    collisions::{HitRecord, Hittable},
+    collisions::{Aabb, HitRecord, Hittable},
+    materials::Material,
     Decimal, Ray,
 };
 use std::sync::Arc;
@@ -17,15 +18,40 @@ impl HittableList {
 }
 
 impl Hittable for HittableList {
-    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
         let mut smallest = None;
 
         for hit in self.0.iter().filter_map(|obj| obj.hit(ray, t_min, t_max)) {
-            if smallest.map_or(true, |smallest: HitRecord| hit.time < smallest.time) {
+            if smallest.is_none_or(|smallest: HitRecord| hit.time < smallest.time) {
                 smallest = Some(hit);
             }
         }
 
         smallest
     }
+
+    fn hit_object(
+        &self,
+        ray: Ray,
+        t_min: Decimal,
+        t_max: Decimal,
+    ) -> Option<(usize, HitRecord<'_>)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(index, obj)| Some((index, obj.hit(ray, t_min, t_max)?)))
+            .min_by(|(_, a), (_, b)| a.time.total_cmp(&b.time))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.0
+            .iter()
+            .map(|obj| obj.bounding_box())
+            .reduce(|acc, bb| Some(acc?.surrounding(bb?)))
+            .flatten()
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        self.0.iter().flat_map(|obj| obj.materials()).collect()
+    }
 }
@@ -0,0 +1,76 @@
+use crate::primitives::{
+    collisions::{Aabb, HitRecord, Hittable, Solid, Span},
+    materials::Material,
+    Decimal, Ray, Transform,
+};
+use std::sync::Arc;
+
+///Places a shared piece of geometry in the world with an affine [`Transform`].
+///
+///Many instances can point to the same underlying object, so the geometry is only stored once.
+///Instances of [`Solid`]s are also solids, so they can be used in [`Csg`](super::csg::Csg) trees.
+#[derive(Debug)]
+pub struct Instance<T: Hittable + ?Sized = dyn Hittable> {
+    object: Arc<Box<T>>,
+    ///object space to world space
+    transform: Transform,
+}
+
+impl<T: Hittable + ?Sized> Clone for Instance<T> {
+    fn clone(&self) -> Self {
+        Self {
+            object: self.object.clone(),
+            transform: self.transform,
+        }
+    }
+}
+
+impl<T: Hittable + ?Sized> Instance<T> {
+    #[must_use]
+    pub const fn new(object: Arc<Box<T>>, transform: Transform) -> Self {
+        Self { object, transform }
+    }
+
+    fn to_world<'a>(&self, mut hit: HitRecord<'a>) -> HitRecord<'a> {
+        hit.point = self.transform.point(hit.point);
+        //the inverse transpose keeps the normal on the same side as the ray, so front_face is still correct
+        hit.normal = self.transform.normal(hit.normal).unit();
+        hit.geometric_normal = self.transform.normal(hit.geometric_normal).unit();
+        hit.dpdu = self.transform.vector(hit.dpdu);
+        hit.dpdv = self.transform.vector(hit.dpdv);
+        hit
+    }
+}
+
+impl<T: Hittable + ?Sized> Hittable for Instance<T> {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        let object_ray = self.transform.inverse().ray(ray);
+        self.object
+            .hit(object_ray, t_min, t_max)
+            .map(|hit| self.to_world(hit))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object
+            .bounding_box()
+            .map(|bb| bb.transformed(&self.transform))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        self.object.materials()
+    }
+}
+
+impl<T: Solid + ?Sized> Solid for Instance<T> {
+    fn spans(&self, ray: Ray) -> Vec<Span<'_>> {
+        let object_ray = self.transform.inverse().ray(ray);
+        self.object
+            .spans(object_ray)
+            .into_iter()
+            .map(|Span { enter, exit }| Span {
+                enter: self.to_world(enter),
+                exit: self.to_world(exit),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,196 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, Aabb, HitRecord, Hittable},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+
+///One triangle of a [`Mesh`], as indices into its vertex data
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Triangle {
+    pub positions: [usize; 3],
+    ///smooth shading normals - the triangle is flat without them
+    pub normals: Option<[usize; 3]>,
+    ///texture coordinates - without them, UVs are the barycentric coordinates of the second and third corners
+    pub uvs: Option<[usize; 3]>,
+    ///the index into the mesh's materials
+    pub material: usize,
+}
+
+///A triangle mesh, like one read from an OBJ file.
+///
+///Every triangle is tested against every ray, as there's no BVH yet, so it's only quick for small meshes.
+#[derive(Clone, Debug)]
+pub struct Mesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(Decimal, Decimal)>,
+    triangles: Vec<Triangle>,
+    materials: Vec<Material>,
+}
+
+impl Mesh {
+    /// # Panics
+    /// If a triangle has an index past the end of the data it indexes
+    #[must_use]
+    pub fn new(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(Decimal, Decimal)>,
+        triangles: Vec<Triangle>,
+        materials: Vec<Material>,
+    ) -> Self {
+        for triangle in &triangles {
+            assert!(
+                triangle
+                    .positions
+                    .iter()
+                    .all(|&index| index < positions.len())
+                    && triangle
+                        .normals
+                        .is_none_or(|indices| indices.iter().all(|&index| index < normals.len()))
+                    && triangle
+                        .uvs
+                        .is_none_or(|indices| indices.iter().all(|&index| index < uvs.len()))
+                    && triangle.material < materials.len(),
+                "triangle index out of range"
+            );
+        }
+
+        Self {
+            positions,
+            normals,
+            uvs,
+            triangles,
+            materials,
+        }
+    }
+
+    #[must_use]
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
+    ///Möller-Trumbore - the distance along `ray` and the barycentric coordinates of the second and third corners
+    fn intersect(
+        &self,
+        triangle: &Triangle,
+        ray: Ray,
+        t_min: Decimal,
+        t_max: Decimal,
+    ) -> Option<(Decimal, Decimal, Decimal)> {
+        count_primitive_test();
+        let [first, second, third] = triangle.positions.map(|index| self.positions[index]);
+        let (edge_1, edge_2) = (second - first, third - first);
+
+        let across = ray.direction().cross(edge_2);
+        let determinant = edge_1.dot(across);
+        if determinant.abs() < 1e-12 {
+            //parallel to the triangle, or the triangle has no area
+            return None;
+        }
+
+        let offset = ray.origin() - first;
+        let beta = offset.dot(across) / determinant;
+        if !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+        let along = offset.cross(edge_1);
+        let gamma = ray.direction().dot(along) / determinant;
+        if gamma < 0.0 || beta + gamma > 1.0 {
+            return None;
+        }
+
+        let t = edge_2.dot(along) / determinant;
+        (t_min..=t_max).contains(&t).then_some((t, beta, gamma))
+    }
+
+    fn hit_record(
+        &self,
+        triangle: &Triangle,
+        ray: Ray,
+        (t, beta, gamma): (Decimal, Decimal, Decimal),
+    ) -> HitRecord<'_> {
+        let [first, second, third] = triangle.positions.map(|index| self.positions[index]);
+        let alpha = 1.0 - beta - gamma;
+        let (edge_1, edge_2) = (second - first, third - first);
+
+        let mut hit = HitRecord::new(
+            t,
+            ray,
+            |_| edge_1.cross(edge_2).unit(),
+            &self.materials[triangle.material],
+        );
+
+        hit = match triangle
+            .uvs
+            .map(|indices| indices.map(|index| self.uvs[index]))
+        {
+            Some([uv_a, uv_b, uv_c]) => {
+                let (u, v) = (
+                    alpha * uv_a.0 + beta * uv_b.0 + gamma * uv_c.0,
+                    alpha * uv_a.1 + beta * uv_b.1 + gamma * uv_c.1,
+                );
+                //solving for how the position changes with u and v along both edges
+                let (uv_edge_1, uv_edge_2) = (
+                    (uv_b.0 - uv_a.0, uv_b.1 - uv_a.1),
+                    (uv_c.0 - uv_a.0, uv_c.1 - uv_a.1),
+                );
+                let determinant = uv_edge_1.0 * uv_edge_2.1 - uv_edge_1.1 * uv_edge_2.0;
+                let hit = hit.with_uv(u, v);
+                if determinant.abs() < 1e-12 {
+                    hit.with_derivatives(edge_1, edge_2)
+                } else {
+                    hit.with_derivatives(
+                        (uv_edge_2.1 * edge_1 - uv_edge_1.1 * edge_2) / determinant,
+                        (uv_edge_1.0 * edge_2 - uv_edge_2.0 * edge_1) / determinant,
+                    )
+                }
+            }
+            None => hit.with_uv(beta, gamma).with_derivatives(edge_1, edge_2),
+        };
+
+        if let Some([n_a, n_b, n_c]) = triangle
+            .normals
+            .map(|indices| indices.map(|index| self.normals[index]))
+        {
+            let shading = alpha * n_a + beta * n_b + gamma * n_c;
+            if !shading.near_zero() {
+                //on the same side as the geometric normal, so it still faces against the ray
+                let shading = shading.unit();
+                hit.normal = if shading.dot(hit.geometric_normal) < 0.0 {
+                    -shading
+                } else {
+                    shading
+                };
+            }
+        }
+
+        hit
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        let mut closest: Option<(&Triangle, (Decimal, Decimal, Decimal))> = None;
+        for triangle in &self.triangles {
+            let t_max = closest.map_or(t_max, |(_, (t, _, _))| t);
+            if let Some(found) = self.intersect(triangle, ray, t_min, t_max) {
+                closest = Some((triangle, found));
+            }
+        }
+
+        closest.map(|(triangle, found)| self.hit_record(triangle, ray, found))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Aabb::from_points(
+            self.triangles
+                .iter()
+                .flat_map(|triangle| triangle.positions.map(|index| self.positions[index])),
+        )
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        self.materials.iter().collect()
+    }
+}
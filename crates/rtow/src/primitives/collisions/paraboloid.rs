@@ -0,0 +1,208 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, solvers, Aabb, HitRecord, Hittable, Solid},
+    decimal_consts::{PI, TAU},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+
+///A bowl-shaped paraboloid with its vertex at `vertex`, opening up the y-axis until it is `radius` wide at `height` - rotate it with an [`Instance`](super::instance::Instance).
+///
+///UVs on the side go around (`u`) and up (`v`), and UVs on the cap are the x and z positions mapped to 0-1.
+#[derive(Clone, Debug)]
+pub struct Paraboloid {
+    vertex: Point3,
+    radius: Decimal,
+    height: Decimal,
+    capped: bool,
+    material: Material,
+}
+
+impl Paraboloid {
+    ///A closed paraboloid, with a disk on top
+    #[must_use]
+    pub const fn new(vertex: Point3, radius: Decimal, height: Decimal, material: Material) -> Self {
+        Self {
+            vertex,
+            radius,
+            height,
+            capped: true,
+            material,
+        }
+    }
+    ///An open bowl, with no cap - this isn't closed, so it shouldn't be used as a [`Solid`]
+    #[must_use]
+    pub const fn uncapped(
+        vertex: Point3,
+        radius: Decimal,
+        height: Decimal,
+        material: Material,
+    ) -> Self {
+        Self {
+            vertex,
+            radius,
+            height,
+            capped: false,
+            material,
+        }
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let local = Ray::new(ray.origin() - self.vertex, ray.direction());
+        let (origin, direction) = (local.origin(), local.direction());
+
+        //x^2 + z^2 = k y
+        let k = self.radius * self.radius / self.height;
+        let side = solvers::quadratic(
+            direction.x() * direction.x() + direction.z() * direction.z(),
+            2.0 * (origin.x() * direction.x() + origin.z() * direction.z()) - k * direction.y(),
+            origin.x() * origin.x() + origin.z() * origin.z() - k * origin.y(),
+        )
+        .into_iter()
+        .find(|&t| (t_min..=t_max).contains(&t) && local.at(t).y() <= self.height);
+
+        let cap = if self.capped {
+            solvers::horizontal_disk(
+                local,
+                self.height,
+                self.radius,
+                t_min,
+                side.unwrap_or(t_max),
+            )
+        } else {
+            None
+        };
+
+        if let Some(t) = cap {
+            let point = local.at(t);
+            return Some(
+                HitRecord::new(t, ray, |_| Vec3::UP, &self.material)
+                    .with_uv(
+                        0.5 + point.x() / (2.0 * self.radius),
+                        0.5 + point.z() / (2.0 * self.radius),
+                    )
+                    .with_derivatives(
+                        Vec3::new(2.0 * self.radius, 0.0, 0.0),
+                        Vec3::new(0.0, 0.0, 2.0 * self.radius),
+                    ),
+            );
+        }
+
+        let t = side?;
+        let point = local.at(t);
+        let normal = Vec3::new(2.0 * point.x(), -k, 2.0 * point.z()).unit();
+        let u = (point.z().atan2(point.x()) + PI) / TAU;
+        //going up the side moves out by d(sqrt(ky))/dy = k / 2 sqrt(ky)
+        let spread =
+            k * self.height / (2.0 * (point.x() * point.x() + point.z() * point.z())).max(1e-12);
+
+        Some(
+            HitRecord::new(t, ray, |_| normal, &self.material)
+                .with_uv(u, point.y() / self.height)
+                .with_derivatives(
+                    TAU * Vec3::new(-point.z(), 0.0, point.x()),
+                    Vec3::new(point.x() * spread, self.height, point.z() * spread),
+                ),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.vertex - Vec3::new(self.radius, 0.0, self.radius),
+            self.vertex + Vec3::new(self.radius, self.height, self.radius),
+        ))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Solid for Paraboloid {}
+
+#[cfg(test)]
+mod tests {
+    use super::Paraboloid;
+    use crate::primitives::{
+        collisions::{
+            tests::{assert_hits_on_surface, material, random_rays, TOLERANCE},
+            Hittable,
+        },
+        Decimal, Point3, Ray, Vec3,
+    };
+
+    ///The side of a bowl with its vertex at y = -1, which is 1 wide at y = 1
+    fn side(point: Point3) -> (Decimal, Vec3) {
+        (
+            point.x() * point.x() + point.z() * point.z() - 0.5 * (point.y() + 1.0),
+            Vec3::new(2.0 * point.x(), -0.5, 2.0 * point.z()),
+        )
+    }
+
+    ///[`side`], with the cap on top
+    fn closed(point: Point3) -> (Decimal, Vec3) {
+        let (side, gradient) = side(point);
+        let cap = point.y() - 1.0;
+        if side > cap {
+            (side, gradient)
+        } else {
+            (cap, Vec3::new(0.0, 1.0, 0.0))
+        }
+    }
+
+    ///Rays which only just touch the bowl, or go along its axis
+    fn grazing() -> [Ray; 5] {
+        let down = Vec3::new(0.0, -1.0, 0.0);
+        [
+            //tangent at the vertex
+            Ray::new(Point3::new(-5.0, -1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            //down the axis, and parallel to it
+            Ray::new(Point3::new(0.0, 5.0, 0.0), down),
+            Ray::new(Point3::new(0.4, 5.0, -0.3), down),
+            //up the axis from under the vertex
+            Ray::new(Point3::new(0.0, -5.0, 0.0), -down),
+            //skimming the top, so it only just hits the middle of the cap
+            Ray::new(Point3::new(-5.0, 1.001, 0.0), Vec3::new(5.0, -0.001, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn open_hits_on_surface() {
+        let paraboloid = Paraboloid::uncapped(Point3::new(0.0, -1.0, 0.0), 1.0, 2.0, material());
+        let hits = assert_hits_on_surface(&paraboloid, random_rays(5).chain(grazing()), side);
+        assert!(hits.len() > 1000, "only {} rays hit", hits.len());
+
+        //down the axis goes into the bowl and hits the vertex from inside
+        let axis = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = paraboloid
+            .hit(axis, 0.00001, Decimal::INFINITY)
+            .expect("the axis goes through the vertex");
+        assert!((hit.point - Point3::new(0.0, -1.0, 0.0)).magnitude() < TOLERANCE);
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn closed_hits_on_surface() {
+        let paraboloid = Paraboloid::new(Point3::new(0.0, -1.0, 0.0), 1.0, 2.0, material());
+        let hits = assert_hits_on_surface(&paraboloid, random_rays(6).chain(grazing()), closed);
+        let on_cap = hits
+            .iter()
+            .filter(|point| (point.y() - 1.0).abs() < TOLERANCE)
+            .count();
+        assert!(
+            on_cap > 500 && hits.len() - on_cap > 500,
+            "{on_cap} of {} hits were on the cap",
+            hits.len()
+        );
+
+        //down the axis hits the middle of the cap
+        let axis = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        let hit = paraboloid
+            .hit(axis, 0.00001, Decimal::INFINITY)
+            .expect("the axis goes through the cap");
+        assert!((hit.point - Point3::new(0.0, 1.0, 0.0)).magnitude() < TOLERANCE);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).magnitude() < TOLERANCE);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, Aabb, HitRecord, Hittable},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+
+///An infinite plane through `point`.
+///
+///UV coordinates are world-space distances from `point` along two tangents, so they are unbounded - textures should wrap them.
+#[derive(Clone, Debug)]
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Material,
+}
+
+impl Plane {
+    #[must_use]
+    pub fn new(point: Point3, normal: Vec3, material: Material) -> Self {
+        let normal = normal.unit();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+
+        Self {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = self.normal.dot(self.point - ray.origin()) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let planar = ray.at(t) - self.point;
+        Some(
+            HitRecord::new(t, ray, |_| self.normal, &self.material)
+                .with_uv(planar.dot(self.tangent), planar.dot(self.bitangent))
+                .with_derivatives(self.tangent, self.bitangent),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
@@ -0,0 +1,104 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, Aabb, HitRecord, Hittable, Sampleable},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+
+///A parallelogram, with one corner at `corner` and two edges going along `u` and `v`.
+///
+///UV coordinates go from 0-1 along each edge.
+#[derive(Clone, Debug)]
+pub struct Quad {
+    corner: Point3,
+    u: Vec3,
+    v: Vec3,
+    material: Material,
+    ///unit normal, from `u` cross `v`
+    normal: Vec3,
+    ///plane constant, so all points on the plane satisfy `normal.dot(p) == d`
+    d: Decimal,
+    ///`n / n.dot(n)` where n is the non-unit normal, used to find planar coordinates
+    w: Vec3,
+    area: Decimal,
+}
+
+impl Quad {
+    #[must_use]
+    pub fn new(corner: Point3, u: Vec3, v: Vec3, material: Material) -> Self {
+        let n = u.cross(v);
+        let normal = n.unit();
+
+        Self {
+            corner,
+            u,
+            v,
+            material,
+            normal,
+            d: normal.dot(corner),
+            w: n / n.magnitude_squared(),
+            area: n.magnitude(),
+        }
+    }
+
+    #[must_use]
+    pub const fn corner(&self) -> Point3 {
+        self.corner
+    }
+    #[must_use]
+    pub const fn normal(&self) -> Vec3 {
+        self.normal
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let denominator = self.normal.dot(ray.direction());
+        if denominator.abs() < 1e-8 {
+            //parallel to the plane
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(ray.origin())) / denominator;
+        if t < t_min || t > t_max {
+            return None;
+        }
+
+        let planar = ray.at(t) - self.corner;
+        let alpha = self.w.dot(planar.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        Some(
+            HitRecord::new(t, ray, |_| self.normal, &self.material)
+                .with_uv(alpha, beta)
+                .with_derivatives(self.u, self.v),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(
+            Aabb::new(self.corner, self.corner + self.u + self.v)
+                .surrounding(Aabb::new(self.corner + self.u, self.corner + self.v)),
+        )
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Sampleable for Quad {
+    fn area(&self) -> Decimal {
+        self.area
+    }
+
+    fn sample_point(&self, rng: &mut ThreadRng) -> (Point3, Vec3) {
+        let point =
+            self.corner + rng.gen_range(0.0..=1.0) * self.u + rng.gen_range(0.0..=1.0) * self.v;
+        (point, self.normal)
+    }
+}
@@ -0,0 +1,120 @@
+//! Objects defined by signed distance functions, rendered by sphere tracing
+
+pub mod operations;
+pub mod shapes;
+
+use crate::primitives::{
+    collisions::{count_primitive_test, Aabb, HitRecord, Hittable, Solid},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+use std::fmt::Debug;
+
+///A signed distance function - negative inside the surface, positive outside, and 0 on it.
+pub trait Sdf: Debug + Send + Sync {
+    fn distance(&self, point: Point3) -> Decimal;
+    ///A box that the whole surface fits inside
+    fn bounding_box(&self) -> Aabb;
+    ///How much faster than 1 unit per unit the distance can change.
+    ///
+    ///Exact distance functions have a Lipschitz bound of 1, but domain operations like [`operations::Twist`] stretch space,
+    ///so we have to take smaller steps to not overshoot the surface.
+    fn lipschitz(&self) -> Decimal {
+        1.0
+    }
+}
+
+///Sphere traces an [`Sdf`], so it can be used in the world alongside analytic objects with any [`Material`].
+#[derive(Debug)]
+pub struct SdfObject {
+    sdf: Box<dyn Sdf>,
+    material: Material,
+    bounding_box: Aabb,
+}
+
+impl SdfObject {
+    ///Distance from the surface at which we count it as a hit
+    const HIT_DISTANCE: Decimal = 1e-5;
+    ///Offset used when finding the gradient for normals
+    const NORMAL_OFFSET: Decimal = 1e-5;
+    const MAX_STEPS: usize = 512;
+
+    #[must_use]
+    pub fn new(sdf: Box<dyn Sdf>, material: Material) -> Self {
+        let bounding_box = sdf.bounding_box();
+        Self {
+            sdf,
+            material,
+            bounding_box,
+        }
+    }
+
+    ///The surface normal from the gradient of the distance function, using the tetrahedron technique so we only need 4 samples
+    fn normal(&self, point: Point3) -> Vec3 {
+        [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ]
+        .into_iter()
+        .map(|k| k * self.sdf.distance(point + k * Self::NORMAL_OFFSET))
+        .fold(Vec3::default(), |acc, k| acc + k)
+        .unit()
+    }
+}
+
+impl Hittable for SdfObject {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        let (mut t, t_end) = self.bounding_box.interval(ray, t_min, t_max)?;
+
+        let direction_length = ray.direction().magnitude();
+        let step_scale = 1.0 / (self.sdf.lipschitz() * direction_length);
+
+        //work out which side of the surface we're marching on, so rays which have been refracted inside work too
+        let start_distance = self.sdf.distance(ray.at(t));
+        let mut escaped = start_distance.abs() >= Self::HIT_DISTANCE;
+        let sign = if escaped {
+            start_distance.signum()
+        } else {
+            //we're starting on the surface, so we're outside if we're going up the gradient
+            self.normal(ray.at(t)).dot(ray.direction()).signum()
+        };
+
+        for _ in 0..Self::MAX_STEPS {
+            if t > t_end {
+                return None;
+            }
+
+            let distance = sign * self.sdf.distance(ray.at(t));
+            if escaped {
+                if distance < Self::HIT_DISTANCE {
+                    return Some(HitRecord::new(
+                        t,
+                        ray,
+                        |point| self.normal(point),
+                        &self.material,
+                    ));
+                }
+                t += distance * step_scale;
+            } else {
+                //creep off the surface we started on, so we don't hit it again straight away
+                escaped = distance >= Self::HIT_DISTANCE;
+                t += Self::HIT_DISTANCE / direction_length;
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounding_box)
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Solid for SdfObject {}
@@ -0,0 +1,170 @@
+//! Ways to combine and distort signed distance functions
+
+use crate::primitives::{
+    collisions::{sdf::Sdf, Aabb},
+    Decimal, Point3, Vec3,
+};
+
+///Polynomial smooth minimum, blending over a distance of `k`
+fn smooth_min(a: Decimal, b: Decimal, k: Decimal) -> Decimal {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    b + (a - b) * h - k * h * (1.0 - h)
+}
+
+///A box that any point within `distance` of the original box fits in
+fn grown(bounding_box: Aabb, distance: Decimal) -> Aabb {
+    let extent = Vec3::new(distance, distance, distance);
+    Aabb::new(bounding_box.min() - extent, bounding_box.max() + extent)
+}
+
+///A cube around the origin that anything in the box can be rotated into
+fn rotation_bounds(bounding_box: Aabb) -> Aabb {
+    let radius = bounding_box
+        .min()
+        .magnitude()
+        .max(bounding_box.max().magnitude());
+    Aabb::new(
+        Vec3::new(-radius, -radius, -radius),
+        Vec3::new(radius, radius, radius),
+    )
+}
+
+#[derive(Debug)]
+pub struct Translate {
+    pub inner: Box<dyn Sdf>,
+    pub offset: Vec3,
+}
+
+impl Sdf for Translate {
+    fn distance(&self, point: Point3) -> Decimal {
+        self.inner.distance(point - self.offset)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let inner = self.inner.bounding_box();
+        Aabb::new(inner.min() + self.offset, inner.max() + self.offset)
+    }
+
+    fn lipschitz(&self) -> Decimal {
+        self.inner.lipschitz()
+    }
+}
+
+///Joins two shapes, filling in the crease between them within `smoothness`
+#[derive(Debug)]
+pub struct SmoothUnion {
+    pub a: Box<dyn Sdf>,
+    pub b: Box<dyn Sdf>,
+    pub smoothness: Decimal,
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, point: Point3) -> Decimal {
+        smooth_min(
+            self.a.distance(point),
+            self.b.distance(point),
+            self.smoothness,
+        )
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        grown(
+            self.a.bounding_box().surrounding(self.b.bounding_box()),
+            self.smoothness,
+        )
+    }
+
+    fn lipschitz(&self) -> Decimal {
+        self.a.lipschitz().max(self.b.lipschitz())
+    }
+}
+
+///Carves `cutter` out of `base`, rounding off the new edges within `smoothness`
+#[derive(Debug)]
+pub struct SmoothSubtraction {
+    pub base: Box<dyn Sdf>,
+    pub cutter: Box<dyn Sdf>,
+    pub smoothness: Decimal,
+}
+
+impl Sdf for SmoothSubtraction {
+    fn distance(&self, point: Point3) -> Decimal {
+        //max(a, -b) == -min(-a, b)
+        -smooth_min(
+            -self.base.distance(point),
+            self.cutter.distance(point),
+            self.smoothness,
+        )
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        grown(self.base.bounding_box(), self.smoothness)
+    }
+
+    fn lipschitz(&self) -> Decimal {
+        self.base.lipschitz().max(self.cutter.lipschitz())
+    }
+}
+
+///Twists the shape around the y-axis, by `rate` radians per unit of height
+#[derive(Debug)]
+pub struct Twist {
+    pub inner: Box<dyn Sdf>,
+    pub rate: Decimal,
+}
+
+impl Sdf for Twist {
+    fn distance(&self, point: Point3) -> Decimal {
+        let (sin, cos) = (self.rate * point.y()).sin_cos();
+        self.inner.distance(Point3::new(
+            cos * point.x() - sin * point.z(),
+            point.y(),
+            sin * point.x() + cos * point.z(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let inner = self.inner.bounding_box();
+        let bounds = rotation_bounds(inner);
+        //twisting doesn't change the height
+        Aabb::new(
+            Point3::new(bounds.min().x(), inner.min().y(), bounds.min().z()),
+            Point3::new(bounds.max().x(), inner.max().y(), bounds.max().z()),
+        )
+    }
+
+    fn lipschitz(&self) -> Decimal {
+        let radius = rotation_bounds(self.inner.bounding_box()).max().x();
+        self.inner.lipschitz() * (self.rate * radius).hypot(1.0)
+    }
+}
+
+///Bends the shape in the xy-plane, by `rate` radians per unit along the x-axis
+#[derive(Debug)]
+pub struct Bend {
+    pub inner: Box<dyn Sdf>,
+    pub rate: Decimal,
+}
+
+impl Sdf for Bend {
+    fn distance(&self, point: Point3) -> Decimal {
+        let (sin, cos) = (self.rate * point.x()).sin_cos();
+        self.inner.distance(Point3::new(
+            cos * point.x() - sin * point.y(),
+            sin * point.x() + cos * point.y(),
+            point.z(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        rotation_bounds(self.inner.bounding_box())
+    }
+
+    fn lipschitz(&self) -> Decimal {
+        let radius = rotation_bounds(self.inner.bounding_box()).max().x();
+        self.inner.lipschitz() * (self.rate * radius).hypot(1.0)
+    }
+}
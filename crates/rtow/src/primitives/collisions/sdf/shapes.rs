@@ -0,0 +1,150 @@
+//! Signed distance functions for basic shapes, all centred on the origin - move them with [`Translate`](super::operations::Translate)
+
+use crate::primitives::{
+    collisions::{sdf::Sdf, Aabb},
+    Decimal, Point3, Vec3,
+};
+
+const fn abs(v: Vec3) -> Vec3 {
+    Vec3::new(v.x().abs(), v.y().abs(), v.z().abs())
+}
+const fn max_zero(v: Vec3) -> Vec3 {
+    Vec3::new(v.x().max(0.0), v.y().max(0.0), v.z().max(0.0))
+}
+const fn largest_component(v: Vec3) -> Decimal {
+    v.x().max(v.y()).max(v.z())
+}
+///Exact distance to a box with the given half-extents
+fn box_distance(point: Point3, half_extents: Vec3) -> Decimal {
+    let q = abs(point) - half_extents;
+    max_zero(q).magnitude() + largest_component(q).min(0.0)
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Sphere {
+    pub radius: Decimal,
+}
+
+impl Sdf for Sphere {
+    fn distance(&self, point: Point3) -> Decimal {
+        point.magnitude() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(-extent, extent)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Cuboid {
+    pub half_extents: Vec3,
+}
+
+impl Sdf for Cuboid {
+    fn distance(&self, point: Point3) -> Decimal {
+        box_distance(point, self.half_extents)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(-self.half_extents, self.half_extents)
+    }
+}
+
+///A box with its edges and corners rounded off by `radius`, while staying inside `half_extents`
+#[derive(Debug, Copy, Clone)]
+pub struct RoundedCuboid {
+    pub half_extents: Vec3,
+    pub radius: Decimal,
+}
+
+impl Sdf for RoundedCuboid {
+    fn distance(&self, point: Point3) -> Decimal {
+        let shrunk = self.half_extents - Vec3::new(self.radius, self.radius, self.radius);
+        box_distance(point, shrunk) - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(-self.half_extents, self.half_extents)
+    }
+}
+
+///A ring lying in the xz-plane, like [`collisions::torus::Torus`](crate::primitives::collisions::torus::Torus)
+#[derive(Debug, Copy, Clone)]
+pub struct Torus {
+    pub major_radius: Decimal,
+    pub minor_radius: Decimal,
+}
+
+impl Sdf for Torus {
+    fn distance(&self, point: Point3) -> Decimal {
+        let ring = point.x().hypot(point.z()) - self.major_radius;
+        ring.hypot(point.y()) - self.minor_radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let outer = self.major_radius + self.minor_radius;
+        let extent = Vec3::new(outer, self.minor_radius, outer);
+        Aabb::new(-extent, extent)
+    }
+}
+
+///A line segment from `start` to `end`, swept with a sphere of `radius`
+#[derive(Debug, Copy, Clone)]
+pub struct Capsule {
+    pub start: Point3,
+    pub end: Point3,
+    pub radius: Decimal,
+}
+
+impl Sdf for Capsule {
+    fn distance(&self, point: Point3) -> Decimal {
+        let to_point = point - self.start;
+        let along = self.end - self.start;
+        let h = (to_point.dot(along) / along.magnitude_squared()).clamp(0.0, 1.0);
+        (to_point - along * h).magnitude() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.start - extent, self.start + extent)
+            .surrounding(Aabb::new(self.end - extent, self.end + extent))
+    }
+}
+
+///A Menger sponge fractal filling a cube with the given half-size
+#[derive(Debug, Copy, Clone)]
+pub struct MengerSponge {
+    pub half_size: Decimal,
+    pub iterations: usize,
+}
+
+impl Sdf for MengerSponge {
+    fn distance(&self, point: Point3) -> Decimal {
+        //work in a unit cube, and scale the distance back at the end
+        let point = point / self.half_size;
+        let mut distance = box_distance(point, Vec3::new(1.0, 1.0, 1.0));
+
+        let mut scale = 1.0;
+        for _ in 0..self.iterations {
+            let cell = Vec3::new(
+                (point.x() * scale).rem_euclid(2.0) - 1.0,
+                (point.y() * scale).rem_euclid(2.0) - 1.0,
+                (point.z() * scale).rem_euclid(2.0) - 1.0,
+            );
+            scale *= 3.0;
+            let r = abs(Vec3::new(1.0, 1.0, 1.0) - 3.0 * abs(cell));
+
+            //distance to the infinite cross that gets cut out of each cell
+            let cross = r.x().max(r.y()).min(r.y().max(r.z())).min(r.z().max(r.x()));
+            distance = distance.max((cross - 1.0) / scale);
+        }
+
+        distance * self.half_size
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let extent = Vec3::new(self.half_size, self.half_size, self.half_size);
+        Aabb::new(-extent, extent)
+    }
+}
@@ -0,0 +1,218 @@
+//! Polynomial root finders for the analytic surfaces
+#![allow(clippy::many_single_char_names)] //the standard names for coefficients are clearer than anything longer
+
+use crate::primitives::{decimal_consts::PI, Decimal, Ray};
+
+///Anything smaller than this is treated as 0 by the solvers
+const EPSILON: Decimal = 1e-9;
+
+///Real roots of `a x^2 + b x + c`, in ascending order.
+///
+///Uses the numerically stable form, so we don't lose precision on grazing rays where `b^2 ≈ 4ac`,
+///and falls back to the linear solution when `a ≈ 0` (eg. a ray parallel to a cone's side).
+#[must_use]
+pub fn quadratic(a: Decimal, b: Decimal, c: Decimal) -> Vec<Decimal> {
+    if a.abs() < EPSILON {
+        return if b.abs() < EPSILON {
+            vec![]
+        } else {
+            vec![-c / b]
+        };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let mut roots = if q.abs() < EPSILON {
+        vec![-b / (2.0 * a)]
+    } else {
+        vec![q / a, c / q]
+    };
+    roots.sort_by(Decimal::total_cmp);
+    roots
+}
+
+///Real roots of the monic cubic `x^3 + a x^2 + b x + c`, in no particular order
+#[must_use]
+pub fn cubic(a: Decimal, b: Decimal, c: Decimal) -> Vec<Decimal> {
+    //substitute x = y - a/3 to get y^3 + 3p y + 2q = 0
+    let a_squared = a * a;
+    let p = (b - a_squared / 3.0) / 3.0;
+    let q = 0.5 * (2.0 / 27.0 * a * a_squared - a * b / 3.0 + c);
+
+    let p_cubed = p * p * p;
+    let discriminant = q * q + p_cubed;
+
+    let roots = if discriminant.abs() < EPSILON {
+        if q.abs() < EPSILON {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        //three real roots
+        let phi = (-q / (-p_cubed).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let sqrt_discriminant = discriminant.sqrt();
+        vec![(sqrt_discriminant - q).cbrt() - (sqrt_discriminant + q).cbrt()]
+    };
+
+    roots.into_iter().map(|root| root - a / 3.0).collect()
+}
+
+///Real roots of `coefficients[0] x^4 + coefficients[1] x^3 + ... + coefficients[4]`, in ascending order.
+///
+///Uses Ferrari's method, and then polishes each root with a few Newton iterations since the closed form loses a lot of precision.
+#[must_use]
+pub fn quartic(coefficients: [Decimal; 5]) -> Vec<Decimal> {
+    let [c_4, c_3, c_2, c_1, c_0] = coefficients;
+    if c_4.abs() < EPSILON {
+        return vec![];
+    }
+    //make it monic
+    let (a, b, c, d) = (c_3 / c_4, c_2 / c_4, c_1 / c_4, c_0 / c_4);
+
+    //substitute x = y - a/4 to get the depressed quartic y^4 + p y^2 + q y + r = 0
+    let a_squared = a * a;
+    let p = -3.0 / 8.0 * a_squared + b;
+    let q = a_squared * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * a_squared * a_squared + a_squared * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = if r.abs() < EPSILON {
+        //y(y^3 + p y + q) = 0
+        let mut roots = cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        //solve the resolvent cubic, and use its largest root to split into two quadratics - for that one, both factors of (2z - p)(z^2 - r) = q^2/4 are positive, so only rounding can make them negative
+        let z = cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)
+            .into_iter()
+            .fold(Decimal::NEG_INFINITY, Decimal::max);
+
+        let u = (z * z - r).max(0.0).sqrt();
+        let v = (2.0 * z - p).max(0.0).sqrt();
+        let v = if q < 0.0 { -v } else { v };
+
+        //rounding can push the discriminant of a repeated root just below 0, which would lose it
+        let monic_quadratic = |b: Decimal, c: Decimal| {
+            let discriminant = b * b - 4.0 * c;
+            if discriminant < 0.0 && -discriminant < EPSILON * (b * b + 4.0 * c.abs()).max(1.0) {
+                vec![-b / 2.0]
+            } else {
+                quadratic(1.0, b, c)
+            }
+        };
+
+        let mut roots = monic_quadratic(v, z - u);
+        roots.extend(monic_quadratic(-v, z + u));
+        roots
+    };
+
+    let evaluate = |x: Decimal| (((c_4 * x + c_3) * x + c_2) * x + c_1) * x + c_0;
+    let derivative = |x: Decimal| ((4.0 * c_4 * x + 3.0 * c_3) * x + 2.0 * c_2) * x + c_1;
+
+    for root in &mut roots {
+        *root -= a / 4.0;
+        for _ in 0..4 {
+            let slope = derivative(*root);
+            if slope.abs() < EPSILON {
+                break;
+            }
+            *root -= evaluate(*root) / slope;
+        }
+    }
+
+    roots.sort_by(Decimal::total_cmp);
+    roots
+}
+
+///Time at which the ray hits the horizontal disk at height `y` around the y-axis, if it is between `t_min` and `t_max`
+#[must_use]
+pub fn horizontal_disk(
+    ray: Ray,
+    y: Decimal,
+    radius: Decimal,
+    t_min: Decimal,
+    t_max: Decimal,
+) -> Option<Decimal> {
+    if ray.direction().y().abs() < EPSILON {
+        return None;
+    }
+
+    let t = (y - ray.origin().y()) / ray.direction().y();
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    let point = ray.at(t);
+    (point.x() * point.x() + point.z() * point.z() <= radius * radius).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quartic;
+    use crate::primitives::Decimal;
+
+    ///Checks `quartic` finds exactly the distinct `roots` of the polynomial with `coefficients`
+    fn assert_roots(coefficients: [Decimal; 5], roots: &[Decimal]) {
+        let found = quartic(coefficients);
+        //repeated roots only pin down the answer to about the square root of the rounding error
+        let near = |a: Decimal, b: Decimal| (a - b).abs() < 1e-4;
+
+        for root in roots {
+            assert!(
+                found.iter().any(|x| near(*x, *root)),
+                "{coefficients:?} should have a root at {root}, but found {found:?}"
+            );
+        }
+        for x in &found {
+            assert!(
+                roots.iter().any(|root| near(*x, *root)),
+                "{coefficients:?} shouldn't have a root at {x}, but found {found:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn quartic_distinct_roots() {
+        //(x + 2)(x + 1)(x - 1)(x - 3)
+        assert_roots([1.0, -1.0, -7.0, 1.0, 6.0], &[-2.0, -1.0, 1.0, 3.0]);
+        //(x^2 + 1)(x - 1)(x - 2)
+        assert_roots([1.0, -3.0, 3.0, -3.0, 2.0], &[1.0, 2.0]);
+        //(x^2 + 1)(x^2 + 4)
+        assert_roots([1.0, 0.0, 5.0, 0.0, 4.0], &[]);
+        //2(x - 0.5)(x + 0.5)(x - 4)(x + 4)
+        assert_roots([2.0, 0.0, -32.5, 0.0, 8.0], &[-4.0, -0.5, 0.5, 4.0]);
+    }
+
+    #[test]
+    fn quartic_repeated_roots() {
+        //(x - 1)^4
+        assert_roots([1.0, -4.0, 6.0, -4.0, 1.0], &[1.0]);
+        //(x - 1)^2 (x - 2)^2
+        assert_roots([1.0, -6.0, 13.0, -12.0, 4.0], &[1.0, 2.0]);
+        //(x - 1)^2 (x + 2)(x - 3)
+        assert_roots([1.0, -3.0, -3.0, 11.0, -6.0], &[-2.0, 1.0, 3.0]);
+        //(x + 1)^3 (x - 2)
+        assert_roots([1.0, 1.0, -3.0, -5.0, -2.0], &[-1.0, 2.0]);
+        //(x^2 + 1)(x - 2)^2
+        assert_roots([1.0, -4.0, 5.0, -4.0, 4.0], &[2.0]);
+        //(x^2 - 1)^2
+        assert_roots([1.0, 0.0, -2.0, 0.0, 1.0], &[-1.0, 1.0]);
+        //(x + 3)^2 (x + 2)(x - 2), where rounding makes the repeated root's discriminant negative
+        assert_roots([1.0, 6.0, 5.0, -24.0, -36.0], &[-3.0, -2.0, 2.0]);
+        //(x + 3)(x + 2)^2 (x - 3) and (x + 3)(x - 2)^2 (x - 3), where the first root of the resolvent cubic isn't the largest
+        assert_roots([1.0, 4.0, -5.0, -36.0, -36.0], &[-3.0, -2.0, 3.0]);
+        assert_roots([1.0, -4.0, -5.0, 36.0, -36.0], &[-3.0, 2.0, 3.0]);
+    }
+}
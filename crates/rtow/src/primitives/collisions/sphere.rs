@@ -1,7 +1,8 @@
 use crate::primitives::{
-    collisions::{HitRecord, Hittable},
+    collisions::{count_primitive_test, Aabb, HitRecord, Hittable, Solid},
+    decimal_consts::{PI, TAU},
     materials::Material,
-    Decimal, Ray, Vec3,
+    Decimal, Point3, Ray, Vec3,
 };
 
 #[derive(Clone, Debug)]
@@ -20,10 +21,18 @@ impl Sphere {
             material,
         }
     }
+
+    ///Spherical coordinates of a point on the unit sphere - `u` goes around the y-axis from -x, and `v` goes from the bottom to the top
+    fn uv(point: Point3) -> (Decimal, Decimal) {
+        let theta = (-point.y()).clamp(-1.0, 1.0).acos();
+        let phi = (-point.z()).atan2(point.x()) + PI;
+        (phi / TAU, theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
-    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord> {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
         let oc = ray.origin() - self.centre;
 
         let a = ray.direction().magnitude_squared();
@@ -45,11 +54,35 @@ impl Hittable for Sphere {
             }
         }
 
-        Some(HitRecord::new(
+        let hit = HitRecord::new(
             root,
             ray,
             |point| (point - self.centre) / self.radius,
-            self.material,
+            &self.material,
+        );
+        let local = hit.point - self.centre;
+        let (u, v) = Self::uv(local / self.radius.abs());
+
+        //distance from the y-axis, kept away from 0 at the poles
+        let ring = local.x().hypot(local.z()).max(1e-12);
+        Some(hit.with_uv(u, v).with_derivatives(
+            TAU * Vec3::new(local.z(), 0.0, -local.x()),
+            PI * Vec3::new(
+                -local.y() * local.x() / ring,
+                ring,
+                -local.y() * local.z() / ring,
+            ),
         ))
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.centre - extent, self.centre + extent))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
 }
+
+impl Solid for Sphere {}
@@ -0,0 +1,168 @@
+use crate::primitives::{
+    collisions::{count_primitive_test, solvers, Aabb, HitRecord, Hittable, Solid},
+    decimal_consts::{PI, TAU},
+    materials::Material,
+    Decimal, Point3, Ray, Vec3,
+};
+
+///A ring lying flat in the xz-plane around `centre` - rotate it with an [`Instance`](super::instance::Instance).
+///
+///`major_radius` is from the centre to the middle of the tube, and `minor_radius` is the radius of the tube itself.
+///`u` goes around the ring, and `v` goes around the tube.
+#[derive(Clone, Debug)]
+pub struct Torus {
+    centre: Point3,
+    major_radius: Decimal,
+    minor_radius: Decimal,
+    material: Material,
+}
+
+impl Torus {
+    #[must_use]
+    pub const fn new(
+        centre: Point3,
+        major_radius: Decimal,
+        minor_radius: Decimal,
+        material: Material,
+    ) -> Self {
+        Self {
+            centre,
+            major_radius,
+            minor_radius,
+            material,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, ray: Ray, t_min: Decimal, t_max: Decimal) -> Option<HitRecord<'_>> {
+        count_primitive_test();
+        //the quartic is badly conditioned for far away origins and tiny/huge directions,
+        //so we solve with a unit direction from where the ray enters the bounding sphere
+        let length = ray.direction().magnitude();
+        let direction = ray.direction() / length;
+        let origin = ray.origin() - self.centre;
+
+        let bound = self.major_radius + self.minor_radius;
+        let half_b = origin.dot(direction);
+        let discriminant = half_b * half_b - (origin.magnitude_squared() - bound * bound);
+        if discriminant < 0.0 {
+            return None;
+        }
+        let start = (-half_b - discriminant.sqrt()).max(t_min * length);
+        let origin = origin + start * direction;
+
+        let (r_major_sq, r_minor_sq) = (
+            self.major_radius * self.major_radius,
+            self.minor_radius * self.minor_radius,
+        );
+        let o_dot_d = origin.dot(direction);
+        let g = origin.magnitude_squared() + r_major_sq - r_minor_sq;
+
+        let t = solvers::quartic([
+            1.0,
+            4.0 * o_dot_d,
+            4.0 * o_dot_d * o_dot_d + 2.0 * g
+                - 4.0
+                    * r_major_sq
+                    * (direction.x() * direction.x() + direction.z() * direction.z()),
+            4.0 * o_dot_d * g
+                - 8.0 * r_major_sq * (origin.x() * direction.x() + origin.z() * direction.z()),
+            g * g - 4.0 * r_major_sq * (origin.x() * origin.x() + origin.z() * origin.z()),
+        ])
+        .into_iter()
+        .map(|s| (s + start) / length)
+        .find(|t| (t_min..=t_max).contains(t))?;
+
+        let point = ray.at(t) - self.centre;
+        let ring = Vec3::new(point.x(), 0.0, point.z());
+        let ring_distance = ring.magnitude();
+        //the direction from the middle of the tube
+        let tube = point - ring / ring_distance * self.major_radius;
+
+        let u = (point.z().atan2(point.x()) + PI) / TAU;
+        let v = (tube.y().atan2(ring_distance - self.major_radius) + PI) / TAU;
+
+        Some(
+            HitRecord::new(t, ray, |_| tube / self.minor_radius, &self.material)
+                .with_uv(u, v)
+                .with_derivatives(
+                    TAU * Vec3::new(-point.z(), 0.0, point.x()),
+                    TAU * (Vec3::UP * (ring_distance - self.major_radius)
+                        - ring / ring_distance * tube.y()),
+                ),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = Vec3::new(
+            self.major_radius + self.minor_radius,
+            self.minor_radius,
+            self.major_radius + self.minor_radius,
+        );
+        Some(Aabb::new(self.centre - extent, self.centre + extent))
+    }
+
+    fn materials(&self) -> Vec<&Material> {
+        vec![&self.material]
+    }
+}
+
+impl Solid for Torus {}
+
+#[cfg(test)]
+mod tests {
+    use super::Torus;
+    use crate::primitives::{
+        collisions::{
+            tests::{assert_hits_on_surface, material, random_rays, TOLERANCE},
+            Hittable,
+        },
+        Decimal, Point3, Ray, Vec3,
+    };
+
+    ///A ring of radius 1 around the origin, with a tube of radius 0.3
+    fn surface(point: Point3) -> (Decimal, Vec3) {
+        let ring = point.x().hypot(point.z());
+        let from_middle = ring - 1.0;
+        (
+            from_middle.hypot(point.y()) - 0.3,
+            Vec3::new(
+                from_middle * point.x() / ring,
+                point.y(),
+                from_middle * point.z() / ring,
+            ),
+        )
+    }
+
+    ///Rays which only just touch the tube, which gives the quartic repeated roots
+    fn grazing() -> [Ray; 3] {
+        [
+            //over the top of the tube on both sides
+            Ray::new(Point3::new(-5.0, 0.3, 0.0), Vec3::new(1.0, 0.0, 0.0)),
+            //tangent to the outside
+            Ray::new(Point3::new(1.3, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0)),
+            //tangent to the inside of the hole, from above
+            Ray::new(Point3::new(0.7, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+        ]
+    }
+
+    #[test]
+    fn hits_on_surface() {
+        let torus = Torus::new(Point3::default(), 1.0, 0.3, material());
+        let hits = assert_hits_on_surface(&torus, random_rays(7).chain(grazing()), surface);
+        assert!(hits.len() > 1000, "only {} rays hit", hits.len());
+
+        //down the axis goes through the hole
+        let axis = Ray::new(Point3::new(0.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+        assert!(torus.hit(axis, 0.00001, Decimal::INFINITY).is_none());
+
+        //through the middle of the tube, from the outside
+        let across = Ray::new(Point3::new(-5.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let hit = torus
+            .hit(across, 0.00001, Decimal::INFINITY)
+            .expect("the tube is in the way");
+        assert!((hit.point - Point3::new(-1.3, 0.0, 0.0)).magnitude() < TOLERANCE);
+        assert!((hit.normal - Vec3::new(-1.0, 0.0, 0.0)).magnitude() < TOLERANCE);
+    }
+}
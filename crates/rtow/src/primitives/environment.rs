@@ -0,0 +1,267 @@
+//! Light coming from infinitely far away, which rays see when they leave the scene
+
+use crate::{
+    io::images::{self, LoadedImage},
+    primitives::{
+        decimal_consts::{PI, TAU},
+        Colour, Decimal, Vec3,
+    },
+};
+use rand::{rngs::ThreadRng, Rng};
+use std::{fmt::Debug, io, path::Path};
+
+pub mod sky;
+
+pub trait Environment: Debug + Send + Sync {
+    ///The light coming from `direction`, which is a unit vector
+    fn radiance(&self, direction: Vec3) -> Colour;
+
+    ///A unit direction to look for light in, with the radiance from there and the probability density (with respect to solid angle) of picking it.
+    ///
+    ///Returns [`None`] if the environment can't be sampled, so it will only be found by rays which miss everything.
+    fn sample(&self, _rng: &mut ThreadRng) -> Option<(Vec3, Colour, Decimal)> {
+        None
+    }
+    ///The probability density of [`Environment::sample`] giving `direction`
+    fn pdf(&self, _direction: Vec3) -> Decimal {
+        0.0
+    }
+}
+
+///A blend from one colour at the horizon to another straight up - the classic sky
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SkyGradient {
+    horizon: Colour,
+    zenith: Colour,
+}
+
+impl SkyGradient {
+    #[must_use]
+    pub const fn new(horizon: Colour, zenith: Colour) -> Self {
+        Self { horizon, zenith }
+    }
+}
+
+impl Default for SkyGradient {
+    fn default() -> Self {
+        Self::new(Colour::new(1.0, 1.0, 1.0), Colour::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Environment for SkyGradient {
+    fn radiance(&self, direction: Vec3) -> Colour {
+        let t = 0.5 * (direction.y() + 1.0);
+        self.horizon * (1.0 - t) + self.zenith * t
+    }
+}
+
+///The same light from every direction - mostly for testing, as a white furnace
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Uniform(pub Colour);
+
+impl Environment for Uniform {
+    fn radiance(&self, _direction: Vec3) -> Colour {
+        self.0
+    }
+
+    fn sample(&self, rng: &mut ThreadRng) -> Option<(Vec3, Colour, Decimal)> {
+        Some((Vec3::random_unit_vector(rng), self.0, 1.0 / (4.0 * PI)))
+    }
+    fn pdf(&self, _direction: Vec3) -> Decimal {
+        1.0 / (4.0 * PI)
+    }
+}
+
+///A piecewise-constant distribution over `0..len`, for picking things in proportion to a weight
+#[derive(Debug, Clone)]
+struct Distribution {
+    ///the running total of the weights, starting from 0
+    cdf: Vec<Decimal>,
+}
+
+impl Distribution {
+    fn new(weights: impl IntoIterator<Item = Decimal>) -> Self {
+        let mut cdf = vec![0.0];
+        for weight in weights {
+            cdf.push(cdf[cdf.len() - 1] + weight.max(0.0));
+        }
+        Self { cdf }
+    }
+
+    fn total(&self) -> Decimal {
+        self.cdf[self.cdf.len() - 1]
+    }
+
+    ///The probability of picking `index`
+    fn probability(&self, index: usize) -> Decimal {
+        (self.cdf[index + 1] - self.cdf[index]) / self.total()
+    }
+
+    ///Picks an index from a uniform number from 0-1
+    fn sample(&self, sample: Decimal) -> usize {
+        let target = sample * self.total();
+        //the first bucket which ends after the target
+        let index = self.cdf.partition_point(|&total| total <= target);
+        index.clamp(1, self.cdf.len() - 1) - 1
+    }
+}
+
+///An equirectangular (latitude-longitude) HDR image of everything around the scene.
+///
+///The middle of the image faces -z, and the top row is straight up. It is importance sampled by brightness, so small bright things like the sun don't make noise.
+#[derive(Debug, Clone)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+    ///around the vertical axis, in radians
+    rotation: Decimal,
+    intensity: Decimal,
+    rows: Distribution,
+    ///one for each row
+    columns: Vec<Distribution>,
+}
+
+impl EnvironmentMap {
+    ///Makes a map from linear pixels, stored in row-major configuration from the top left
+    ///
+    /// # Panics
+    /// If there aren't exactly `width * height` pixels, or there aren't any
+    #[must_use]
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+        assert!(!pixels.is_empty(), "environment maps need pixels");
+
+        //each pixel is picked in proportion to how much light it gives off, which is less near the poles as the pixels are squashed there
+        let columns: Vec<Distribution> = pixels
+            .chunks_exact(width)
+            .enumerate()
+            .map(|(y, row)| {
+                let sin_theta = (PI * (y as Decimal + 0.5) / height as Decimal).sin();
+                Distribution::new(row.iter().map(|pixel| {
+                    //a little bit everywhere, so every direction can be picked
+                    (luminance(*pixel) + 1e-6) * sin_theta
+                }))
+            })
+            .collect();
+        let rows = Distribution::new(columns.iter().map(Distribution::total));
+
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: 0.0,
+            intensity: 1.0,
+            rows,
+            columns,
+        }
+    }
+
+    ///Makes a map from an image, decoding it to linear if needed
+    #[must_use]
+    pub fn from_image(image: LoadedImage) -> Self {
+        let LoadedImage {
+            width,
+            height,
+            pixels,
+            ..
+        } = image.into_linear();
+        Self::new(width, height, pixels)
+    }
+
+    ///Loads an image - usually an `.hdr`, see [`images::load`]
+    ///
+    /// # Errors
+    /// If the image can't be read
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let image = images::load(path)?;
+        if image.pixels.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "environment map has no pixels",
+            ));
+        }
+        Ok(Self::from_image(image))
+    }
+
+    ///Spins the map around the vertical axis
+    #[must_use]
+    pub const fn with_rotation(mut self, degrees: Decimal) -> Self {
+        self.rotation = degrees.to_radians();
+        self
+    }
+    ///Scales the brightness
+    #[must_use]
+    pub const fn with_intensity(mut self, intensity: Decimal) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    ///Image coordinates from 0-1, with v going down
+    fn uv(&self, direction: Vec3) -> (Decimal, Decimal) {
+        let phi = direction.x().atan2(-direction.z()) - self.rotation;
+        let theta = direction.y().clamp(-1.0, 1.0).acos();
+        ((phi / TAU + 0.5).rem_euclid(1.0), theta / PI)
+    }
+
+    fn direction(&self, u: Decimal, v: Decimal) -> Vec3 {
+        let phi = TAU * (u - 0.5) + self.rotation;
+        let (sin_theta, cos_theta) = (PI * v).sin_cos();
+        Vec3::new(sin_theta * phi.sin(), cos_theta, -sin_theta * phi.cos())
+    }
+
+    fn pixel(&self, x: isize, y: usize) -> Colour {
+        let x = x.rem_euclid(self.width.cast_signed()) as usize;
+        self.pixels[y.min(self.height - 1) * self.width + x]
+    }
+}
+
+fn luminance(colour: Colour) -> Decimal {
+    0.2126 * colour.x() + 0.7152 * colour.y() + 0.0722 * colour.z()
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: Vec3) -> Colour {
+        let (u, v) = self.uv(direction);
+
+        //bilinear, wrapping around horizontally
+        let x = u * self.width as Decimal - 0.5;
+        let y = (v * self.height as Decimal - 0.5).max(0.0);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as isize, y0 as usize);
+
+        let top = self.pixel(x0, y0) * (1.0 - fx) + self.pixel(x0 + 1, y0) * fx;
+        let bottom = self.pixel(x0, y0 + 1) * (1.0 - fx) + self.pixel(x0 + 1, y0 + 1) * fx;
+        (top * (1.0 - fy) + bottom * fy) * self.intensity
+    }
+
+    fn sample(&self, rng: &mut ThreadRng) -> Option<(Vec3, Colour, Decimal)> {
+        let y = self.rows.sample(rng.gen());
+        let x = self.columns[y].sample(rng.gen());
+
+        //anywhere in the pixel
+        let u = (x as Decimal + rng.gen::<Decimal>()) / self.width as Decimal;
+        let v = (y as Decimal + rng.gen::<Decimal>()) / self.height as Decimal;
+        let direction = self.direction(u, v);
+
+        let pdf = self.pdf(direction);
+        (pdf > 0.0).then(|| (direction, self.radiance(direction), pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> Decimal {
+        let (u, v) = self.uv(direction);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        let x = ((u * self.width as Decimal) as usize).min(self.width - 1);
+        let y = ((v * self.height as Decimal) as usize).min(self.height - 1);
+        //from the density over the image, to over the sphere
+        let image_pdf = self.rows.probability(y)
+            * self.columns[y].probability(x)
+            * (self.width * self.height) as Decimal;
+        image_pdf / (2.0 * PI * PI * sin_theta)
+    }
+}
@@ -0,0 +1,385 @@
+//! A physically based daylight sky, with the sun
+
+use super::{luminance, Environment};
+use crate::primitives::{
+    decimal_consts::{FRAC_PI_2, PI, TAU},
+    spectrum::{cie_xyz, planck, xyz_to_linear_srgb, MAX_WAVELENGTH, MIN_WAVELENGTH},
+    Colour, Decimal, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+
+///How far the sun's disk reaches from its middle, in radians
+pub const SUN_ANGULAR_RADIUS: Decimal = 0.004_65;
+///How much light is in one watt of light at the peak of human vision, in lumens
+const LUMINOUS_EFFICACY: Decimal = 683.0;
+///The temperature of the surface of the sun, in kelvin
+const SUN_TEMPERATURE: Decimal = 5778.0;
+
+///The direction to the sun (or anything else in the sky), from its elevation above the horizon and its azimuth clockwise from north, in degrees.
+///
+///North is -z and east is +x, so the middle of an [`EnvironmentMap`](super::EnvironmentMap) faces north.
+#[must_use]
+pub fn sun_direction(elevation: Decimal, azimuth: Decimal) -> Vec3 {
+    let (sin_elevation, cos_elevation) = elevation.to_radians().sin_cos();
+    let (sin_azimuth, cos_azimuth) = azimuth.to_radians().sin_cos();
+    Vec3::new(
+        cos_elevation * sin_azimuth,
+        sin_elevation,
+        -cos_elevation * cos_azimuth,
+    )
+}
+
+const fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+///Where the sun is in the sky, as `(elevation, azimuth)` in degrees for [`sun_direction`].
+///
+///`hours` is the time of day in UTC, so 13.5 is half past one. Latitude is positive to the north and longitude positive to the east. Uses the NOAA approximation, which is good to a few tenths of a degree.
+#[must_use]
+pub fn sun_position(
+    year: i32,
+    month: u32,
+    day: u32,
+    hours: Decimal,
+    latitude: Decimal,
+    longitude: Decimal,
+) -> (Decimal, Decimal) {
+    const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+    let leap = is_leap_year(year);
+    let month = month.clamp(1, 12);
+    let day_of_year = DAYS_BEFORE_MONTH[month as usize - 1] + day + u32::from(leap && month > 2);
+    let days_in_year = if leap { 366.0 } else { 365.0 };
+
+    //how far through the year we are, in radians
+    let year_angle =
+        TAU / days_in_year * (Decimal::from(day_of_year) - 1.0 + (hours - 12.0) / 24.0);
+    let (sin_1, cos_1) = year_angle.sin_cos();
+    let (sin_2, cos_2) = (2.0 * year_angle).sin_cos();
+    let (sin_3, cos_3) = (3.0 * year_angle).sin_cos();
+
+    //in minutes - how far the sun is ahead of the average sun, because the orbit is elliptical and tilted
+    let equation_of_time = 229.18
+        * (0.000_075 + 0.001_868 * cos_1
+            - 0.032_077 * sin_1
+            - 0.014_615 * cos_2
+            - 0.040_849 * sin_2);
+    let declination = 0.006_918 - 0.399_912 * cos_1 + 0.070_257 * sin_1 - 0.006_758 * cos_2
+        + 0.000_907 * sin_2
+        - 0.002_697 * cos_3
+        + 0.001_48 * sin_3;
+
+    let solar_minutes = hours * 60.0 + equation_of_time + 4.0 * longitude;
+    let hour_angle = (solar_minutes / 4.0 - 180.0).to_radians();
+    let latitude = latitude.to_radians();
+
+    let sin_elevation =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    let elevation = sin_elevation.clamp(-1.0, 1.0).asin();
+    //measured from south towards the west, so turn it around to be from the north
+    let azimuth = hour_angle
+        .sin()
+        .atan2(hour_angle.cos() * latitude.sin() - declination.tan() * latitude.cos());
+
+    (
+        elevation.to_degrees(),
+        (azimuth.to_degrees() + 180.0).rem_euclid(360.0),
+    )
+}
+
+///The coefficients of the Perez sky model, which give how the sky gets brighter near the horizon and around the sun
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Perez([Decimal; 5]);
+
+impl Perez {
+    ///`cos_zenith` is for the angle from straight up, and `gamma` is the angle from the sun
+    fn evaluate(self, cos_zenith: Decimal, gamma: Decimal) -> Decimal {
+        let [horizon, gradient, circumsolar, circumsolar_width, backscatter] = self.0;
+        let cos_gamma = gamma.cos();
+        (1.0 + horizon * (gradient / cos_zenith.max(1e-3)).exp())
+            * (1.0
+                + circumsolar * (circumsolar_width * gamma).exp()
+                + backscatter * cos_gamma * cos_gamma)
+    }
+}
+
+///The clear sky from Preetham, Shirley and Smits 1999, "A Practical Analytic Model for Daylight", with the sun.
+///
+///Radiance is in thousands of candela per square metre multiplied by the intensity, which defaults to a level where something white in the midday sun is about 1. Below the horizon is a flat diffuse ground lit by the sky and the sun. It doesn't have twilight, so the sky stays as if the sun were on the horizon when it sets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreethamSky {
+    sun: Vec3,
+    turbidity: Decimal,
+    ///the sun's angle from straight up, clamped to the horizon
+    sun_zenith: Decimal,
+    ///for the brightness, then the x and y chromaticity
+    perez: [Perez; 3],
+    ///the brightness and chromaticity straight up, divided by the perez value there so it can be used for any direction
+    zenith: [Decimal; 3],
+    sun_radiance: Colour,
+    ground_albedo: Colour,
+    ground: Colour,
+    intensity: Decimal,
+    ///the chance of sampling the sun rather than the rest of the sky
+    sun_probability: Decimal,
+}
+
+impl PreethamSky {
+    ///A sky with the sun at `elevation` and `azimuth` in degrees (see [`sun_direction`]), and a `turbidity` from 2 for a very clear sky to 10 for a hazy one
+    #[must_use]
+    pub fn new(elevation: Decimal, azimuth: Decimal, turbidity: Decimal) -> Self {
+        let turbidity = turbidity.clamp(1.7, 10.0);
+        let sun = sun_direction(elevation, azimuth);
+        let sun_zenith = sun.y().clamp(0.0, 1.0).acos();
+
+        let t = turbidity;
+        let perez = [
+            Perez([
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ]),
+            Perez([
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ]),
+            Perez([
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ]),
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * 2.0f64.mul_add(-sun_zenith, PI);
+        let brightness = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let chromaticity = |coefficients: [[Decimal; 4]; 3]| {
+            let theta = [sun_zenith.powi(3), sun_zenith * sun_zenith, sun_zenith, 1.0];
+            let [a, b, c] = coefficients.map(|row| {
+                row.iter()
+                    .zip(theta)
+                    .map(|(coefficient, theta)| coefficient * theta)
+                    .sum::<Decimal>()
+            });
+            t * t * a + t * b + c
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        let mut zenith = [brightness.max(0.0), x, y];
+        for (zenith, perez) in zenith.iter_mut().zip(perez) {
+            *zenith /= perez.evaluate(1.0, sun_zenith);
+        }
+
+        let sun_radiance = if sun.y() > 0.0 {
+            sun_radiance(sun.y(), turbidity)
+        } else {
+            Colour::default()
+        };
+
+        let mut sky = Self {
+            sun,
+            turbidity,
+            sun_zenith,
+            perez,
+            zenith,
+            sun_radiance,
+            ground_albedo: Colour::new(0.3, 0.3, 0.3),
+            ground: Colour::default(),
+            intensity: 0.03,
+            sun_probability: 0.0,
+        };
+        sky.light_ground();
+
+        //in proportion to roughly how much light each gives
+        let sun_power = luminance(sun_radiance) * solid_angle();
+        let sky_power = luminance(sky.sky_radiance(Vec3::new(0.0, 1.0, 0.0))) * TAU;
+        sky.sun_probability = if sun_power > 0.0 {
+            sun_power / (sun_power + sky_power)
+        } else {
+            0.0
+        };
+        sky
+    }
+
+    ///The colour of the ground, which is lit by the sky and the sun
+    #[must_use]
+    pub fn with_ground_albedo(mut self, albedo: Colour) -> Self {
+        self.ground_albedo = albedo;
+        self.light_ground();
+        self
+    }
+    ///Scales the brightness
+    #[must_use]
+    pub const fn with_intensity(mut self, intensity: Decimal) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    ///The unit vector towards the middle of the sun
+    #[must_use]
+    pub const fn sun(&self) -> Vec3 {
+        self.sun
+    }
+    #[must_use]
+    pub const fn turbidity(&self) -> Decimal {
+        self.turbidity
+    }
+
+    ///Without the sun or the ground, or the intensity
+    fn sky_radiance(&self, direction: Vec3) -> Colour {
+        //the sky doesn't go below the horizon
+        let cos_zenith = direction.y().max(0.0);
+        let gamma = direction.dot(self.sun_at_horizon()).clamp(-1.0, 1.0).acos();
+
+        let [brightness, x, y] = [0, 1, 2]
+            .map(|channel| self.zenith[channel] * self.perez[channel].evaluate(cos_zenith, gamma));
+        if y <= 0.0 {
+            return Colour::default();
+        }
+        let xyz = Vec3::new(
+            x * brightness / y,
+            brightness,
+            (1.0 - x - y) * brightness / y,
+        );
+
+        let colour = xyz_to_linear_srgb(xyz);
+        Colour::new(
+            colour.x().max(0.0),
+            colour.y().max(0.0),
+            colour.z().max(0.0),
+        )
+    }
+
+    ///The sun, moved up to the horizon if it has set, for the shape of the sky
+    fn sun_at_horizon(&self) -> Vec3 {
+        let (sin_zenith, cos_zenith) = self.sun_zenith.sin_cos();
+        let horizontal = Vec3::new(self.sun.x(), 0.0, self.sun.z());
+        if horizontal.magnitude_squared() > 0.0 {
+            horizontal.unit() * sin_zenith + Vec3::new(0.0, cos_zenith, 0.0)
+        } else {
+            Vec3::new(0.0, 1.0, 0.0)
+        }
+    }
+
+    fn in_sun(&self, direction: Vec3) -> bool {
+        direction.dot(self.sun) >= SUN_ANGULAR_RADIUS.cos()
+    }
+
+    ///Works out how bright the ground is, by adding up the light falling on it
+    fn light_ground(&mut self) {
+        const STEPS: usize = 32;
+
+        //the sky, by the midpoint rule over the hemisphere
+        let mut irradiance = Colour::default();
+        for zenith_step in 0..STEPS {
+            let zenith = FRAC_PI_2 * (zenith_step as Decimal + 0.5) / STEPS as Decimal;
+            let (sin_zenith, cos_zenith) = zenith.sin_cos();
+            for azimuth_step in 0..2 * STEPS {
+                let azimuth = TAU * (azimuth_step as Decimal + 0.5) / (2 * STEPS) as Decimal;
+                let direction = Vec3::new(
+                    sin_zenith * azimuth.cos(),
+                    cos_zenith,
+                    sin_zenith * azimuth.sin(),
+                );
+                irradiance += self.sky_radiance(direction) * (cos_zenith * sin_zenith);
+            }
+        }
+        irradiance *= FRAC_PI_2 / STEPS as Decimal * TAU / (2 * STEPS) as Decimal;
+
+        irradiance += self.sun_radiance * (solid_angle() * self.sun.y().max(0.0));
+        self.ground = self.ground_albedo * irradiance / PI;
+    }
+}
+
+///The solid angle of the sun's disk
+fn solid_angle() -> Decimal {
+    TAU * (1.0 - SUN_ANGULAR_RADIUS.cos())
+}
+
+///The sun's radiance after going through the atmosphere, with `cos_zenith` of the sun above the horizon.
+///
+///Uses Preetham et al.'s Rayleigh and aerosol scattering, but not absorption by ozone and water.
+fn sun_radiance(cos_zenith: Decimal, turbidity: Decimal) -> Colour {
+    const STEP: Decimal = 5.0;
+    const STEPS: usize = 94;
+
+    //how much air the light goes through, relative to straight up - from Kasten 1966
+    let zenith_degrees = cos_zenith.acos().to_degrees();
+    let air_mass = 1.0 / (cos_zenith + 0.15 * (93.885 - zenith_degrees).powf(-1.253));
+    //Ångström's formula for haze
+    let beta = 0.046_08 * turbidity - 0.045_86;
+
+    let xyz = (0..=STEPS)
+        .map(|step| {
+            let wavelength = MIN_WAVELENGTH + step as Decimal * STEP;
+            let micrometres = wavelength / 1000.0;
+            let rayleigh = -0.008_735 * micrometres.powf(-4.08) * air_mass;
+            let aerosol = -beta * micrometres.powf(-1.3) * air_mass;
+            cie_xyz(wavelength)
+                * (planck(wavelength, SUN_TEMPERATURE) * (rayleigh + aerosol).exp() * STEP)
+        })
+        .fold(Vec3::default(), |total, xyz| total + xyz);
+    debug_assert!(MIN_WAVELENGTH + STEPS as Decimal * STEP <= MAX_WAVELENGTH);
+
+    //to thousands of candela
+    let colour = xyz_to_linear_srgb(xyz * (LUMINOUS_EFFICACY / 1000.0));
+    Colour::new(
+        colour.x().max(0.0),
+        colour.y().max(0.0),
+        colour.z().max(0.0),
+    )
+}
+
+impl Environment for PreethamSky {
+    fn radiance(&self, direction: Vec3) -> Colour {
+        let radiance = if direction.y() < 0.0 {
+            self.ground
+        } else if self.in_sun(direction) {
+            self.sky_radiance(direction) + self.sun_radiance
+        } else {
+            self.sky_radiance(direction)
+        };
+        radiance * self.intensity
+    }
+
+    fn sample(&self, rng: &mut ThreadRng) -> Option<(Vec3, Colour, Decimal)> {
+        let direction = if rng.gen::<Decimal>() < self.sun_probability {
+            //uniformly over the disk of the sun
+            let cos_theta = 1.0 - rng.gen::<Decimal>() * (1.0 - SUN_ANGULAR_RADIUS.cos());
+            let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+            let phi = TAU * rng.gen::<Decimal>();
+            let (tangent, bitangent) = self.sun.orthonormal_basis();
+            (tangent * phi.cos() + bitangent * phi.sin()) * sin_theta + self.sun * cos_theta
+        } else {
+            Vec3::random_unit_vector(rng)
+        };
+
+        let pdf = self.pdf(direction);
+        (pdf > 0.0).then(|| (direction, self.radiance(direction), pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> Decimal {
+        let sun = if self.in_sun(direction) {
+            self.sun_probability / solid_angle()
+        } else {
+            0.0
+        };
+        sun + (1.0 - self.sun_probability) / (4.0 * PI)
+    }
+}
@@ -0,0 +1,541 @@
+//! Integrators work out the light coming back along camera rays - the path tracer is the one for final images, and the others are quicker approximations or views for debugging scenes
+
+pub mod ambient_occlusion;
+pub mod aov;
+pub mod bidirectional;
+pub mod debug;
+pub mod direct;
+pub mod path;
+pub mod photon_mapping;
+pub mod whitted;
+
+use crate::primitives::{
+    collisions::HitRecord,
+    decimal_consts::PI,
+    integrators::aov::{Aov, AovSample},
+    lights::Light,
+    media::Medium,
+    scene::Scene,
+    spectrum::Wavelengths,
+    Colour, Decimal, Point3, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    ops::AddAssign,
+};
+
+pub trait Integrator: Debug + Send + Sync {
+    ///The linear sRGB colour seen along a camera `ray`, where `media` is the stack of volumes the ray starts inside, with the innermost last
+    fn colour<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour;
+
+    ///Like [`Integrator::colour`], but light which reaches the camera somewhere else on the image is given to `splat`, with the `s` and `t` (like in [`Camera::get_ray`](crate::primitives::camera::Camera::get_ray)) where it lands.
+    ///
+    ///Images are rendered with this. Only integrators tracing paths from lights need it - by default, nothing is splatted.
+    fn colour_and_splat<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        _splat: &mut dyn FnMut(Decimal, Decimal, Colour),
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        self.colour(ray, scene, media, rng)
+    }
+
+    ///Like [`Integrator::colour_and_splat`], but also fills in `aovs` with the first surface it hit and the light split up (in linear sRGB).
+    ///
+    ///By default, they're left empty.
+    fn colour_with_aovs<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        splat: &mut dyn FnMut(Decimal, Decimal, Colour),
+        _aovs: &mut AovSample<'a>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        self.colour_and_splat(ray, scene, media, splat, rng)
+    }
+
+    ///The [`Aov`]s that [`Integrator::colour_with_aovs`] fills in - only the [`PathTracer`](path::PathTracer) tracks them, so by default there are none
+    fn aovs(&self) -> &'static [Aov] {
+        &[]
+    }
+}
+
+///The light along a camera ray split up by how it got there, for compositing - direct and indirect add up to everything but the emission, and so do diffuse and specular
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct LightPasses {
+    ///seen straight from the camera, including the background
+    pub emission: Colour,
+    ///bounced once on the way to the camera
+    pub direct: Colour,
+    ///bounced more than once
+    pub indirect: Colour,
+    ///bounced diffusely off the first surface
+    pub diffuse: Colour,
+    ///reflected or refracted by the first surface, whether it's rough or smooth
+    pub specular: Colour,
+}
+
+impl LightPasses {
+    ///Adds `light` which got to the camera after `bounces` bounces, where `diffuse` of it (in each channel, from 0-1) scattered diffusely off the first surface, and gives it back
+    fn add(&mut self, light: Colour, bounces: usize, diffuse: Colour) -> Colour {
+        if bounces == 0 {
+            self.emission += light;
+            return light;
+        }
+
+        if bounces == 1 {
+            self.direct += light;
+        } else {
+            self.indirect += light;
+        }
+        self.diffuse += light * diffuse;
+        self.specular += light * (Colour::splat(1.0) - diffuse);
+        light
+    }
+
+    ///Every pass converted back to linear sRGB, if they were traced spectrally along `ray`
+    fn to_rgb(self, ray: Ray) -> Self {
+        Self {
+            emission: to_rgb(ray, self.emission),
+            direct: to_rgb(ray, self.direct),
+            indirect: to_rgb(ray, self.indirect),
+            diffuse: to_rgb(ray, self.diffuse),
+            specular: to_rgb(ray, self.specular),
+        }
+    }
+}
+
+impl AddAssign for LightPasses {
+    fn add_assign(&mut self, rhs: Self) {
+        self.emission += rhs.emission;
+        self.direct += rhs.direct;
+        self.indirect += rhs.indirect;
+        self.diffuse += rhs.diffuse;
+        self.specular += rhs.specular;
+    }
+}
+
+///Light found along `ray`, converted back to linear sRGB if it was traced spectrally
+fn to_rgb(ray: Ray, light: Colour) -> Colour {
+    ray.wavelengths()
+        .map_or(light, |wavelengths| wavelengths.to_rgb(light))
+}
+
+///A colour from a hash of `id`, so things next to each other look different
+fn id_colour(id: impl Hash) -> Colour {
+    let mut hasher = DefaultHasher::new();
+    id.hash(&mut hasher);
+    let hash = hasher.finish();
+    let channel = |shift: u32| ((hash >> shift) & 0xff) as Decimal / 255.0;
+    Colour::new(channel(0), channel(8), channel(16))
+}
+
+///Light given off by the surface at `hit`, for the wavelengths of the `ray` which hit it
+fn emitted(ray: Ray, hit: &HitRecord) -> Colour {
+    ray.wavelengths().map_or_else(
+        || hit.material.emitted(*hit),
+        |wavelengths| hit.material.emitted_spectrum(*hit, &wavelengths),
+    )
+}
+
+///How much light is left after scattering off `hit` with `attenuation`, for the wavelengths of the `ray` which hit it
+fn scattered_weight(ray: Ray, hit: &HitRecord, attenuation: Colour) -> Colour {
+    let weight = ray.reflectance(attenuation);
+    if ray.wavelengths().is_some() && hit.material.is_dispersive() {
+        //the other wavelengths would have gone somewhere else
+        weight * Wavelengths::hero_only()
+    } else {
+        weight
+    }
+}
+
+///How a ray was made, which changes how much of the environment it sees when it leaves the scene
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Bounce {
+    ///straight from the camera, so it sees the background
+    Camera,
+    ///from something which can't be evaluated for sampled light, so this is the only way it finds the environment
+    Specular,
+    ///sampled with this probability density, which is weighted against sampling the environment directly
+    Sampled(Decimal),
+}
+
+///The environment seen by a `ray` which doesn't hit anything, weighted against having sampled it directly
+fn missed(ray: Ray, scene: &Scene, bounce: Bounce) -> Colour {
+    let direction = ray.direction().unit();
+    match bounce {
+        Bounce::Camera => scene.background().radiance(direction),
+        Bounce::Specular => scene.environment().radiance(direction),
+        Bounce::Sampled(pdf) => {
+            let environment = scene.environment();
+            environment.radiance(direction) * power_heuristic(pdf, environment.pdf(direction))
+        }
+    }
+}
+
+///Weights for multiple importance sampling - the chance of one strategy finding a path, compared to all of them
+fn power_heuristic(pdf: Decimal, other_pdf: Decimal) -> Decimal {
+    let (squared, other_squared) = (pdf * pdf, other_pdf * other_pdf);
+    if squared + other_squared > 0.0 {
+        squared / (squared + other_squared)
+    } else {
+        0.0
+    }
+}
+
+fn is_innermost(media: &[&dyn Medium], medium: &dyn Medium) -> bool {
+    media
+        .last()
+        .is_some_and(|last| std::ptr::addr_eq(*last, medium))
+}
+
+///Next event estimation - light sampled from the environment and every light, where `evaluate` gives the BSDF times the cosine for a direction, its pdf, and the part of the BSDF from diffuse lobes (like [`Material::evaluate_lobes`](crate::primitives::materials::Material::evaluate_lobes)).
+///
+///`ray_in` is the ray which got to `point`, for its wavelengths. Gives the light, and the part of it which scattered diffusely.
+fn direct_light<'a>(
+    scene: &'a Scene,
+    ray_in: Ray,
+    point: Point3,
+    media: &[&'a dyn Medium],
+    rng: &mut ThreadRng,
+    evaluate: impl Fn(Vec3, &mut ThreadRng) -> Option<(Colour, Decimal, Colour)>,
+) -> (Colour, Colour) {
+    let (environment, environment_diffuse) =
+        sample_environment(scene, ray_in, point, media, rng, &evaluate);
+    let (lights, lights_diffuse) = sample_lights(
+        scene,
+        scene.lights().iter(),
+        ray_in,
+        point,
+        media,
+        rng,
+        &evaluate,
+    );
+    (environment + lights, environment_diffuse + lights_diffuse)
+}
+
+///Light sampled from each of `lights`, like in [`direct_light`]
+fn sample_lights<'a>(
+    scene: &'a Scene,
+    lights: impl Iterator<Item = &'a dyn Light>,
+    ray_in: Ray,
+    point: Point3,
+    media: &[&'a dyn Medium],
+    rng: &mut ThreadRng,
+    evaluate: impl Fn(Vec3, &mut ThreadRng) -> Option<(Colour, Decimal, Colour)>,
+) -> (Colour, Colour) {
+    let (mut direct, mut diffuse) = (Colour::default(), Colour::default());
+
+    //these can't be hit, so there's nothing to weight them against
+    for light in lights {
+        let Some(sample) = light.sample(point) else {
+            continue;
+        };
+        let Some((value, _, diffuse_value)) = evaluate(sample.direction, rng) else {
+            continue;
+        };
+        if value.near_zero() || sample.irradiance.near_zero() {
+            continue;
+        }
+
+        let ray = Ray::new(point, sample.direction);
+        let light = ray_in.light(sample.irradiance)
+            * ray_in.reflectance(unoccluded(scene, ray, sample.distance, media, rng));
+        direct += ray_in.reflectance(value) * light;
+        diffuse += ray_in.reflectance(diffuse_value) * light;
+    }
+
+    (direct, diffuse)
+}
+
+///Light from a direction sampled from the environment, weighted against the chance of `evaluate` finding it by itself
+fn sample_environment<'a>(
+    scene: &'a Scene,
+    ray_in: Ray,
+    point: Point3,
+    media: &[&'a dyn Medium],
+    rng: &mut ThreadRng,
+    evaluate: impl Fn(Vec3, &mut ThreadRng) -> Option<(Colour, Decimal, Colour)>,
+) -> (Colour, Colour) {
+    let nothing = (Colour::default(), Colour::default());
+    let Some((direction, radiance, light_pdf)) = scene.environment().sample(rng) else {
+        return nothing;
+    };
+    let Some((value, scattering_pdf, diffuse)) = evaluate(direction, rng) else {
+        return nothing;
+    };
+    if value.near_zero() || radiance.near_zero() {
+        return nothing;
+    }
+
+    let light = ray_in.light(radiance)
+        * ray_in.reflectance(unoccluded(
+            scene,
+            Ray::new(point, direction),
+            Decimal::INFINITY,
+            media,
+            rng,
+        ))
+        * (power_heuristic(light_pdf, scattering_pdf) / light_pdf);
+    (
+        ray_in.reflectance(value) * light,
+        ray_in.reflectance(diffuse) * light,
+    )
+}
+
+///Light arriving at `hit` straight from lights, the environment and emissive surfaces, for the `ray` which hit it.
+///
+///As well as sampling lights, this scatters once to find emissive surfaces and the rest of the environment.
+fn direct_at<'a>(
+    scene: &'a Scene,
+    ray: Ray,
+    hit: &HitRecord<'a>,
+    media: &[&'a dyn Medium],
+    rng: &mut ThreadRng,
+) -> Colour {
+    let (direct, _) = direct_light(scene, ray, hit.point, media, rng, |direction, rng| {
+        hit.material.evaluate_lobes(ray, *hit, direction, rng)
+    });
+    let Some((attenuation, scattered, _)) = hit.material.scatter(ray, *hit, rng) else {
+        return direct;
+    };
+    let bounce = hit
+        .material
+        .evaluate(ray, *hit, scattered.direction(), rng)
+        .map_or(Bounce::Specular, |(_, pdf)| Bounce::Sampled(pdf));
+    let weight = scattered_weight(ray, hit, attenuation);
+
+    let scattered = ray.continued(scattered);
+    let mut media = media.to_vec();
+    cross(&mut media, hit, scattered.direction());
+    let (transmittance, found) =
+        first_surface(scene, scattered, Decimal::INFINITY, &mut media, rng);
+    let light = found.map_or_else(
+        || scattered.light(missed(scattered, scene, bounce)),
+        |mut found| {
+            found.set_footprint(scattered);
+            emitted(scattered, &found)
+        },
+    );
+    direct + weight * ray.reflectance(transmittance) * light
+}
+
+///Going through the boundary of a volume along `direction` means going in or out of its medium
+fn cross<'a>(media: &mut Vec<&'a dyn Medium>, hit: &HitRecord<'a>, direction: Vec3) {
+    if direction.dot(hit.geometric_normal) >= 0.0 {
+        return;
+    }
+    match hit.material.medium() {
+        Some(medium) if hit.front_face => media.push(medium),
+        Some(medium) if is_innermost(media, medium) => {
+            media.pop();
+        }
+        _ => {}
+    }
+}
+
+///How much light makes it from `distance` along `ray` (which can be infinite) back to its origin - only invisible boundaries and the media inside them let any through
+fn unoccluded<'a>(
+    scene: &'a Scene,
+    ray: Ray,
+    distance: Decimal,
+    media: &[&'a dyn Medium],
+    rng: &mut ThreadRng,
+) -> Colour {
+    match first_surface(scene, ray, distance, &mut media.to_vec(), rng) {
+        (transmittance, None) => transmittance,
+        (_, Some(_)) => Colour::default(),
+    }
+}
+
+///The first surface within `distance` along `ray` which light can't go straight through, with its material resolved, and how much light makes it back from there (or from `distance` if there isn't one).
+///
+///`media` is updated with the volumes gone in and out of on the way.
+fn first_surface<'a>(
+    scene: &'a Scene,
+    mut ray: Ray,
+    mut distance: Decimal,
+    media: &mut Vec<&'a dyn Medium>,
+    rng: &mut ThreadRng,
+) -> (Colour, Option<HitRecord<'a>>) {
+    //so we can't get stuck going back and forth on a surface
+    const MAX_BOUNDARIES: usize = 64;
+
+    let mut transmittance = Colour::splat(1.0);
+
+    for _ in 0..MAX_BOUNDARIES {
+        let Some(mut hit) = scene.world().hit(ray, 0.00001, distance) else {
+            //media are only between surfaces, so they don't get in the way of the sky
+            return match media.last() {
+                Some(medium) if distance.is_finite() => (
+                    transmittance * medium.transmittance(ray, distance, rng),
+                    None,
+                ),
+                _ => (transmittance, None),
+            };
+        };
+        if let Some(medium) = media.last() {
+            transmittance *= medium.transmittance(ray, hit.time, rng);
+        }
+        match hit.material.resolve(&hit, rng) {
+            Some(material) if !material.is_invisible() => {
+                hit.material = material;
+                return (transmittance, Some(hit));
+            }
+            _ => {}
+        }
+
+        cross(media, &hit, ray.direction());
+        ray = ray.restarted_at(hit.point);
+        distance -= hit.time;
+    }
+
+    (Colour::default(), None)
+}
+
+///Something paths from lights can start from
+#[derive(Debug, Copy, Clone)]
+enum Emitter<'a> {
+    ///a light with a position, which nothing can hit
+    Point(&'a dyn Light),
+    ///one of the scene's area lights, by its index
+    Area(usize),
+}
+
+impl Emitter<'_> {
+    ///A direction for light to leave a point with `normal` (zero for point lights) in, and its probability density (with respect to solid angle)
+    fn emit(self, normal: Vec3, rng: &mut ThreadRng) -> (Vec3, Decimal) {
+        match self {
+            Self::Point(_) => (Vec3::random_unit_vector(rng), 1.0 / (4.0 * PI)),
+            Self::Area(_) => {
+                let mut direction = normal + Vec3::random_unit_vector(rng);
+                if direction.near_zero() {
+                    direction = normal;
+                }
+                let direction = direction.unit();
+                (direction, self.pdf(normal, direction))
+            }
+        }
+    }
+
+    ///The probability density (with respect to solid angle) of [`Emitter::emit`] giving `direction`
+    fn pdf(self, normal: Vec3, direction: Vec3) -> Decimal {
+        match self {
+            Self::Point(_) => 1.0 / (4.0 * PI),
+            Self::Area(_) => normal.dot(direction).max(0.0) / PI,
+        }
+    }
+
+    ///The light leaving `point` along `direction` - the intensity for point lights, and the radiance times the cosine for area lights.
+    ///
+    ///`ray` is for its wavelengths.
+    fn emitted(
+        self,
+        scene: &Scene,
+        point: Point3,
+        normal: Vec3,
+        direction: Vec3,
+        ray: Ray,
+    ) -> Colour {
+        match self {
+            Self::Point(light) => ray.light(light.intensity(direction)),
+            Self::Area(index) => {
+                //a tiny ray back at the point finds the material, and which side it's on
+                const OFFSET: Decimal = 1e-4;
+
+                let probe = ray.continued(Ray::new(point + OFFSET * direction, -direction));
+                scene.area_lights()[index]
+                    .hit(probe, 0.0, 2.0 * OFFSET)
+                    .map_or_else(Colour::default, |hit| emitted(probe, &hit))
+                    * normal.dot(direction).abs()
+            }
+        }
+    }
+}
+
+///A point on an [`Emitter`] to start a path from, picked with probability density `pdf` (with respect to area)
+#[derive(Debug, Copy, Clone)]
+struct EmitterSample<'a> {
+    emitter: Emitter<'a>,
+    point: Point3,
+    ///zero for point lights
+    normal: Vec3,
+    pdf: Decimal,
+}
+
+///Everything paths can start from - the scene's area lights, and lights with a position
+struct Emitters<'a> {
+    scene: &'a Scene,
+    points: Vec<&'a dyn Light>,
+}
+
+impl<'a> Emitters<'a> {
+    fn new(scene: &'a Scene) -> Self {
+        Self {
+            scene,
+            points: scene
+                .lights()
+                .iter()
+                .filter(|light| light.position().is_some())
+                .collect(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.scene.area_lights().len() + self.points.len()
+    }
+
+    ///A point on a light picked uniformly at random, to start a path from
+    fn sample(&self, rng: &mut ThreadRng) -> Option<EmitterSample<'a>> {
+        if self.len() == 0 {
+            return None;
+        }
+        let pick = 1.0 / self.len() as Decimal;
+        let index = rng.gen_range(0..self.len());
+        let area_lights = self.scene.area_lights();
+
+        if let Some(light) = area_lights.get(index) {
+            let (point, normal) = light.sample_point(rng);
+            Some(EmitterSample {
+                emitter: Emitter::Area(index),
+                point,
+                normal,
+                pdf: pick / light.area(),
+            })
+        } else {
+            let light = self.points[index - area_lights.len()];
+            Some(EmitterSample {
+                emitter: Emitter::Point(light),
+                point: light.position()?,
+                normal: Vec3::default(),
+                pdf: pick,
+            })
+        }
+    }
+
+    ///The probability density (with respect to area) of [`Emitters::sample`] picking a point on the area light with `index`
+    fn pdf(&self, index: usize) -> Decimal {
+        1.0 / (self.len() as Decimal * self.scene.area_lights()[index].area())
+    }
+
+    ///The index of the area light which `ray` hit at `hit`, if it was one
+    fn find(&self, ray: Ray, hit: &HitRecord) -> Option<usize> {
+        self.scene.area_lights().iter().position(|light| {
+            light
+                .hit(ray, 0.00001, Decimal::INFINITY)
+                .is_some_and(|found| (found.time - hit.time).abs() <= 1e-6 * hit.time.max(1.0))
+        })
+    }
+}
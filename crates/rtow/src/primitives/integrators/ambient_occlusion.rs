@@ -0,0 +1,41 @@
+//! Ambient occlusion, which shows how enclosed each point is
+
+use super::{first_surface, unoccluded, Integrator};
+use crate::primitives::{media::Medium, scene::Scene, Colour, Decimal, Ray, Vec3};
+use rand::rngs::ThreadRng;
+
+///How much of the hemisphere above the first surface seen is open for `distance`, weighted by the cosine - a quick way to check shapes without setting up any lights or materials.
+///
+///The sky is black.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AmbientOcclusion {
+    distance: Decimal,
+}
+
+impl AmbientOcclusion {
+    #[must_use]
+    pub const fn new(distance: Decimal) -> Self {
+        Self { distance }
+    }
+}
+
+impl Integrator for AmbientOcclusion {
+    fn colour<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let (_, Some(hit)) = first_surface(scene, ray, Decimal::INFINITY, media, rng) else {
+            return Colour::default();
+        };
+
+        let mut direction = hit.normal + Vec3::random_unit_vector(rng);
+        if direction.near_zero() {
+            direction = hit.normal;
+        }
+        let ray = Ray::new(hit.point, direction.unit());
+        unoccluded(scene, ray, self.distance, media, rng)
+    }
+}
@@ -0,0 +1,120 @@
+//! Arbitrary output variables - buffers rendered in the same pass as the image, for compositing and as the inputs to denoisers
+
+use super::{id_colour, LightPasses};
+use crate::primitives::{collisions::HitRecord, Colour, Decimal};
+
+///Something rendered alongside the image. Those about the first surface are black where the camera sees the sky, or the path scatters in a medium before getting to a surface.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Aov {
+    ///the colour of the first surface, from [`Material::albedo`](crate::primitives::materials::Material::albedo)
+    Albedo,
+    ///the shading normal of the first surface, from -1 to 1
+    Normal,
+    ///the distance from the camera to the first surface
+    Depth,
+    ///where the first surface is
+    Position,
+    ///a different colour for each object in the world
+    ObjectId,
+    ///a different colour for each material
+    MaterialId,
+    ///[`LightPasses::direct`]
+    Direct,
+    ///[`LightPasses::indirect`]
+    Indirect,
+    ///[`LightPasses::diffuse`]
+    Diffuse,
+    ///[`LightPasses::specular`]
+    Specular,
+    ///[`LightPasses::emission`]
+    Emission,
+}
+
+impl Aov {
+    pub const ALL: [Self; 11] = [
+        Self::Albedo,
+        Self::Normal,
+        Self::Depth,
+        Self::Position,
+        Self::ObjectId,
+        Self::MaterialId,
+        Self::Direct,
+        Self::Indirect,
+        Self::Diffuse,
+        Self::Specular,
+        Self::Emission,
+    ];
+
+    ///What it's called, for naming its file
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Albedo => "albedo",
+            Self::Normal => "normal",
+            Self::Depth => "depth",
+            Self::Position => "position",
+            Self::ObjectId => "object_id",
+            Self::MaterialId => "material_id",
+            Self::Direct => "direct",
+            Self::Indirect => "indirect",
+            Self::Diffuse => "diffuse",
+            Self::Specular => "specular",
+            Self::Emission => "emission",
+        }
+    }
+
+    ///Whether it's about the [`FirstHit`], rather than the [`LightPasses`]
+    #[must_use]
+    pub const fn is_geometric(self) -> bool {
+        matches!(
+            self,
+            Self::Albedo
+                | Self::Normal
+                | Self::Depth
+                | Self::Position
+                | Self::ObjectId
+                | Self::MaterialId
+        )
+    }
+
+    ///Its value for one `sample`
+    #[must_use]
+    pub fn value(self, sample: &AovSample) -> Colour {
+        let passes = &sample.passes;
+        match (self, sample.first) {
+            (Self::Direct, _) => passes.direct,
+            (Self::Indirect, _) => passes.indirect,
+            (Self::Diffuse, _) => passes.diffuse,
+            (Self::Specular, _) => passes.specular,
+            (Self::Emission, _) => passes.emission,
+            (_, None) => Colour::default(),
+            (Self::Albedo, Some(first)) => first.albedo,
+            (Self::Normal, Some(first)) => first.hit.normal.unit(),
+            (Self::Depth, Some(first)) => Colour::splat(first.distance),
+            (Self::Position, Some(first)) => first.hit.point,
+            (Self::ObjectId, Some(first)) => id_colour(first.object),
+            (Self::MaterialId, Some(first)) => id_colour(first.material),
+        }
+    }
+}
+
+///What an integrator found along one camera ray besides its colour, for filling in the [`Aov`]s
+#[derive(Debug, Default, Copy, Clone)]
+pub struct AovSample<'a> {
+    ///[`None`] if it didn't get to a surface
+    pub first: Option<FirstHit<'a>>,
+    pub passes: LightPasses,
+}
+
+///The first surface a camera ray hits, skipping holes and invisible boundaries
+#[derive(Debug, Copy, Clone)]
+pub struct FirstHit<'a> {
+    pub hit: HitRecord<'a>,
+    ///the index of the object in the world
+    pub object: usize,
+    ///from the origin of the camera ray
+    pub distance: Decimal,
+    pub albedo: Colour,
+    ///from [`Scene::material_id`](crate::primitives::scene::Scene::material_id)
+    pub material: Option<usize>,
+}
@@ -0,0 +1,665 @@
+//! Bidirectional path tracing, which traces paths from the camera and from the lights, and joins them up every way it can
+
+use super::{
+    emitted, missed, sample_environment, sample_lights, scattered_weight, to_rgb, unoccluded,
+    Bounce, Emitter, EmitterSample, Emitters, Integrator,
+};
+use crate::primitives::{
+    camera::Camera, collisions::HitRecord, media::Medium, scene::Scene, Colour, Decimal, Point3,
+    Ray, Vec3,
+};
+use rand::rngs::ThreadRng;
+
+///Traces paths from both the camera and the lights, and joins them up every way it can.
+///
+///Every vertex on the camera path is joined to every vertex on the light path, weighting each way of making a path against the others with multiple importance sampling.
+///
+///Light which only gets to the camera through small openings, or focused by glass into caustics, is found far more often than by the [`PathTracer`](super::path::PathTracer). Paths from lights can land anywhere on the image, so they are splatted with [`Integrator::colour_and_splat`].
+///
+///Paths start from the scene's [area lights](Scene::with_area_lights) and lights with a [position](crate::primitives::lights::Light::position) - the environment and other lights are sampled from the camera path, like in the path tracer. Volumes are treated as empty, so scenes with media should use the path tracer.
+#[derive(Debug, Clone)]
+pub struct BidirectionalPathTracer {
+    ///the camera the image is rendered with, for joining paths from lights to it
+    camera: Camera,
+    max_depth: usize,
+}
+
+impl BidirectionalPathTracer {
+    ///`camera` needs to be the one the image is rendered with, and paths can have up to `max_depth` bounces
+    #[must_use]
+    pub const fn new(camera: Camera, max_depth: usize) -> Self {
+        Self { camera, max_depth }
+    }
+}
+
+impl Integrator for BidirectionalPathTracer {
+    fn colour<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        //light which only reaches the camera by joining paths from lights to it is lost
+        self.colour_and_splat(ray, scene, media, &mut |_, _, _| {}, rng)
+    }
+
+    fn colour_and_splat<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        _media: &mut Vec<&'a dyn Medium>,
+        splat: &mut dyn FnMut(Decimal, Decimal, Colour),
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let emitters = Emitters::new(scene);
+
+        let mut camera_path = vec![Vertex::camera(ray.origin())];
+        let pdf = self.camera.pdf_direction(ray.origin(), ray.direction());
+        let escaped = self.walk(
+            scene,
+            &emitters,
+            ray,
+            Colour::splat(1.0),
+            pdf,
+            &mut camera_path,
+            self.max_depth + 2,
+            rng,
+        );
+
+        let mut light_path = vec![];
+        if let Some(sample) = emitters.sample(rng) {
+            let start = Vertex::light(sample);
+            let (direction, pdf) = sample.emitter.emit(sample.normal, rng);
+            let throughput =
+                start.throughput * start.value(scene, start.point + direction, ray, rng);
+            light_path.push(start);
+            if pdf > 0.0 && !throughput.near_zero() {
+                self.walk(
+                    scene,
+                    &emitters,
+                    ray.continued(Ray::new(start.point, direction)),
+                    throughput / pdf,
+                    pdf,
+                    &mut light_path,
+                    self.max_depth + 1,
+                    rng,
+                );
+            }
+        }
+
+        let mut light = self.unidirectional(scene, &camera_path, escaped, rng);
+        let most_light_vertices = light_path.len().max(usize::from(emitters.len() > 0));
+        for t in 1..=camera_path.len() {
+            for s in 0..=most_light_vertices {
+                if (s == 1 && t == 1) || s + t < 2 || s + t - 2 > self.max_depth {
+                    continue;
+                }
+                let Some((value, lands)) =
+                    self.connect(scene, &emitters, &camera_path, &light_path, s, t, ray, rng)
+                else {
+                    continue;
+                };
+                match lands {
+                    Some((u, v)) => splat(u, v, to_rgb(ray, value)),
+                    None => light += value,
+                }
+            }
+        }
+
+        to_rgb(ray, light)
+    }
+}
+
+impl BidirectionalPathTracer {
+    ///Carries on a path along `ray` from the last vertex in `path`, where `pdf` (with respect to solid angle) is the chance of going that way, adding vertices until it's absorbed or there are `length` of them.
+    ///
+    ///If it leaves the scene, gives the ray which left, how it was made and how much light gets back along it.
+    #[allow(clippy::too_many_arguments)]
+    fn walk<'a>(
+        &self,
+        scene: &'a Scene,
+        emitters: &Emitters,
+        mut ray: Ray,
+        mut throughput: Colour,
+        mut pdf: Decimal,
+        path: &mut Vec<Vertex<'a>>,
+        length: usize,
+        rng: &mut ThreadRng,
+    ) -> Option<(Ray, Bounce, Colour)> {
+        let mut bounce = Bounce::Camera;
+
+        while path.len() < length {
+            let Some(mut hit) = scene.world().hit(ray, 0.00001, Decimal::INFINITY) else {
+                return Some((ray, bounce, throughput));
+            };
+            hit.set_footprint(ray);
+            //holes and the boundaries of volumes are skipped, as if they weren't there
+            let material = match hit.material.resolve(&hit, rng) {
+                Some(material) if !material.is_invisible() => material,
+                _ => {
+                    ray = ray.restarted_at(hit.point);
+                    continue;
+                }
+            };
+            hit.material = material;
+
+            let previous = path.len() - 1;
+            let mut vertex = Vertex::surface(hit, ray, throughput);
+            vertex.pdf_forward = vertex.area_density(pdf, path[previous].point);
+            if !emitted(ray, &hit).near_zero() {
+                vertex.light = emitters.find(ray, &hit);
+            }
+
+            let Some((attenuation, scattered, _)) = material.scatter(ray, hit, rng) else {
+                path.push(vertex);
+                return None;
+            };
+            let direction = scattered.direction();
+            if let Some((_, forward)) = material.evaluate(ray, hit, direction, rng) {
+                pdf = forward;
+                bounce = Bounce::Sampled(forward);
+                //the chance of a path going the other way, from where this one goes next
+                path[previous].pdf_reverse = vertex.pdf(
+                    &self.camera,
+                    Some(hit.point + direction),
+                    &path[previous],
+                    rng,
+                );
+            } else {
+                //a perfect reflection or refraction, which can't be joined to, so it isn't weighted
+                pdf = 0.0;
+                bounce = Bounce::Specular;
+                vertex.delta = true;
+                path[previous].pdf_reverse = 0.0;
+            }
+            path.push(vertex);
+
+            throughput *= scattered_weight(ray, &hit, attenuation);
+            if throughput.near_zero() {
+                return None;
+            }
+            ray = ray.continued(scattered);
+        }
+
+        None
+    }
+
+    ///Light which paths can't start from, found from the camera path like in the path tracer - the environment and lights without a position
+    fn unidirectional<'a>(
+        &self,
+        scene: &'a Scene,
+        camera_path: &[Vertex<'a>],
+        escaped: Option<(Ray, Bounce, Colour)>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let mut light = escaped.map_or_else(Colour::default, |(ray, bounce, throughput)| {
+            throughput * ray.light(missed(ray, scene, bounce))
+        });
+
+        for vertex in camera_path.iter().skip(1).take(self.max_depth) {
+            let Kind::Surface(hit, ray_in) = vertex.kind else {
+                continue;
+            };
+            let evaluate = |direction, rng: &mut ThreadRng| {
+                hit.material.evaluate_lobes(ray_in, hit, direction, rng)
+            };
+            let lights = scene
+                .lights()
+                .iter()
+                .filter(|light| light.position().is_none());
+            let (environment, _) = sample_environment(scene, ray_in, hit.point, &[], rng, evaluate);
+            let (sampled, _) = sample_lights(scene, lights, ray_in, hit.point, &[], rng, evaluate);
+            light += vertex.throughput * (environment + sampled);
+        }
+
+        light
+    }
+
+    ///The light from joining the first `s` vertices of the light path to the first `t` vertices of the camera path, weighted against every other way of making the same path, and where it lands on the image if it was joined straight to the camera.
+    ///
+    ///Paths with one vertex from a light sample a new point on a light, and paths with one from the camera sample a new point on the lens. `ray` is the camera ray, for its wavelengths.
+    #[allow(clippy::too_many_arguments)]
+    fn connect<'a>(
+        &self,
+        scene: &'a Scene,
+        emitters: &Emitters<'a>,
+        camera_path: &[Vertex<'a>],
+        light_path: &[Vertex<'a>],
+        s: usize,
+        t: usize,
+        ray: Ray,
+        rng: &mut ThreadRng,
+    ) -> Option<(Colour, Option<(Decimal, Decimal)>)> {
+        let mut camera_path = camera_path[..t].to_vec();
+        let mut light_path = if s == 1 {
+            vec![Vertex::light(emitters.sample(rng)?)]
+        } else {
+            light_path[..s].to_vec()
+        };
+        let mut lands = None;
+
+        let value = if s == 0 {
+            //the camera path hit a light by itself
+            let end = camera_path[t - 1];
+            let Kind::Surface(hit, ray_in) = end.kind else {
+                return None;
+            };
+            let value = end.throughput * emitted(ray_in, &hit);
+            if end.light.is_none() {
+                //paths can't start from this light, so this is the only way to find it
+                return (!value.near_zero()).then_some((value, None));
+            }
+            value
+        } else if t == 1 {
+            //joining the light path straight to the camera
+            let end = light_path[s - 1];
+            if end.delta {
+                return None;
+            }
+            let lens = Vertex::camera(self.camera.sample_lens(rng));
+            let offset = end.point - lens.point;
+            let distance_squared = offset.magnitude_squared();
+            let (u, v, importance) = self.camera.importance(lens.point, offset)?;
+            let cos_theta = offset.unit().dot(self.camera.forward());
+            let visible = unoccluded(
+                scene,
+                Ray::new(end.point, -offset.unit()),
+                distance_squared.sqrt() - 0.0001,
+                &[],
+                rng,
+            );
+            camera_path[0] = lens;
+            lands = Some((u, v));
+            end.throughput
+                * end.value(scene, lens.point, ray, rng)
+                * ray.reflectance(visible)
+                * (importance * cos_theta * self.camera.lens_area() / distance_squared)
+        } else {
+            let (light_end, camera_end) = (light_path[s - 1], camera_path[t - 1]);
+            if light_end.delta || camera_end.delta {
+                return None;
+            }
+            let offset = light_end.point - camera_end.point;
+            let distance_squared = offset.magnitude_squared();
+            let visible = unoccluded(
+                scene,
+                Ray::new(camera_end.point, offset.unit()),
+                distance_squared.sqrt() - 0.0001,
+                &[],
+                rng,
+            );
+            light_end.throughput
+                * light_end.value(scene, camera_end.point, ray, rng)
+                * camera_end.value(scene, light_end.point, ray, rng)
+                * camera_end.throughput
+                * ray.reflectance(visible)
+                / distance_squared
+        };
+        if value.near_zero() {
+            return None;
+        }
+
+        let weight = self.weight(emitters, &mut camera_path, &mut light_path, rng);
+        Some((value * weight, lands))
+    }
+
+    ///The multiple importance sampling weight (with the power heuristic) for a path made by joining `light_path` to `camera_path`, against every other way of making it.
+    ///
+    ///This works out the chances of the vertices either side of the join being found from the other side, which changes them.
+    fn weight<'a>(
+        &self,
+        emitters: &Emitters<'a>,
+        camera_path: &mut [Vertex<'a>],
+        light_path: &mut [Vertex<'a>],
+        rng: &mut ThreadRng,
+    ) -> Decimal {
+        let (s, t) = (light_path.len(), camera_path.len());
+        if s == 0 {
+            let end = camera_path[t - 1];
+            camera_path[t - 1].pdf_reverse = end.light.map_or(0.0, |index| emitters.pdf(index));
+            camera_path[t - 2].pdf_reverse = end.emission_pdf(&camera_path[t - 2]);
+        } else {
+            let (light_end, camera_end) = (light_path[s - 1], camera_path[t - 1]);
+            let light_before = (s > 1).then(|| light_path[s - 2].point);
+            let camera_before = (t > 1).then(|| camera_path[t - 2].point);
+            camera_path[t - 1].pdf_reverse =
+                light_end.pdf(&self.camera, light_before, &camera_end, rng);
+            light_path[s - 1].pdf_reverse =
+                camera_end.pdf(&self.camera, camera_before, &light_end, rng);
+            if t > 1 {
+                camera_path[t - 2].pdf_reverse = camera_end.pdf(
+                    &self.camera,
+                    Some(light_end.point),
+                    &camera_path[t - 2],
+                    rng,
+                );
+            }
+            if s > 1 {
+                light_path[s - 2].pdf_reverse = light_end.pdf(
+                    &self.camera,
+                    Some(camera_end.point),
+                    &light_path[s - 2],
+                    rng,
+                );
+            }
+        }
+
+        //perfect reflections and refractions have no density, and are never joined to
+        let remap = |pdf: Decimal| if pdf == 0.0 { 1.0 } else { pdf };
+        let mut others = 0.0;
+
+        //the same path with fewer vertices from the camera, and more from the light
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            ratio *=
+                (remap(camera_path[i].pdf_reverse) / remap(camera_path[i].pdf_forward)).powi(2);
+            //lights can't be seen straight from the camera by joining them to it
+            let straight_to_light = i == 1 && s + t == 2;
+            if !camera_path[i].delta && !camera_path[i - 1].delta && !straight_to_light {
+                others += ratio;
+            }
+        }
+
+        //and with fewer from the light, and more from the camera
+        ratio = 1.0;
+        for i in (0..s).rev() {
+            ratio *= (remap(light_path[i].pdf_reverse) / remap(light_path[i].pdf_forward)).powi(2);
+            let before_delta = if i > 0 {
+                light_path[i - 1].delta
+            } else {
+                light_path[0].is_point_light()
+            };
+            if !light_path[i].delta && !before_delta {
+                others += ratio;
+            }
+        }
+
+        1.0 / (1.0 + others)
+    }
+}
+
+///What's at a vertex of a path
+#[derive(Debug, Copy, Clone)]
+#[allow(clippy::large_enum_variant)] //nearly every vertex is on a surface anyway
+enum Kind<'a> {
+    ///a point on the lens
+    Camera,
+    ///where a path from a light starts
+    Light(Emitter<'a>),
+    ///a surface hit by the ray
+    Surface(HitRecord<'a>, Ray),
+}
+
+///A point on a path from the camera or a light
+#[derive(Debug, Copy, Clone)]
+struct Vertex<'a> {
+    kind: Kind<'a>,
+    point: Point3,
+    ///the geometric normal, for converting densities - zero for points
+    normal: Vec3,
+    ///how much of the light or importance from the start of the path gets here
+    throughput: Colour,
+    ///the probability density (with respect to area) of the path it's on finding it
+    pdf_forward: Decimal,
+    ///the probability density (with respect to area) of a path from the other end finding it
+    pdf_reverse: Decimal,
+    ///whether it only scatters in one direction, so it can't be joined to
+    delta: bool,
+    ///the index of the area light it's on, if any
+    light: Option<usize>,
+}
+
+impl<'a> Vertex<'a> {
+    fn camera(point: Point3) -> Self {
+        Self {
+            kind: Kind::Camera,
+            point,
+            normal: Vec3::default(),
+            throughput: Colour::splat(1.0),
+            pdf_forward: 1.0,
+            pdf_reverse: 0.0,
+            delta: false,
+            light: None,
+        }
+    }
+
+    ///The start of a path from a light
+    fn light(sample: EmitterSample<'a>) -> Self {
+        Self {
+            kind: Kind::Light(sample.emitter),
+            point: sample.point,
+            normal: sample.normal,
+            throughput: Colour::splat(1.0 / sample.pdf),
+            pdf_forward: sample.pdf,
+            pdf_reverse: 0.0,
+            delta: false,
+            light: match sample.emitter {
+                Emitter::Area(index) => Some(index),
+                Emitter::Point(_) => None,
+            },
+        }
+    }
+
+    const fn surface(hit: HitRecord<'a>, ray: Ray, throughput: Colour) -> Self {
+        Self {
+            kind: Kind::Surface(hit, ray),
+            point: hit.point,
+            normal: hit.geometric_normal,
+            throughput,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+            delta: false,
+            light: None,
+        }
+    }
+
+    const fn is_point_light(&self) -> bool {
+        matches!(self.kind, Kind::Light(Emitter::Point(_)))
+    }
+
+    ///How much light goes between here and `towards` - the BSDF times the cosine for surfaces, the radiance times the cosine for area lights, and the intensity for point lights.
+    ///
+    ///`ray` is the camera ray, for its wavelengths.
+    fn value(&self, scene: &Scene, towards: Point3, ray: Ray, rng: &mut ThreadRng) -> Colour {
+        let direction = (towards - self.point).unit();
+        match self.kind {
+            Kind::Camera => Colour::default(),
+            Kind::Light(emitter) => emitter.emitted(scene, self.point, self.normal, direction, ray),
+            Kind::Surface(hit, ray_in) => hit
+                .material
+                .evaluate(ray_in, hit, direction, rng)
+                .map_or_else(Colour::default, |(value, _)| ray_in.reflectance(value)),
+        }
+    }
+
+    ///The probability density (with respect to area) of a path which got here from `from` going on to `next` - `from` is only needed for surfaces
+    fn pdf(
+        &self,
+        camera: &Camera,
+        from: Option<Point3>,
+        next: &Self,
+        rng: &mut ThreadRng,
+    ) -> Decimal {
+        let direction = (next.point - self.point).unit();
+        let pdf = match self.kind {
+            Kind::Camera => camera.pdf_direction(self.point, direction),
+            Kind::Light(emitter) => emitter.pdf(self.normal, direction),
+            Kind::Surface(hit, _) => {
+                let Some(from) = from else {
+                    return 0.0;
+                };
+                let ray_in = Ray::new(from, self.point - from);
+                let hit = seen_from(hit, ray_in.direction());
+                hit.material
+                    .evaluate(ray_in, hit, direction, rng)
+                    .map_or(0.0, |(_, pdf)| pdf)
+            }
+        };
+        next.area_density(pdf, self.point)
+    }
+
+    ///The probability density (with respect to area) of a path from the light at this vertex going to `next`, if a path had started here
+    fn emission_pdf(&self, next: &Self) -> Decimal {
+        let (Kind::Surface(hit, _), Some(index)) = (&self.kind, self.light) else {
+            return 0.0;
+        };
+        //lights emit around their outward normal, whichever side the path hit them from
+        let outward = if hit.front_face {
+            self.normal
+        } else {
+            -self.normal
+        };
+        let direction = (next.point - self.point).unit();
+        next.area_density(Emitter::Area(index).pdf(outward, direction), self.point)
+    }
+
+    ///A probability density with respect to solid angle as seen `from` somewhere, converted to be with respect to area here
+    fn area_density(&self, pdf: Decimal, from: Point3) -> Decimal {
+        let offset = self.point - from;
+        let distance_squared = offset.magnitude_squared();
+        if distance_squared <= 0.0 {
+            return 0.0;
+        }
+        let cos = if self.normal.near_zero() {
+            1.0
+        } else {
+            self.normal.dot(offset).abs() / distance_squared.sqrt()
+        };
+        pdf * cos / distance_squared
+    }
+}
+
+///`hit` as it would have been for a ray going along `direction`, with the normals facing against it
+fn seen_from(mut hit: HitRecord, direction: Vec3) -> HitRecord {
+    if hit.geometric_normal.dot(direction) > 0.0 {
+        hit.normal = -hit.normal;
+        hit.geometric_normal = -hit.geometric_normal;
+        hit.front_face = !hit.front_face;
+    }
+    hit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BidirectionalPathTracer;
+    use crate::{
+        io::images::ppm::PPMImage,
+        primitives::{
+            camera::Camera,
+            collisions::{quad::Quad, HittableList},
+            environment::Uniform,
+            integrators::{path::PathTracer, Integrator},
+            materials::Material,
+            scene::Scene,
+            Colour, Decimal, Vec3,
+        },
+    };
+    use std::sync::Arc;
+
+    ///A closed box from -1 to 1 with a red wall, lit only by a light in the ceiling, and a camera inside it
+    fn diffuse_box() -> (Scene, Camera) {
+        let white = || Material::LambertianDiffuse(Colour::splat(0.7).into());
+        let walls = [
+            (
+                Vec3::new(-1.0, -1.0, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+            ),
+            (
+                Vec3::new(-1.0, -1.0, 1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 2.0, 0.0),
+            ),
+            (
+                Vec3::new(-1.0, -1.0, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+            ),
+            (
+                Vec3::new(-1.0, 1.0, -1.0),
+                Vec3::new(2.0, 0.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+            ),
+            (
+                Vec3::new(1.0, -1.0, -1.0),
+                Vec3::new(0.0, 2.0, 0.0),
+                Vec3::new(0.0, 0.0, 2.0),
+            ),
+        ];
+
+        let mut world = HittableList::default();
+        for (corner, u, v) in walls {
+            world.add(Arc::new(Box::new(Quad::new(corner, u, v, white()))));
+        }
+        world.add(Arc::new(Box::new(Quad::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(0.0, 2.0, 0.0),
+            Vec3::new(0.0, 0.0, 2.0),
+            Material::LambertianDiffuse(Colour::new(0.7, 0.1, 0.1).into()),
+        ))));
+        //facing down, just under the ceiling
+        let light = Quad::new(
+            Vec3::new(-0.6, 0.99, -0.6),
+            Vec3::new(1.2, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.2),
+            Material::DiffuseLight(Colour::splat(2.0).into()),
+        );
+        world.add(Arc::new(Box::new(light.clone())));
+
+        let scene = Scene::new(world)
+            .with_area_lights(vec![Arc::new(light)])
+            .with_environment(Arc::new(Uniform(Colour::default())));
+        let camera = Camera::new(
+            70.0,
+            16.0 / 9.0,
+            Vec3::new(0.0, 0.0, 0.9),
+            Vec3::new(0.0, 0.0, -1.0),
+            0.0,
+            1.0,
+        );
+        (scene, camera)
+    }
+
+    ///The average pixel of the image `integrator` renders
+    fn mean(camera: &Camera, scene: &Scene, integrator: &dyn Integrator) -> Colour {
+        const WIDTH: usize = 32;
+        const HEIGHT: usize = 18;
+        const SAMPLES: usize = 64;
+
+        let mut image = PPMImage::new(WIDTH, HEIGHT);
+        image.fill(camera, scene, integrator, SAMPLES);
+
+        let mut sum = Colour::default();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                sum += image[(x, y)];
+            }
+        }
+        sum / (WIDTH * HEIGHT * SAMPLES) as Decimal
+    }
+
+    #[test]
+    fn agrees_with_path_tracing() {
+        //about four times the noise in the difference at this many samples
+        const TOLERANCE: Decimal = 0.06;
+        //deep enough that where each stops makes no difference
+        const DEPTH: usize = 20;
+
+        let (scene, camera) = diffuse_box();
+        let path = mean(&camera, &scene, &PathTracer::new(DEPTH));
+        let bidirectional = mean(
+            &camera,
+            &scene,
+            &BidirectionalPathTracer::new(camera.clone(), DEPTH),
+        );
+
+        for channel in 0..3 {
+            let relative = (bidirectional[channel] - path[channel]).abs() / path[channel];
+            assert!(
+                relative < TOLERANCE,
+                "path tracing found {path:?} but bidirectional found {bidirectional:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Views of what camera rays hit rather than how it's lit, for checking scenes
+
+use super::{id_colour, Integrator};
+use crate::primitives::{
+    collisions::primitive_tests, media::Medium, scene::Scene, Colour, Decimal, Ray,
+};
+use rand::rngs::ThreadRng;
+
+///Something about the first surface each camera ray hits, shown as a colour - the sky is black
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DebugView {
+    ///the shading normal, with each axis from -1 - 1 shown as 0 - 1
+    Normals,
+    ///u in red and v in green
+    Uvs,
+    ///the distance from the camera, from black up to white at this distance
+    Depth(Decimal),
+    ///a different colour for each object's material
+    MaterialId,
+    ///how many primitives the ray was tested against to find the hit, from blue through green up to red at this many - a heatmap of which parts of the scene are slowest to intersect
+    IntersectionCost(Decimal),
+}
+
+impl Integrator for DebugView {
+    fn colour<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        _media: &mut Vec<&'a dyn Medium>,
+        _rng: &mut ThreadRng,
+    ) -> Colour {
+        let before = primitive_tests();
+        let hit = scene.world().hit(ray, 0.00001, Decimal::INFINITY);
+        let tests = primitive_tests().wrapping_sub(before) as Decimal;
+
+        match (*self, hit) {
+            (Self::IntersectionCost(most), _) => heat(tests / most),
+            (_, None) => Colour::default(),
+            (Self::Normals, Some(hit)) => 0.5 * (hit.normal.unit() + Colour::splat(1.0)),
+            (Self::Uvs, Some(hit)) => Colour::new(hit.u, hit.v, 0.0),
+            (Self::Depth(furthest), Some(hit)) => {
+                Colour::splat((hit.time * ray.direction().magnitude() / furthest).min(1.0))
+            }
+            (Self::MaterialId, Some(hit)) => id_colour(scene.material_id(hit.material)),
+        }
+    }
+}
+
+///From blue at 0 through green at 0.5 up to red at 1
+fn heat(t: Decimal) -> Colour {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.5 {
+        Colour::new(0.0, 2.0 * t, 1.0 - 2.0 * t)
+    } else {
+        Colour::new(2.0 * t - 1.0, 2.0 - 2.0 * t, 0.0)
+    }
+}
@@ -0,0 +1,31 @@
+//! Lighting straight from lights, emissive surfaces and the environment, without any light bouncing between surfaces
+
+use super::{direct_at, emitted, first_surface, missed, to_rgb, Bounce, Integrator};
+use crate::primitives::{media::Medium, scene::Scene, Colour, Decimal, Ray};
+use rand::rngs::ThreadRng;
+
+///Only the light arriving at the first surface seen, straight from lights, emissive surfaces and the environment.
+///
+///It's quick, but shadows are black and glass looks dark, because nothing is lit by light bouncing off anything else.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct DirectLighting;
+
+impl Integrator for DirectLighting {
+    fn colour<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let (transmittance, hit) = first_surface(scene, ray, Decimal::INFINITY, media, rng);
+        let light = hit.map_or_else(
+            || ray.light(missed(ray, scene, Bounce::Camera)),
+            |mut hit| {
+                hit.set_footprint(ray);
+                emitted(ray, &hit) + direct_at(scene, ray, &hit, media, rng)
+            },
+        );
+        to_rgb(ray, ray.reflectance(transmittance) * light)
+    }
+}
@@ -0,0 +1,382 @@
+//! The path tracer, which follows light bouncing around the scene
+
+use super::{
+    aov::{Aov, AovSample, FirstHit},
+    cross, direct_light, emitted, missed, scattered_weight, to_rgb, Bounce, Integrator,
+    LightPasses,
+};
+use crate::primitives::{materials::Lobe, media::Medium, scene::Scene, Colour, Decimal, Ray};
+use rand::{rngs::ThreadRng, Rng};
+
+///Follows paths from the camera, bouncing until they leave the scene or reach the maximum depth, with lights sampled at every bounce
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct PathTracer {
+    max_depth: usize,
+}
+
+impl PathTracer {
+    ///The most bounces a path can take is `max_depth`, but paths can also be limited by the scene's [`LobeDepths`](crate::primitives::scene::LobeDepths) and russian roulette
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self { max_depth }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn colour<'a>(
+        &self,
+        ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let mut aovs = AovSample::default();
+        self.colour_with_aovs(ray, scene, media, &mut |_, _, _| {}, &mut aovs, rng)
+    }
+
+    fn colour_with_aovs<'a>(
+        &self,
+        camera_ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        _splat: &mut dyn FnMut(Decimal, Decimal, Colour),
+        aovs: &mut AovSample<'a>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let mut ray = camera_ray;
+        let mut path = Path::new(self.max_depth);
+        let mut bounce = Bounce::Camera;
+        let mut light = Colour::default();
+        let mut found = LightPasses::default();
+        //how far the camera ray has gone, through holes and invisible boundaries
+        let mut distance = 0.0;
+
+        while path.depth > 0 {
+            let Some((object, mut hit)) = scene.world().hit_object(ray, 0.00001, Decimal::INFINITY)
+            else {
+                //media are only between surfaces, so we can still see the sky through fog
+                light += path.found(
+                    scene,
+                    ray.light(missed(ray, scene, bounce)),
+                    false,
+                    &mut found,
+                );
+                break;
+            };
+
+            if let Some(medium) = media.last().copied() {
+                let sample = medium.sample_distance(ray, hit.time, rng);
+                light += path.found(scene, ray.light(sample.emitted), false, &mut found);
+                path.throughput *= ray.reflectance(sample.weight);
+
+                if let Some(t) = sample.scattered_at {
+                    let (point, direction_in, phase) =
+                        (ray.at(t), ray.direction().unit(), medium.phase());
+                    let (direct, _) =
+                        direct_light(scene, ray, point, media, rng, |direction, _| {
+                            let value = phase.evaluate(direction_in, direction);
+                            Some((Colour::splat(value), value, Colour::splat(value)))
+                        });
+                    light += path.found(scene, direct, true, &mut found);
+
+                    let direction = phase.sample(direction_in, rng);
+                    bounce = Bounce::Sampled(phase.evaluate(direction_in, direction));
+                    path.bounced(Colour::splat(1.0), None);
+                    if !path.roulette(scene, rng) {
+                        break;
+                    }
+                    ray = ray.continued(Ray::new(point, direction));
+                    continue;
+                }
+            }
+
+            hit.set_footprint(ray);
+            distance += hit.time * ray.direction().magnitude();
+            let surface = hit.material;
+            let Some(material) = surface.resolve(&hit, rng) else {
+                //a hole in a cutout, so it's as if we never hit anything, and it doesn't count as a bounce
+                ray = ray.restarted_at(hit.point);
+                continue;
+            };
+            hit.material = material;
+            let scattered = material.scatter(ray, hit, rng);
+            let first = path.bounces == 0 && !material.is_invisible();
+            if first {
+                aovs.first = Some(FirstHit {
+                    hit,
+                    object,
+                    distance,
+                    albedo: surface.albedo(&hit),
+                    material: scene.material_id(surface),
+                });
+            }
+
+            let emitted = emitted(ray, &hit);
+            let (direct, direct_diffuse) =
+                direct_light(scene, ray, hit.point, media, rng, |direction, rng| {
+                    hit.material.evaluate_lobes(ray, hit, direction, rng)
+                });
+            //light sampled from the first surface is split up by the lobes it scattered off
+            let diffuse = if first {
+                diffuse_fraction(direct_diffuse, direct)
+            } else {
+                path.diffuse
+            };
+            light += path.found(scene, emitted, false, &mut found)
+                + path.found_with(scene, direct, true, diffuse, &mut found);
+
+            let Some((attenuation, scattered, lobe)) = scattered else {
+                break;
+            };
+            let attenuation = scattered_weight(ray, &hit, attenuation);
+
+            if hit.material.is_invisible() {
+                //going straight through an invisible boundary doesn't count as a bounce, so it's as if we never hit anything
+                path.throughput *= attenuation;
+            } else {
+                let evaluated = hit
+                    .material
+                    .evaluate_lobes(ray, hit, scattered.direction(), rng);
+                bounce = evaluated.map_or(Bounce::Specular, |(_, pdf, _)| Bounce::Sampled(pdf));
+                if first {
+                    path.left_first_surface(ray, evaluated, lobe);
+                }
+                path.bounced(attenuation, Some(lobe));
+                if path.lobe_bounces(lobe) > scene.lobe_depths().get(lobe)
+                    || !path.roulette(scene, rng)
+                {
+                    break;
+                }
+            }
+
+            cross(media, &hit, scattered.direction());
+            ray = ray.continued(scattered);
+        }
+
+        aovs.passes += found.to_rgb(camera_ray);
+        to_rgb(camera_ray, light)
+    }
+
+    fn aovs(&self) -> &'static [Aov] {
+        &Aov::ALL
+    }
+}
+
+///How far along a path is, and how much of the light it finds will make it back to the camera
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Path {
+    ///how many more bounces it can take
+    depth: usize,
+    ///how many bounces it's taken
+    bounces: usize,
+    ///how many bounces it's taken of each [`Lobe`]
+    lobes: [usize; 3],
+    ///how much of the light found from here on scattered diffusely off the first surface, for splitting up the light - scattering in media counts as diffuse
+    diffuse: Colour,
+    ///everything light found along the path gets multiplied by
+    throughput: Colour,
+}
+
+impl Path {
+    const fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            bounces: 0,
+            lobes: [0; 3],
+            diffuse: Colour::splat(1.0),
+            throughput: Colour::splat(1.0),
+        }
+    }
+
+    ///Everything found from here on is split up by how much of the BSDF times the cosine is diffuse in the direction the path went off the first surface, from `evaluated` (like [`Material::evaluate_lobes`](crate::primitives::materials::Material::evaluate_lobes)) for the `ray` which hit it.
+    ///
+    ///If the BSDF can't be evaluated, it goes by the `lobe` the direction was sampled from.
+    fn left_first_surface(
+        &mut self,
+        ray: Ray,
+        evaluated: Option<(Colour, Decimal, Colour)>,
+        lobe: Lobe,
+    ) {
+        self.diffuse = evaluated.map_or_else(
+            || Colour::splat(if lobe == Lobe::Diffuse { 1.0 } else { 0.0 }),
+            |(value, _, diffuse)| {
+                diffuse_fraction(ray.reflectance(diffuse), ray.reflectance(value))
+            },
+        );
+    }
+
+    ///After scattering with `weight` - scattering in media doesn't have a lobe
+    fn bounced(&mut self, weight: Colour, lobe: Option<Lobe>) {
+        self.depth -= 1;
+        self.bounces += 1;
+        if let Some(lobe) = lobe {
+            self.lobes[lobe as usize] += 1;
+        }
+        self.throughput *= weight;
+    }
+
+    const fn lobe_bounces(&self, lobe: Lobe) -> usize {
+        self.lobes[lobe as usize]
+    }
+
+    ///Russian roulette - randomly ends paths which can't carry much light any more, and boosts the ones which carry on to make up for it.
+    ///
+    ///Gives whether the path carries on.
+    fn roulette(&mut self, scene: &Scene, rng: &mut ThreadRng) -> bool {
+        match scene.russian_roulette() {
+            Some(after) if self.bounces > after => {
+                let survival = self
+                    .throughput
+                    .x()
+                    .max(self.throughput.y())
+                    .max(self.throughput.z())
+                    .min(1.0);
+                if rng.gen::<Decimal>() >= survival {
+                    return false;
+                }
+                self.throughput /= survival;
+                true
+            }
+            _ => true,
+        }
+    }
+
+    ///How much of the light found here gets back to the camera, limited so it can't be brighter than the scene's clamp.
+    ///
+    ///`sampled` is for light sampled from here, which has bounced once more. Only indirect light is limited, so lights seen from the camera or lighting the first surface directly are left alone.
+    ///
+    ///It's also added to the right `passes`.
+    fn found(
+        &self,
+        scene: &Scene,
+        light: Colour,
+        sampled: bool,
+        passes: &mut LightPasses,
+    ) -> Colour {
+        self.found_with(scene, light, sampled, self.diffuse, passes)
+    }
+
+    ///Like [`Path::found`], but with `diffuse` of it going to the diffuse pass instead of the path's own split
+    fn found_with(
+        &self,
+        scene: &Scene,
+        light: Colour,
+        sampled: bool,
+        diffuse: Colour,
+        passes: &mut LightPasses,
+    ) -> Colour {
+        let bounces = self.bounces + usize::from(sampled);
+        let contribution = self.throughput * light;
+        let contribution = match scene.clamp() {
+            Some(clamp) if bounces >= 2 => {
+                let brightest = contribution.x().max(contribution.y()).max(contribution.z());
+                if brightest > clamp {
+                    contribution * (clamp / brightest)
+                } else {
+                    contribution
+                }
+            }
+            _ => contribution,
+        };
+        passes.add(contribution, bounces, diffuse)
+    }
+}
+
+///How much of `total` is `diffuse` in each channel, from 0-1
+fn diffuse_fraction(diffuse: Colour, total: Colour) -> Colour {
+    let channel = |diffuse: Decimal, total: Decimal| {
+        if total > 0.0 {
+            (diffuse / total).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+    Colour::new(
+        channel(diffuse.x(), total.x()),
+        channel(diffuse.y(), total.y()),
+        channel(diffuse.z(), total.z()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathTracer;
+    use crate::primitives::{
+        collisions::{sphere::Sphere, HittableList},
+        decimal_consts::PI,
+        integrators::Integrator,
+        lights::{LightList, PointLight},
+        materials::Material,
+        scene::Scene,
+        Colour, Decimal, Point3, Ray, Vec3,
+    };
+    use rand::thread_rng;
+    use std::sync::Arc;
+
+    ///Deep enough that the light left after it is too little to matter
+    const DEPTH: usize = 64;
+
+    ///A closed grey sphere lit by a point light at its centre.
+    ///
+    ///Every point on the inside gets the same light, so the radiance everywhere is the same. With an albedo of 1/2, and the light giving an irradiance of π on the sphere, that comes out to exactly 1 once all the bounces are added up.
+    fn furnace() -> Scene {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Box::new(Sphere::new(
+            Point3::default(),
+            1.0,
+            Material::LambertianDiffuse(Colour::splat(0.5).into()),
+        ))));
+        let mut lights = LightList::default();
+        lights.add(Arc::new(PointLight::new(
+            Point3::default(),
+            Colour::splat(PI),
+        )));
+        Scene::new(world).with_lights(lights)
+    }
+
+    ///The average light found by `samples` rays looking out from the centre of `scene`
+    fn mean(scene: &Scene, samples: usize) -> Colour {
+        let mut rng = thread_rng();
+        let mut total = Colour::default();
+        for _ in 0..samples {
+            let ray = Ray::new(Point3::default(), Vec3::random_unit_vector(&mut rng));
+            total += PathTracer::new(DEPTH).colour(ray, scene, &mut vec![], &mut rng);
+        }
+        total / samples as Decimal
+    }
+
+    fn assert_near(found: Colour, expected: Decimal, tolerance: Decimal) {
+        for channel in 0..3 {
+            assert!(
+                (found[channel] - expected).abs() < tolerance,
+                "found {found:?}, not {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn russian_roulette_is_unbiased() {
+        //without roulette, every path finds the same light
+        let exact = mean(&furnace().with_russian_roulette(None), 100);
+        assert_near(exact, 1.0, 1e-9);
+
+        //with it, paths are cut short at random, which makes a few tenths of a percent of noise at this many samples
+        for after in [0, 3] {
+            let roulette = mean(&furnace().with_russian_roulette(Some(after)), 20_000);
+            assert_near(roulette, 1.0, 0.02);
+        }
+    }
+
+    #[test]
+    fn unreached_clamp_changes_nothing() {
+        //no single bounce finds more than 1/2, so a clamp of 1 is never reached
+        let unclamped = mean(&furnace().with_russian_roulette(None).with_clamp(None), 100);
+        let clamped = mean(
+            &furnace().with_russian_roulette(None).with_clamp(Some(1.0)),
+            100,
+        );
+
+        assert_near(unclamped, 1.0, 1e-9);
+        assert_near(clamped, unclamped.x(), 1e-9);
+    }
+}
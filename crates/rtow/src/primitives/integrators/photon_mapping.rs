@@ -0,0 +1,282 @@
+//! Photon mapping, which traces light out from the lights before rendering, then looks up how much of it landed near where camera rays hit
+
+use super::{
+    cross, direct_at, emitted, first_surface, missed, scattered_weight, to_rgb, Bounce, Emitters,
+    Integrator,
+};
+use crate::primitives::{
+    collisions::{Aabb, HitRecord},
+    decimal_consts::PI,
+    media::Medium,
+    scene::Scene,
+    Colour, Decimal, Point3, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, thread_rng, Rng};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+
+///How quickly the radius shrinks from one pass to the next - smaller shrinks faster, blurring less but with more noise
+const ALPHA: Decimal = 2.0 / 3.0;
+
+///Traces photons from the lights and stores where they land on diffuse surfaces, then estimates the light bouncing off surfaces seen through mirrors and glass from the photons nearby.
+///
+///Caustics come out smooth rather than as noise. Each pass of photons is traced separately and gathered with a smaller radius than the last, and every camera sample uses a random pass, so the blurring goes away as the passes are averaged. Direct light is sampled like in [`DirectLighting`](super::direct::DirectLighting).
+///
+///Photons start from the scene's [area lights](Scene::with_area_lights) and lights with a [position](crate::primitives::lights::Light::position), and are traced in RGB. Light bouncing from the environment and other lights is left out.
+#[derive(Debug, Clone)]
+pub struct PhotonMapper {
+    passes: Vec<PhotonMap>,
+    max_depth: usize,
+}
+
+impl PhotonMapper {
+    ///Traces `passes` lots of `photons` photons from the lights in `scene`, where the first pass gathers photons within `radius`.
+    ///
+    ///Photons and camera rays can bounce up to `max_depth` times.
+    #[must_use]
+    pub fn new(
+        scene: &Scene,
+        passes: usize,
+        photons: usize,
+        radius: Decimal,
+        max_depth: usize,
+    ) -> Self {
+        let mut radius_squared = radius * radius;
+        let radii: Vec<Decimal> = (0..passes.max(1))
+            .map(|pass| {
+                let radius = radius_squared.sqrt();
+                radius_squared *= (pass as Decimal + 1.0 + ALPHA) / (pass as Decimal + 2.0);
+                radius
+            })
+            .collect();
+
+        Self {
+            passes: radii
+                .into_par_iter()
+                .map(|radius| PhotonMap::trace(scene, photons, radius, max_depth))
+                .collect(),
+            max_depth,
+        }
+    }
+}
+
+impl Integrator for PhotonMapper {
+    fn colour<'a>(
+        &self,
+        camera_ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let pass = &self.passes[rng.gen_range(0..self.passes.len())];
+        let mut ray = camera_ray;
+        let mut bounce = Bounce::Camera;
+        let mut throughput = Colour::splat(1.0);
+        let mut light = Colour::default();
+
+        for _ in 0..self.max_depth {
+            let (transmittance, hit) = first_surface(scene, ray, Decimal::INFINITY, media, rng);
+            throughput *= ray.reflectance(transmittance);
+            let Some(mut hit) = hit else {
+                light += throughput * ray.light(missed(ray, scene, bounce));
+                break;
+            };
+            hit.set_footprint(ray);
+            light += throughput * emitted(ray, &hit);
+
+            let Some((attenuation, scattered, _)) = hit.material.scatter(ray, hit, rng) else {
+                break;
+            };
+            let smooth = hit
+                .material
+                .evaluate(ray, hit, scattered.direction(), rng)
+                .is_none();
+            if !smooth {
+                light += throughput
+                    * (direct_at(scene, ray, &hit, media, rng) + pass.estimate(ray, &hit, rng));
+                break;
+            }
+
+            throughput *= scattered_weight(ray, &hit, attenuation);
+            bounce = Bounce::Specular;
+            cross(media, &hit, scattered.direction());
+            ray = ray.continued(scattered);
+        }
+
+        to_rgb(camera_ray, light)
+    }
+}
+
+///Light which landed on a diffuse surface after bouncing at least once
+#[derive(Debug, Copy, Clone)]
+struct Photon {
+    point: Point3,
+    ///pointing back the way it came
+    direction: Vec3,
+    ///the geometric normal of the surface, facing the side it landed on
+    normal: Vec3,
+    power: Colour,
+    ///the axis the photons around it in the [`PhotonMap`] are split along
+    axis: usize,
+}
+
+///One pass of photons, arranged as a kd-tree so the ones near a point can be found quickly.
+///
+///Each slice of the photons is split in half at its middle photon, along the axis it's most spread out in, and the halves are split again.
+#[derive(Debug, Clone)]
+struct PhotonMap {
+    photons: Vec<Photon>,
+    radius: Decimal,
+}
+
+impl PhotonMap {
+    ///Traces `photons` photons from the lights in `scene`, bouncing up to `max_depth` times, to be gathered within `radius`
+    fn trace(scene: &Scene, photons: usize, radius: Decimal, max_depth: usize) -> Self {
+        let emitters = Emitters::new(scene);
+        let mut rng = thread_rng();
+        let mut stored = vec![];
+
+        for _ in 0..photons {
+            let Some(sample) = emitters.sample(&mut rng) else {
+                break;
+            };
+            let (direction, pdf) = sample.emitter.emit(sample.normal, &mut rng);
+            let ray = Ray::new(sample.point, direction);
+            let power = sample
+                .emitter
+                .emitted(scene, sample.point, sample.normal, direction, ray)
+                / (sample.pdf * pdf * photons as Decimal);
+            if pdf > 0.0 && !power.near_zero() {
+                scatter_photon(scene, ray, power, max_depth, &mut stored, &mut rng);
+            }
+        }
+
+        split(&mut stored);
+        Self {
+            photons: stored,
+            radius,
+        }
+    }
+
+    ///The light bouncing off `hit` back along `ray`, estimated from the photons within the radius
+    fn estimate(&self, ray: Ray, hit: &HitRecord, rng: &mut ThreadRng) -> Colour {
+        let radius_squared = self.radius * self.radius;
+        let mut light = Colour::default();
+
+        gather(&self.photons, hit.point, radius_squared, &mut |photon| {
+            //photons on surfaces facing another way, like round a corner, don't belong here
+            let cos = photon.direction.dot(hit.normal).abs();
+            if photon.normal.dot(hit.geometric_normal) <= 0.0 || cos < 1e-6 {
+                return;
+            }
+            if let Some((value, _)) = hit.material.evaluate(ray, *hit, photon.direction, rng) {
+                light += ray.reflectance(value) / cos * ray.light(photon.power);
+            }
+        });
+
+        light / (PI * radius_squared)
+    }
+}
+
+///Follows a photon with `power` along `ray`, storing it everywhere it lands on a diffuse surface after the first
+fn scatter_photon(
+    scene: &Scene,
+    mut ray: Ray,
+    mut power: Colour,
+    max_depth: usize,
+    photons: &mut Vec<Photon>,
+    rng: &mut ThreadRng,
+) {
+    let mut media = vec![];
+
+    for bounces in 0..max_depth {
+        let (transmittance, hit) = first_surface(scene, ray, Decimal::INFINITY, &mut media, rng);
+        let Some(hit) = hit else {
+            return;
+        };
+        power *= transmittance;
+
+        let Some((attenuation, scattered, _)) = hit.material.scatter(ray, hit, rng) else {
+            return;
+        };
+        let smooth = hit
+            .material
+            .evaluate(ray, hit, scattered.direction(), rng)
+            .is_none();
+        //light straight from the lights is sampled directly, so only light which has bounced is stored
+        if !smooth && bounces > 0 {
+            photons.push(Photon {
+                point: hit.point,
+                direction: -ray.direction().unit(),
+                normal: hit.geometric_normal,
+                power,
+                axis: 0,
+            });
+        }
+
+        //russian roulette, so photons keep about the same power until they're absorbed
+        let weight = scattered_weight(ray, &hit, attenuation);
+        let survival = weight.x().max(weight.y()).max(weight.z()).min(1.0);
+        if rng.gen::<Decimal>() >= survival {
+            return;
+        }
+        power *= weight / survival;
+
+        cross(&mut media, &hit, scattered.direction());
+        ray = ray.continued(scattered);
+    }
+}
+
+///Arranges `photons` into a kd-tree, with the middle photon splitting the rest along its axis
+fn split(photons: &mut [Photon]) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    let extent = Aabb::from_points(photons.iter().map(|photon| photon.point))
+        .map_or_else(Vec3::default, |bounds| bounds.diagonal());
+    let axis = (0..3)
+        .max_by(|a, b| extent[*a].total_cmp(&extent[*b]))
+        .unwrap_or_default();
+
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.point[axis].total_cmp(&b.point[axis]));
+    photons[middle].axis = axis;
+
+    let (below, rest) = photons.split_at_mut(middle);
+    split(below);
+    split(&mut rest[1..]);
+}
+
+///Calls `found` for every photon in the kd-tree `photons` within the square root of `radius_squared` of `point`
+fn gather(
+    photons: &[Photon],
+    point: Point3,
+    radius_squared: Decimal,
+    found: &mut impl FnMut(&Photon),
+) {
+    if photons.is_empty() {
+        return;
+    }
+
+    let middle = photons.len() / 2;
+    let photon = &photons[middle];
+    if (photon.point - point).magnitude_squared() <= radius_squared {
+        found(photon);
+    }
+    if photons.len() == 1 {
+        return;
+    }
+
+    let offset = point[photon.axis] - photon.point[photon.axis];
+    let (below, above) = (&photons[..middle], &photons[middle + 1..]);
+    let (near, far) = if offset < 0.0 {
+        (below, above)
+    } else {
+        (above, below)
+    };
+    gather(near, point, radius_squared, found);
+    //the other side can only have photons in range if the split is
+    if offset * offset <= radius_squared {
+        gather(far, point, radius_squared, found);
+    }
+}
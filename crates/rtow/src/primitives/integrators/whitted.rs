@@ -0,0 +1,67 @@
+//! Whitted-style ray tracing, which follows mirrors and glass but lights everything else directly
+
+use super::{
+    cross, direct_at, emitted, first_surface, missed, scattered_weight, to_rgb, Bounce, Integrator,
+};
+use crate::primitives::{media::Medium, scene::Scene, Colour, Decimal, Ray};
+use rand::rngs::ThreadRng;
+
+///Follows smooth reflections and refractions up to `max_depth` times, then lights the surface it ends on like [`DirectLighting`](super::direct::DirectLighting).
+///
+///Smooth surfaces are the ones which can't be evaluated for sampled light. Glass and mirrors look right, but there's no light bouncing between diffuse surfaces, so it's much quicker than path tracing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Whitted {
+    max_depth: usize,
+}
+
+impl Whitted {
+    #[must_use]
+    pub const fn new(max_depth: usize) -> Self {
+        Self { max_depth }
+    }
+}
+
+impl Integrator for Whitted {
+    fn colour<'a>(
+        &self,
+        camera_ray: Ray,
+        scene: &'a Scene,
+        media: &mut Vec<&'a dyn Medium>,
+        rng: &mut ThreadRng,
+    ) -> Colour {
+        let mut ray = camera_ray;
+        let mut bounce = Bounce::Camera;
+        let mut throughput = Colour::splat(1.0);
+        let mut light = Colour::default();
+
+        for _ in 0..self.max_depth {
+            let (transmittance, hit) = first_surface(scene, ray, Decimal::INFINITY, media, rng);
+            throughput *= ray.reflectance(transmittance);
+            let Some(mut hit) = hit else {
+                light += throughput * ray.light(missed(ray, scene, bounce));
+                break;
+            };
+            hit.set_footprint(ray);
+            light += throughput * emitted(ray, &hit);
+
+            let Some((attenuation, scattered, _)) = hit.material.scatter(ray, hit, rng) else {
+                break;
+            };
+            let smooth = hit
+                .material
+                .evaluate(ray, hit, scattered.direction(), rng)
+                .is_none();
+            if !smooth {
+                light += throughput * direct_at(scene, ray, &hit, media, rng);
+                break;
+            }
+
+            throughput *= scattered_weight(ray, &hit, attenuation);
+            bounce = Bounce::Specular;
+            cross(media, &hit, scattered.direction());
+            ray = ray.continued(scattered);
+        }
+
+        to_rgb(camera_ray, light)
+    }
+}
@@ -0,0 +1,189 @@
+//! Lights which aren't part of the world - they can't be seen or hit, only sampled from surfaces and media
+
+use crate::primitives::{Colour, Decimal, Point3, Vec3};
+use std::{fmt::Debug, sync::Arc};
+
+pub mod ies;
+
+///Light arriving at a point from a [`Light`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightSample {
+    ///the unit vector from the point towards the light
+    pub direction: Vec3,
+    ///how far away the light is, which is infinite for [`DirectionalLight`]s
+    pub distance: Decimal,
+    ///the light falling on a surface facing the light
+    pub irradiance: Colour,
+}
+
+pub trait Light: Debug + Send + Sync {
+    ///The light reaching `point`, ignoring anything in the way, or [`None`] if none does
+    fn sample(&self, point: Point3) -> Option<LightSample>;
+
+    ///Where the light comes from, if it's a point - paths can be traced out from these, but not from lights infinitely far away
+    fn position(&self) -> Option<Point3> {
+        None
+    }
+    ///The irradiance one unit away along `direction` (a unit vector) from the [`Light::position`]
+    fn intensity(&self, _direction: Vec3) -> Colour {
+        Colour::default()
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct LightList(Vec<Arc<dyn Light>>);
+
+impl LightList {
+    pub fn add(&mut self, light: Arc<dyn Light>) {
+        self.0.push(light);
+    }
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Light> {
+        self.0.iter().map(AsRef::as_ref)
+    }
+}
+
+///From `from` to `to`, with the inverse square falloff of the distance between them
+fn towards(from: Point3, to: Point3, intensity: Colour) -> Option<LightSample> {
+    let offset = to - from;
+    let distance_squared = offset.magnitude_squared();
+    if distance_squared <= 0.0 {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+
+    Some(LightSample {
+        direction: offset / distance,
+        distance,
+        irradiance: intensity / distance_squared,
+    })
+}
+
+///Light going out equally in every direction from a point
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointLight {
+    position: Point3,
+    intensity: Colour,
+}
+
+impl PointLight {
+    ///`intensity` is the irradiance one unit away
+    #[must_use]
+    pub const fn new(position: Point3, intensity: Colour) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        towards(point, self.position, self.intensity)
+    }
+
+    fn position(&self) -> Option<Point3> {
+        Some(self.position)
+    }
+    fn intensity(&self, _direction: Vec3) -> Colour {
+        self.intensity
+    }
+}
+
+///A point light which only shines in a cone, fading out towards the edge
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpotLight {
+    position: Point3,
+    ///unit vector
+    direction: Vec3,
+    intensity: Colour,
+    ///cosine of the angle at which it stops
+    cos_outer: Decimal,
+    ///cosine of the angle at which it starts fading
+    cos_inner: Decimal,
+}
+
+impl SpotLight {
+    ///A spot pointing from `position` at `target`, lighting everything within `angle` degrees of its middle.
+    ///
+    ///It is at full brightness up to `falloff_start` degrees, and then fades out smoothly.
+    #[must_use]
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        intensity: Colour,
+        angle: Decimal,
+        falloff_start: Decimal,
+    ) -> Self {
+        let cos_outer = angle.to_radians().cos();
+        Self {
+            position,
+            direction: (target - position).unit(),
+            intensity,
+            cos_outer,
+            cos_inner: falloff_start.min(angle).to_radians().cos(),
+        }
+    }
+
+    fn falloff(&self, cos_theta: Decimal) -> Decimal {
+        if cos_theta >= self.cos_inner {
+            return 1.0;
+        }
+        if cos_theta <= self.cos_outer {
+            return 0.0;
+        }
+        //smoothstep
+        let t = (cos_theta - self.cos_outer) / (self.cos_inner - self.cos_outer);
+        t * t * 2.0f64.mul_add(-t, 3.0)
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        let mut sample = towards(point, self.position, self.intensity)?;
+        let falloff = self.falloff(-sample.direction.dot(self.direction));
+        if falloff <= 0.0 {
+            return None;
+        }
+        sample.irradiance *= falloff;
+        Some(sample)
+    }
+
+    fn position(&self) -> Option<Point3> {
+        Some(self.position)
+    }
+    fn intensity(&self, direction: Vec3) -> Colour {
+        self.intensity * self.falloff(direction.dot(self.direction))
+    }
+}
+
+///Parallel light from infinitely far away, like the sun
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DirectionalLight {
+    ///unit vector towards the light
+    towards: Vec3,
+    irradiance: Colour,
+}
+
+impl DirectionalLight {
+    ///Light travelling along `direction`, giving `irradiance` to surfaces facing it
+    #[must_use]
+    pub fn new(direction: Vec3, irradiance: Colour) -> Self {
+        Self {
+            towards: -direction.unit(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _point: Point3) -> Option<LightSample> {
+        Some(LightSample {
+            direction: self.towards,
+            distance: Decimal::INFINITY,
+            irradiance: self.irradiance,
+        })
+    }
+}
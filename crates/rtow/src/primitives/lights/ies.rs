@@ -0,0 +1,217 @@
+//! Lights shaped like real fixtures, from measurements of how bright they are in each direction
+
+use super::{towards, Light, LightSample};
+use crate::primitives::{Colour, Decimal, Point3, Vec3};
+use std::sync::Arc;
+
+///How bright a light fixture is in each direction, in candela, as measured in a photometric (IES) file.
+///
+///Uses type C photometry - vertical angles go from 0 straight down to 180 straight up, and horizontal angles go around anticlockwise (seen from above) from 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    ///in degrees, increasing
+    vertical: Vec<Decimal>,
+    ///in degrees, increasing
+    horizontal: Vec<Decimal>,
+    ///all of the vertical angles for the first horizontal angle, then the next
+    candela: Vec<Decimal>,
+    max: Decimal,
+}
+
+impl IesProfile {
+    ///Makes a profile from the angles in degrees, and the candela for each vertical angle of each horizontal angle in turn
+    ///
+    /// # Panics
+    /// If there are no angles, or there isn't a value for every pair of angles
+    #[must_use]
+    pub fn new(vertical: Vec<Decimal>, horizontal: Vec<Decimal>, candela: Vec<Decimal>) -> Self {
+        assert!(
+            !vertical.is_empty() && !horizontal.is_empty(),
+            "IES profiles need angles"
+        );
+        assert_eq!(
+            candela.len(),
+            vertical.len() * horizontal.len(),
+            "wrong number of candela values"
+        );
+        let max = candela.iter().copied().fold(0.0, Decimal::max);
+
+        Self {
+            vertical,
+            horizontal,
+            candela,
+            max,
+        }
+    }
+
+    ///The brightest direction, in candela
+    #[must_use]
+    pub const fn max(&self) -> Decimal {
+        self.max
+    }
+
+    ///The brightness in candela at some angles in degrees, interpolated between the measurements
+    #[must_use]
+    pub fn candela(&self, vertical: Decimal, horizontal: Decimal) -> Decimal {
+        let horizontal = self.fold_horizontal(horizontal);
+        let Some((v, v_weight)) = segment(&self.vertical, vertical) else {
+            return 0.0;
+        };
+        let (h, h_weight) = segment(&self.horizontal, horizontal).unwrap_or((0, 0.0));
+
+        let value = |h: usize, v: usize| {
+            let h = h.min(self.horizontal.len() - 1);
+            let v = v.min(self.vertical.len() - 1);
+            self.candela[h * self.vertical.len() + v]
+        };
+        let near = value(h, v) * (1.0 - v_weight) + value(h, v + 1) * v_weight;
+        let far = value(h + 1, v) * (1.0 - v_weight) + value(h + 1, v + 1) * v_weight;
+        near * (1.0 - h_weight) + far * h_weight
+    }
+
+    ///Uses the symmetry of the fixture to bring an angle into the measured range
+    fn fold_horizontal(&self, horizontal: Decimal) -> Decimal {
+        let horizontal = horizontal.rem_euclid(360.0);
+        let last = self.horizontal[self.horizontal.len() - 1];
+        if last <= 0.0 {
+            //the same all the way around
+            0.0
+        } else if last <= 90.0 {
+            //the same in each quadrant
+            let horizontal = if horizontal > 180.0 {
+                360.0 - horizontal
+            } else {
+                horizontal
+            };
+            if horizontal > 90.0 {
+                180.0 - horizontal
+            } else {
+                horizontal
+            }
+        } else if last <= 180.0 && horizontal > 180.0 {
+            //mirrored across the 0-180 plane
+            360.0 - horizontal
+        } else {
+            horizontal
+        }
+    }
+}
+
+///The index of the measurement before `angle` and how far it is to the next, or [`None`] if it is outside the measurements
+fn segment(angles: &[Decimal], angle: Decimal) -> Option<(usize, Decimal)> {
+    let (first, last) = (angles[0], angles[angles.len() - 1]);
+    if angle < first || angle > last {
+        return None;
+    }
+    if angles.len() == 1 {
+        return Some((0, 0.0));
+    }
+
+    let index = angles
+        .partition_point(|&measured| measured <= angle)
+        .clamp(1, angles.len() - 1)
+        - 1;
+    let (start, end) = (angles[index], angles[index + 1]);
+    let weight = if end > start {
+        ((angle - start) / (end - start)).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    Some((index, weight))
+}
+
+///A point light shaped by an [`IesProfile`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesLight {
+    position: Point3,
+    profile: Arc<IesProfile>,
+    ///the irradiance one unit away in the brightest direction
+    intensity: Colour,
+    ///unit vector for a vertical angle of 0
+    down: Vec3,
+    ///unit vector for a horizontal angle of 0, perpendicular to `down`
+    across: Vec3,
+}
+
+impl IesLight {
+    ///A fixture at `position` with its downwards direction pointing at `target`.
+    ///
+    ///The profile is scaled so its brightest direction has `intensity`, like a [`PointLight`](super::PointLight). A horizontal angle of 0 is as close to +x as it can be.
+    #[must_use]
+    pub fn new(
+        position: Point3,
+        target: Point3,
+        profile: Arc<IesProfile>,
+        intensity: Colour,
+    ) -> Self {
+        let down = (target - position).unit();
+        let reference = if down.x().abs() < 0.99 {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        };
+        let across = (reference - down * reference.dot(down)).unit();
+
+        Self {
+            position,
+            profile,
+            intensity,
+            down,
+            across,
+        }
+    }
+
+    ///Spins the fixture around its downwards direction
+    #[must_use]
+    pub fn with_rotation(mut self, degrees: Decimal) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        self.across = self.across * cos + self.sideways() * sin;
+        self
+    }
+
+    ///The unit vector for a horizontal angle of 90
+    fn sideways(&self) -> Vec3 {
+        (-self.down).cross(self.across)
+    }
+}
+
+impl IesLight {
+    ///The fraction of the brightest intensity going along `outgoing`, a unit vector away from the fixture
+    fn fraction(&self, outgoing: Vec3) -> Decimal {
+        //in the fixture's own space
+        let vertical = outgoing.dot(self.down).clamp(-1.0, 1.0).acos();
+        let horizontal = outgoing
+            .dot(self.sideways())
+            .atan2(outgoing.dot(self.across));
+
+        self.profile
+            .candela(vertical.to_degrees(), horizontal.to_degrees())
+            / self.profile.max()
+    }
+}
+
+impl Light for IesLight {
+    fn sample(&self, point: Point3) -> Option<LightSample> {
+        if self.profile.max() <= 0.0 {
+            return None;
+        }
+        let mut sample = towards(point, self.position, self.intensity)?;
+
+        let fraction = self.fraction(-sample.direction);
+        if fraction <= 0.0 {
+            return None;
+        }
+        sample.irradiance *= fraction;
+        Some(sample)
+    }
+
+    fn position(&self) -> Option<Point3> {
+        Some(self.position)
+    }
+    fn intensity(&self, direction: Vec3) -> Colour {
+        if self.profile.max() <= 0.0 {
+            return Colour::default();
+        }
+        self.intensity * self.fraction(direction).max(0.0)
+    }
+}
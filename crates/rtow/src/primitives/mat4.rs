@@ -0,0 +1,175 @@
+use crate::primitives::{Decimal, Vec3};
+use std::ops::{Index, IndexMut, Mul};
+
+///A 4x4 matrix, stored in row-major configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4([[Decimal; 4]; 4]);
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self([
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]);
+
+    #[must_use]
+    pub const fn new(rows: [[Decimal; 4]; 4]) -> Self {
+        Self(rows)
+    }
+
+    #[must_use]
+    pub const fn translation(by: Vec3) -> Self {
+        Self([
+            [1.0, 0.0, 0.0, by.x()],
+            [0.0, 1.0, 0.0, by.y()],
+            [0.0, 0.0, 1.0, by.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    #[must_use]
+    pub const fn scale(by: Vec3) -> Self {
+        Self([
+            [by.x(), 0.0, 0.0, 0.0],
+            [0.0, by.y(), 0.0, 0.0],
+            [0.0, 0.0, by.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+    ///Rotation of `degrees` anti-clockwise around `axis`, using Rodrigues' formula
+    #[must_use]
+    pub fn rotation(axis: Vec3, degrees: Decimal) -> Self {
+        let axis = axis.unit();
+        let (x, y, z) = (axis.x(), axis.y(), axis.z());
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let t = 1.0 - cos;
+
+        Self([
+            [
+                t * x * x + cos,
+                t * x * y - sin * z,
+                t * x * z + sin * y,
+                0.0,
+            ],
+            [
+                t * x * y + sin * z,
+                t * y * y + cos,
+                t * y * z - sin * x,
+                0.0,
+            ],
+            [
+                t * x * z - sin * y,
+                t * y * z + sin * x,
+                t * z * z + cos,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    #[must_use]
+    pub fn transpose(&self) -> Self {
+        let mut out = Self::IDENTITY;
+        for row in 0..4 {
+            for col in 0..4 {
+                out[(row, col)] = self[(col, row)];
+            }
+        }
+        out
+    }
+
+    ///Inverts the matrix using Gauss-Jordan elimination with partial pivoting.
+    ///
+    ///Returns [`None`] if the matrix is singular (eg. a scale by 0 on one axis)
+    #[must_use]
+    pub fn inverse(&self) -> Option<Self> {
+        let mut m = self.0;
+        let mut inv = Self::IDENTITY.0;
+
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&a, &b| m[a][col].abs().total_cmp(&m[b][col].abs()))?;
+            if m[pivot][col].abs() < Decimal::EPSILON {
+                return None;
+            }
+            m.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / m[col][col];
+            for i in 0..4 {
+                m[col][i] *= scale;
+                inv[col][i] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = m[row][col];
+                for i in 0..4 {
+                    m[row][i] -= factor * m[col][i];
+                    inv[row][i] -= factor * inv[col][i];
+                }
+            }
+        }
+
+        Some(Self(inv))
+    }
+
+    ///Transforms a point, so translations are applied
+    #[must_use]
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        let transformed =
+            self.transform_vector(point) + Vec3::new(self.0[0][3], self.0[1][3], self.0[2][3]);
+        let [bottom_x, bottom_y, bottom_z, bottom_w] = self.0[3];
+        let w = bottom_x * point.x() + bottom_y * point.y() + bottom_z * point.z() + bottom_w;
+
+        if (w - 1.0).abs() < Decimal::EPSILON || w == 0.0 {
+            transformed
+        } else {
+            transformed / w
+        }
+    }
+    ///Transforms a direction, so translations are ignored
+    #[must_use]
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.0;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut out = [[0.0; 4]; 4];
+        for (row, out_row) in out.iter_mut().enumerate() {
+            for (col, out_cell) in out_row.iter_mut().enumerate() {
+                *out_cell = (0..4).map(|i| self[(row, i)] * rhs[(i, col)]).sum();
+            }
+        }
+        Self(out)
+    }
+}
+
+impl Index<(usize, usize)> for Mat4 {
+    type Output = Decimal;
+
+    fn index(&self, (row, col): (usize, usize)) -> &Self::Output {
+        &self.0[row][col]
+    }
+}
+impl IndexMut<(usize, usize)> for Mat4 {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut Self::Output {
+        &mut self.0[row][col]
+    }
+}
@@ -1,25 +1,112 @@
-use crate::primitives::{collisions::HitRecord, Colour, Decimal, Point3, Ray};
+pub mod bump;
+pub mod coated;
+pub mod dispersion;
+pub mod microfacet;
+pub mod principled;
+pub mod thin_film;
+
+use crate::primitives::{
+    collisions::HitRecord,
+    decimal_consts::PI,
+    media::Medium,
+    spectrum::{illuminant::Illuminant, Wavelengths},
+    textures::Texture,
+    Colour, Decimal, Point3, Ray, Vec3,
+};
+use coated::ClearCoat;
+use dispersion::{Ior, D_LINE};
+use microfacet::{fresnel_dielectric, ComplexIor, Frame, Ggx};
+use principled::{Lobes, Principled};
 use rand::{rngs::ThreadRng, Rng};
-use std::fmt::Debug;
+use std::{fmt::Debug, sync::Arc};
+use thin_film::ThinFilm;
+
+///The kinds of scattering, which paths can have separate limits on
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Lobe {
+    Diffuse,
+    ///mirror-like and glossy reflections
+    Specular,
+    ///going through the surface
+    Transmission,
+}
 
-#[derive(Debug, Copy, Clone)]
+///Colours and fuzz are [`Texture`]s - a plain [`Colour`] can be turned into one with `.into()`, and fuzz uses [`Texture::scalar`]
+#[derive(Debug, Clone)]
 pub enum Material {
     ///albedo
-    LambertianDiffuse(Colour),
-    ///albedo, fuzz
-    MetalReflection(Colour, Decimal),
-    ///index of refraction
-    DielectricRefraction(Decimal),
+    LambertianDiffuse(Arc<dyn Texture>),
+    ///albedo, fuzz - a cheap approximation, use [`Material::MicrofacetConductor`] for physically based metals
+    MetalReflection(Arc<dyn Texture>, Arc<dyn Texture>),
+    ///complex index of refraction, roughness - a rough metal using the GGX microfacet model
+    MicrofacetConductor(ComplexIor, Arc<dyn Texture>),
+    ///index of refraction, roughness - frosted glass using the GGX microfacet model
+    MicrofacetDielectric(Decimal, Arc<dyn Texture>),
+    ///index of refraction - a plain number can be turned into an [`Ior`] with `.into()`
+    DielectricRefraction(Ior),
+    ///a Disney-style uber-material, with lobes for diffuse, sheen, specular, clearcoat and transmission
+    Principled(Box<Principled>),
+    ///a clear layer like varnish or lacquer over another material
+    Coated(Box<ClearCoat>),
+    ///thickness in nanometres, index of refraction, material - a thin film which makes a conductor or dielectric under it iridescent, like a soap bubble or oil on water.
+    ///
+    ///The thickness comes from [`Texture::scalar`], so it can swirl around. Other materials are left as they are.
+    ThinFilm(Arc<dyn Texture>, Decimal, Box<Self>),
+    ///emitted radiance - used to make area lights out of any [`Sampleable`](super::collisions::Sampleable) object
+    DiffuseLight(Arc<dyn Texture>),
+    ///emitted light with a spectrum, like a black body - the same as [`Material::DiffuseLight`] unless we're rendering spectrally
+    SpectralLight(Illuminant),
+    ///tangent space normal map, material - works on anything with UVs
+    NormalMapped(Arc<dyn Texture>, Box<Self>),
+    ///height map, strength, material - the height comes from [`Texture::scalar`]
+    BumpMapped(Arc<dyn Texture>, Decimal, Box<Self>),
+    ///medium, surface - fills the closed object this is on with the medium, and the boundary is invisible if there's no surface
+    Volume(Arc<dyn Medium>, Option<Box<Self>>),
+    ///weight, first, second - each hit picks the second material with the probability from [`Texture::scalar`], and the first otherwise
+    Mix(Arc<dyn Texture>, Box<Self>, Box<Self>),
+    ///opacity, material - a hole where the opacity from [`Texture::scalar`] is 0, so a single quad can be a leaf or a fence
+    Cutout(Arc<dyn Texture>, Box<Self>),
+    ///front, back - a different material on each side of a surface
+    TwoSided(Box<Self>, Box<Self>),
 }
 
 impl Material {
+    ///The material which is actually at a hit, picking from [`Material::Mix`]es and the sides of [`Material::TwoSided`]s, or [`None`] if it's a hole in a [`Material::Cutout`]
+    #[must_use]
+    pub fn resolve(&self, hit_record: &HitRecord, rng: &mut ThreadRng) -> Option<&Self> {
+        let (u, v, point) = (hit_record.u, hit_record.v, hit_record.point);
+        match self {
+            Self::Mix(weight, first, second) => {
+                let material = if rng.gen::<Decimal>() < weight.scalar(u, v, point) {
+                    second
+                } else {
+                    first
+                };
+                material.resolve(hit_record, rng)
+            }
+            Self::Cutout(opacity, material) => {
+                if rng.gen::<Decimal>() < opacity.scalar(u, v, point) {
+                    material.resolve(hit_record, rng)
+                } else {
+                    None
+                }
+            }
+            Self::TwoSided(front, back) => {
+                let material = if hit_record.front_face { front } else { back };
+                material.resolve(hit_record, rng)
+            }
+            _ => Some(self),
+        }
+    }
+
+    ///Picks a direction for light arriving back along `ray_in` to have come from, giving its weight (the BSDF times the cosine over the pdf), the scattered ray, and the [`Lobe`] it was picked from
     #[must_use]
     pub fn scatter(
         &self,
         ray_in: Ray,
         hit_record: HitRecord,
         rng: &mut ThreadRng,
-    ) -> Option<(Colour, Ray)> {
+    ) -> Option<(Colour, Ray, Lobe)> {
         match self {
             Self::LambertianDiffuse(albedo) => {
                 let mut scatter_dir = hit_record.normal + Point3::random_unit_vector(rng);
@@ -28,51 +115,888 @@ impl Material {
                     scatter_dir = hit_record.normal;
                 }
 
-                Some((*albedo, Ray::new(hit_record.point, scatter_dir)))
+                Some((
+                    albedo.filtered_value(
+                        hit_record.u,
+                        hit_record.v,
+                        hit_record.point,
+                        hit_record.footprint,
+                    ),
+                    Ray::new(hit_record.point, scatter_dir),
+                    Lobe::Diffuse,
+                ))
             }
             Self::MetalReflection(albedo, fuzz) => {
+                let fuzz = fuzz.scalar(hit_record.u, hit_record.v, hit_record.point);
                 let reflected = ray_in.direction().unit().reflect(hit_record.normal);
                 let scattered = Ray::new(
                     hit_record.point,
-                    reflected + *fuzz * Point3::random_in_unit_sphere(rng),
+                    reflected + fuzz * Point3::random_in_unit_sphere(rng),
                 );
                 if scattered.direction().dot(hit_record.normal) > 0.0 {
-                    Some((*albedo, scattered))
+                    Some((
+                        albedo.filtered_value(
+                            hit_record.u,
+                            hit_record.v,
+                            hit_record.point,
+                            hit_record.footprint,
+                        ),
+                        scattered,
+                        Lobe::Specular,
+                    ))
                 } else {
                     None
                 }
             }
-            Self::DielectricRefraction(ior) => {
-                fn reflectance(cos: Decimal, reference_index: Decimal) -> Decimal {
-                    //shlick's approximation
-                    let r_0 = ((1.0 - reference_index) / (1.0 + reference_index)).powi(2);
-                    r_0 + (1.0 - r_0) * (1.0 - cos).powi(5)
+            Self::DielectricRefraction(ior) => Some(scatter_smooth_dielectric(
+                *ior, None, ray_in, hit_record, rng,
+            )),
+            Self::MicrofacetConductor(ior, roughness) => {
+                let roughness = roughness.scalar(hit_record.u, hit_record.v, hit_record.point);
+                scatter_conductor(*ior, None, Ggx::new(roughness), ray_in, hit_record, rng)
+            }
+            Self::MicrofacetDielectric(ior, roughness) => {
+                let roughness = roughness.scalar(hit_record.u, hit_record.v, hit_record.point);
+                scatter_dielectric(*ior, None, Ggx::new(roughness), ray_in, hit_record, rng)
+            }
+            Self::Principled(principled) => {
+                principled.scatter(ray_in.direction(), &hit_record, rng)
+            }
+            Self::Coated(coat) => coat.scatter(ray_in.direction(), hit_record, rng),
+            Self::ThinFilm(thickness, ior, material) => {
+                let (u, v, point) = (hit_record.u, hit_record.v, hit_record.point);
+                let film = Some(ThinFilm::new(thickness.scalar(u, v, point), *ior));
+                match material.as_ref() {
+                    Self::MicrofacetConductor(base, roughness) => {
+                        let ggx = Ggx::new(roughness.scalar(u, v, point));
+                        scatter_conductor(*base, film, ggx, ray_in, hit_record, rng)
+                    }
+                    Self::MicrofacetDielectric(base, roughness) => {
+                        let ggx = Ggx::new(roughness.scalar(u, v, point));
+                        scatter_dielectric(*base, film, ggx, ray_in, hit_record, rng)
+                    }
+                    Self::DielectricRefraction(base) => Some(scatter_smooth_dielectric(
+                        *base, film, ray_in, hit_record, rng,
+                    )),
+                    material => material.scatter(ray_in, hit_record, rng),
                 }
+            }
+            Self::DiffuseLight(_) | Self::SpectralLight(_) => None,
+            Self::NormalMapped(map, material) => {
+                let normal = bump::normal_mapped(map.as_ref(), &hit_record);
+                bump::scatter_with_normal(material, normal, ray_in, hit_record, rng)
+            }
+            Self::BumpMapped(height, strength, material) => {
+                let normal = bump::bump_mapped(height.as_ref(), *strength, &hit_record);
+                bump::scatter_with_normal(material, normal, ray_in, hit_record, rng)
+            }
+            Self::Volume(_, Some(surface)) => surface.scatter(ray_in, hit_record, rng),
+            Self::Volume(_, None) => Some((
+                Colour::splat(1.0),
+                Ray::new(hit_record.point, ray_in.direction()),
+                Lobe::Transmission,
+            )),
+            Self::Mix(..) | Self::Cutout(..) | Self::TwoSided(..) => {
+                self.resolve(&hit_record, rng).map_or_else(
+                    || {
+                        Some((
+                            Colour::splat(1.0),
+                            Ray::new(hit_record.point, ray_in.direction()),
+                            Lobe::Transmission,
+                        ))
+                    },
+                    |material| material.scatter(ray_in, hit_record, rng),
+                )
+            }
+        }
+    }
 
-                let refraction_ratio = if hit_record.front_face {
-                    1.0 / ior
-                } else {
-                    *ior
-                };
+    ///The BSDF times the cosine for light arriving from `direction` and leaving back along `ray_in`, and the probability density of [`Material::scatter`] picking `direction`.
+    ///
+    ///Layered materials can only give an estimate of the BSDF which is right on average, which is what `rng` is for.
+    ///
+    ///Returns [`None`] for materials which only scatter in a few exact directions, as sampled lights can never line up with them.
+    #[must_use]
+    pub fn evaluate(
+        &self,
+        ray_in: Ray,
+        hit_record: HitRecord,
+        direction: Vec3,
+        rng: &mut ThreadRng,
+    ) -> Option<(Colour, Decimal)> {
+        self.evaluate_lobes(ray_in, hit_record, direction, rng)
+            .map(|(value, pdf, _)| (value, pdf))
+    }
 
-                let unit_direction = ray_in.direction().unit();
-                let cos_theta = (-unit_direction).dot(hit_record.normal).min(1.0);
-                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt(); //identities
+    ///Like [`Material::evaluate`], but also gives the part of the BSDF times the cosine which is from diffuse lobes, for splitting light up into passes
+    #[must_use]
+    pub fn evaluate_lobes(
+        &self,
+        ray_in: Ray,
+        hit_record: HitRecord,
+        direction: Vec3,
+        rng: &mut ThreadRng,
+    ) -> Option<(Colour, Decimal, Colour)> {
+        let (u, v, point) = (hit_record.u, hit_record.v, hit_record.point);
+        let specular = |(value, pdf)| Some((value, pdf, Colour::default()));
+        match self {
+            Self::LambertianDiffuse(albedo) => {
+                let cos_theta = hit_record.normal.dot(direction.unit());
+                if cos_theta <= 0.0 {
+                    return Some((Colour::default(), 0.0, Colour::default()));
+                }
+                let value =
+                    albedo.filtered_value(u, v, point, hit_record.footprint) * (cos_theta / PI);
+                Some((value, cos_theta / PI, value))
+            }
+            Self::MicrofacetConductor(ior, roughness) => specular(evaluate_conductor(
+                *ior,
+                None,
+                Ggx::new(roughness.scalar(u, v, point)),
+                ray_in,
+                hit_record,
+                direction,
+            )),
+            Self::MicrofacetDielectric(ior, roughness) => specular(evaluate_dielectric(
+                *ior,
+                None,
+                Ggx::new(roughness.scalar(u, v, point)),
+                ray_in,
+                hit_record,
+                direction,
+            )),
+            Self::Principled(principled) => {
+                Some(principled.evaluate(ray_in.direction(), &hit_record, direction))
+            }
+            Self::Coated(coat) => coat.evaluate(ray_in.direction(), hit_record, direction, rng),
+            Self::ThinFilm(thickness, ior, material) => {
+                let film = Some(ThinFilm::new(thickness.scalar(u, v, point), *ior));
+                match material.as_ref() {
+                    Self::MicrofacetConductor(base, roughness) => {
+                        let ggx = Ggx::new(roughness.scalar(u, v, point));
+                        specular(evaluate_conductor(
+                            *base, film, ggx, ray_in, hit_record, direction,
+                        ))
+                    }
+                    Self::MicrofacetDielectric(base, roughness) => {
+                        let ggx = Ggx::new(roughness.scalar(u, v, point));
+                        specular(evaluate_dielectric(
+                            *base, film, ggx, ray_in, hit_record, direction,
+                        ))
+                    }
+                    material => material.evaluate_lobes(ray_in, hit_record, direction, rng),
+                }
+            }
+            Self::NormalMapped(map, material) => {
+                let normal = bump::normal_mapped(map.as_ref(), &hit_record);
+                bump::evaluate_with_normal(material, normal, ray_in, hit_record, direction, rng)
+            }
+            Self::BumpMapped(height, strength, material) => {
+                let normal = bump::bump_mapped(height.as_ref(), *strength, &hit_record);
+                bump::evaluate_with_normal(material, normal, ray_in, hit_record, direction, rng)
+            }
+            Self::Volume(_, Some(surface)) => {
+                surface.evaluate_lobes(ray_in, hit_record, direction, rng)
+            }
+            Self::Mix(weight, first, second) => {
+                //the pdf has to cover both, so this can only be done for materials which aren't picked already
+                let weight = weight.scalar(u, v, point);
+                let (first_value, first_pdf, first_diffuse) =
+                    first.evaluate_lobes(ray_in, hit_record, direction, rng)?;
+                let (second_value, second_pdf, second_diffuse) =
+                    second.evaluate_lobes(ray_in, hit_record, direction, rng)?;
+                Some((
+                    first_value * (1.0 - weight) + second_value * weight,
+                    first_pdf * (1.0 - weight) + second_pdf * weight,
+                    first_diffuse * (1.0 - weight) + second_diffuse * weight,
+                ))
+            }
+            Self::Cutout(opacity, material) => {
+                let opacity = opacity.scalar(u, v, point);
+                let (value, pdf, diffuse) =
+                    material.evaluate_lobes(ray_in, hit_record, direction, rng)?;
+                Some((value * opacity, pdf * opacity, diffuse * opacity))
+            }
+            Self::TwoSided(front, back) => {
+                let material = if hit_record.front_face { front } else { back };
+                material.evaluate_lobes(ray_in, hit_record, direction, rng)
+            }
+            Self::MetalReflection(..)
+            | Self::DielectricRefraction(_)
+            | Self::DiffuseLight(_)
+            | Self::SpectralLight(_)
+            | Self::Volume(_, None) => None,
+        }
+    }
+
+    ///Light given off by the material - lights only emit from their front face
+    #[must_use]
+    pub fn emitted(&self, hit_record: HitRecord) -> Colour {
+        match self {
+            Self::DiffuseLight(radiance) if hit_record.front_face => radiance.filtered_value(
+                hit_record.u,
+                hit_record.v,
+                hit_record.point,
+                hit_record.footprint,
+            ),
+            Self::SpectralLight(illuminant) if hit_record.front_face => illuminant.rgb(),
+            Self::NormalMapped(_, material)
+            | Self::BumpMapped(_, _, material)
+            | Self::Volume(_, Some(material)) => material.emitted(hit_record),
+            Self::Mix(weight, first, second) => {
+                let weight = weight.scalar(hit_record.u, hit_record.v, hit_record.point);
+                first.emitted(hit_record) * (1.0 - weight) + second.emitted(hit_record) * weight
+            }
+            Self::Cutout(opacity, material) => {
+                material.emitted(hit_record)
+                    * opacity.scalar(hit_record.u, hit_record.v, hit_record.point)
+            }
+            Self::TwoSided(front, back) => {
+                let material = if hit_record.front_face { front } else { back };
+                material.emitted(hit_record)
+            }
+            _ => Colour::default(),
+        }
+    }
+    ///Like [`Material::emitted`], but for each of the wavelengths of a spectral path
+    #[must_use]
+    pub fn emitted_spectrum(&self, hit_record: HitRecord, wavelengths: &Wavelengths) -> Colour {
+        match self {
+            Self::SpectralLight(illuminant) if hit_record.front_face => {
+                wavelengths.spectrum(|wavelength| illuminant.at(wavelength))
+            }
+            Self::NormalMapped(_, material)
+            | Self::BumpMapped(_, _, material)
+            | Self::Volume(_, Some(material)) => material.emitted_spectrum(hit_record, wavelengths),
+            Self::Mix(weight, first, second) => {
+                let weight = weight.scalar(hit_record.u, hit_record.v, hit_record.point);
+                first.emitted_spectrum(hit_record, wavelengths) * (1.0 - weight)
+                    + second.emitted_spectrum(hit_record, wavelengths) * weight
+            }
+            Self::Cutout(opacity, material) => {
+                material.emitted_spectrum(hit_record, wavelengths)
+                    * opacity.scalar(hit_record.u, hit_record.v, hit_record.point)
+            }
+            Self::TwoSided(front, back) => {
+                let material = if hit_record.front_face { front } else { back };
+                material.emitted_spectrum(hit_record, wavelengths)
+            }
+            _ => wavelengths.illuminant(self.emitted(hit_record)),
+        }
+    }
+
+    ///The colour of the surface without any lighting, for the albedo AOV - the base colour for diffuse materials, the reflectance straight on for conductors, and white for clear dielectrics
+    #[must_use]
+    pub fn albedo(&self, hit_record: &HitRecord) -> Colour {
+        let (u, v, point) = (hit_record.u, hit_record.v, hit_record.point);
+        match self {
+            Self::LambertianDiffuse(albedo) | Self::MetalReflection(albedo, _) => {
+                albedo.filtered_value(u, v, point, hit_record.footprint)
+            }
+            Self::MicrofacetConductor(ior, _) => ior.fresnel(1.0),
+            Self::MicrofacetDielectric(..) | Self::DielectricRefraction(_) => Colour::splat(1.0),
+            Self::Principled(principled) => Lobes::new(principled, hit_record).albedo(),
+            Self::Coated(coat) => {
+                coat.base.albedo(hit_record)
+                    * coat
+                        .colour
+                        .filtered_value(u, v, point, hit_record.footprint)
+            }
+            Self::ThinFilm(_, _, material)
+            | Self::NormalMapped(_, material)
+            | Self::BumpMapped(_, _, material)
+            | Self::Volume(_, Some(material))
+            | Self::Cutout(_, material) => material.albedo(hit_record),
+            Self::Mix(weight, first, second) => {
+                let weight = weight.scalar(u, v, point);
+                first.albedo(hit_record) * (1.0 - weight) + second.albedo(hit_record) * weight
+            }
+            Self::TwoSided(front, back) => {
+                let material = if hit_record.front_face { front } else { back };
+                material.albedo(hit_record)
+            }
+            Self::DiffuseLight(_) | Self::SpectralLight(_) | Self::Volume(_, None) => {
+                Colour::default()
+            }
+        }
+    }
+
+    ///Whether where light goes depends on its wavelength, so a spectral path can only follow its hero wavelength
+    #[must_use]
+    pub fn is_dispersive(&self) -> bool {
+        match self {
+            Self::DielectricRefraction(ior) => ior.is_dispersive(),
+            Self::Coated(coat) => coat.base.is_dispersive(),
+            Self::NormalMapped(_, material)
+            | Self::ThinFilm(_, _, material)
+            | Self::BumpMapped(_, _, material)
+            | Self::Volume(_, Some(material))
+            | Self::Cutout(_, material) => material.is_dispersive(),
+            Self::Mix(_, first, second) | Self::TwoSided(first, second) => {
+                first.is_dispersive() || second.is_dispersive()
+            }
+            _ => false,
+        }
+    }
+
+    ///Whether rays go straight through without anything happening, like the boundary of a volume without a surface
+    #[must_use]
+    pub const fn is_invisible(&self) -> bool {
+        matches!(self, Self::Volume(_, None))
+    }
 
-                let direction = if refraction_ratio * sin_theta > 1.0
-                    || reflectance(cos_theta, refraction_ratio) > rng.gen_range(0.0..=1.0)
-                {
-                    //can't refract
-                    unit_direction.reflect(hit_record.normal)
+    ///The medium inside the object, if it's the boundary of a volume
+    #[must_use]
+    pub fn medium(&self) -> Option<&dyn Medium> {
+        match self {
+            Self::Volume(medium, _) => Some(medium.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+///The fraction of light reflected by a conductor, or by a thin film on it
+fn conductor_reflectance(ior: ComplexIor, film: Option<ThinFilm>, cos_theta: Decimal) -> Colour {
+    film.map_or_else(
+        || ior.fresnel(cos_theta),
+        |film| film.over_conductor(cos_theta, ior),
+    )
+}
+
+///The fraction of light reflected going from a dielectric with index `near` to one with `far`, or by a thin film between them
+fn dielectric_reflectance(
+    near: Decimal,
+    far: Decimal,
+    film: Option<ThinFilm>,
+    cos_theta: Decimal,
+) -> Colour {
+    film.map_or_else(
+        || Colour::splat(fresnel_dielectric(cos_theta, far / near)),
+        |film| film.over_dielectric(cos_theta, near, far),
+    )
+}
+
+fn average(colour: Colour) -> Decimal {
+    (colour.x() + colour.y() + colour.z()) / 3.0
+}
+
+///Reflects off or refracts through a perfectly smooth surface
+fn scatter_smooth_dielectric(
+    ior: Ior,
+    film: Option<ThinFilm>,
+    ray_in: Ray,
+    hit_record: HitRecord,
+    rng: &mut ThreadRng,
+) -> (Colour, Ray, Lobe) {
+    fn reflectance(cos: Decimal, reference_index: Decimal) -> Decimal {
+        //shlick's approximation
+        let r_0 = ((1.0 - reference_index) / (1.0 + reference_index)).powi(2);
+        r_0 + (1.0 - r_0) * (1.0 - cos).powi(5)
+    }
+
+    let ior = ior.at(ray_in
+        .wavelengths()
+        .map_or(D_LINE, |wavelengths| wavelengths.hero()));
+    let refraction_ratio = if hit_record.front_face {
+        1.0 / ior
+    } else {
+        ior
+    };
+
+    let unit_direction = ray_in.direction().unit();
+    let cos_theta = (-unit_direction).dot(hit_record.normal).min(1.0);
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt(); //identities
+    let reflected = Ray::new(hit_record.point, unit_direction.reflect(hit_record.normal));
+    let refracted = Ray::new(
+        hit_record.point,
+        unit_direction.refract(hit_record.normal, refraction_ratio),
+    );
+    if refraction_ratio * sin_theta > 1.0 {
+        //can't refract
+        return (Colour::splat(1.0), reflected, Lobe::Specular);
+    }
+
+    let Some(film) = film else {
+        let (scattered, lobe) =
+            if reflectance(cos_theta, refraction_ratio) > rng.gen_range(0.0..=1.0) {
+                (reflected, Lobe::Specular)
+            } else {
+                (refracted, Lobe::Transmission)
+            };
+        return (Colour::splat(1.0), scattered, lobe);
+    };
+
+    //the film can reflect some colours more than others, so pick in proportion to the average and weight by the rest
+    let (near, far) = sides(ior, &hit_record);
+    let reflectance = film.over_dielectric(cos_theta, near, far);
+    let probability = average(reflectance);
+    if rng.gen::<Decimal>() < probability {
+        (reflectance / probability, reflected, Lobe::Specular)
+    } else {
+        (
+            (Colour::splat(1.0) - reflectance) / (1.0 - probability),
+            refracted,
+            Lobe::Transmission,
+        )
+    }
+}
+
+///Reflects off a microfacet sampled from the visible normals
+fn scatter_conductor(
+    ior: ComplexIor,
+    film: Option<ThinFilm>,
+    ggx: Ggx,
+    ray_in: Ray,
+    hit_record: HitRecord,
+    rng: &mut ThreadRng,
+) -> Option<(Colour, Ray, Lobe)> {
+    let frame = Frame::new(hit_record.normal);
+    let outgoing = frame.to_local(-ray_in.direction().unit());
+
+    let microfacet = ggx.sample_visible_normal(outgoing, (rng.gen(), rng.gen()));
+    let incoming = (-outgoing).reflect(microfacet);
+    if incoming.z() <= 0.0 {
+        //reflected into the surface, so it would have hit another microfacet
+        return None;
+    }
+
+    let weight = conductor_reflectance(ior, film, outgoing.dot(microfacet))
+        * (ggx.masking_shadowing(outgoing, incoming) / ggx.masking(outgoing));
+    Some((
+        weight,
+        Ray::new(hit_record.point, frame.to_world(incoming)),
+        Lobe::Specular,
+    ))
+}
+
+///The conductor BRDF times the cosine, and the pdf of [`scatter_conductor`]
+fn evaluate_conductor(
+    ior: ComplexIor,
+    film: Option<ThinFilm>,
+    ggx: Ggx,
+    ray_in: Ray,
+    hit_record: HitRecord,
+    direction: Vec3,
+) -> (Colour, Decimal) {
+    let frame = Frame::new(hit_record.normal);
+    let outgoing = frame.to_local(-ray_in.direction().unit());
+    let incoming = frame.to_local(direction.unit());
+    if outgoing.z() <= 0.0 || incoming.z() <= 0.0 {
+        return (Colour::default(), 0.0);
+    }
+
+    let microfacet = (outgoing + incoming).unit();
+    let distribution = ggx.distribution(microfacet);
+    //the cosine cancels with the one in the denominator of the BRDF
+    let value = conductor_reflectance(ior, film, outgoing.dot(microfacet))
+        * (distribution * ggx.masking_shadowing(outgoing, incoming) / (4.0 * outgoing.z()));
+    let pdf = ggx.visible_normal_pdf(outgoing, microfacet) / (4.0 * outgoing.dot(microfacet));
+    (value, pdf)
+}
+
+///The indices of refraction on the near and far sides of a dielectric surface
+const fn sides(ior: Decimal, hit_record: &HitRecord) -> (Decimal, Decimal) {
+    if hit_record.front_face {
+        (1.0, ior)
+    } else {
+        (ior, 1.0)
+    }
+}
+
+///Reflects off or refracts through a microfacet sampled from the visible normals
+fn scatter_dielectric(
+    ior: Decimal,
+    film: Option<ThinFilm>,
+    ggx: Ggx,
+    ray_in: Ray,
+    hit_record: HitRecord,
+    rng: &mut ThreadRng,
+) -> Option<(Colour, Ray, Lobe)> {
+    let frame = Frame::new(hit_record.normal);
+    let outgoing = frame.to_local(-ray_in.direction().unit());
+    let (near, far) = sides(ior, &hit_record);
+    //the index on the far side over the one on the near side
+    let eta = far / near;
+
+    let microfacet = ggx.sample_visible_normal(outgoing, (rng.gen(), rng.gen()));
+    let reflectance = dielectric_reflectance(near, far, film, outgoing.dot(microfacet));
+    let probability = average(reflectance);
+
+    //pick reflection or refraction in proportion to the fresnel term, so it cancels out of the weight
+    let (incoming, fresnel, lobe) = if rng.gen::<Decimal>() < probability {
+        let incoming = (-outgoing).reflect(microfacet);
+        if incoming.z() <= 0.0 {
+            return None;
+        }
+        (incoming, reflectance / probability, Lobe::Specular)
+    } else {
+        let incoming = (-outgoing).refract(microfacet, 1.0 / eta);
+        if incoming.z() >= 0.0 {
+            return None;
+        }
+        (
+            incoming,
+            (Colour::splat(1.0) - reflectance) / (1.0 - probability),
+            Lobe::Transmission,
+        )
+    };
+
+    let weight = ggx.masking_shadowing(outgoing, incoming) / ggx.masking(outgoing);
+    Some((
+        fresnel * weight,
+        Ray::new(hit_record.point, frame.to_world(incoming)),
+        lobe,
+    ))
+}
+
+///The rough dielectric BSDF times the cosine, and the pdf of [`scatter_dielectric`]
+fn evaluate_dielectric(
+    ior: Decimal,
+    film: Option<ThinFilm>,
+    ggx: Ggx,
+    ray_in: Ray,
+    hit_record: HitRecord,
+    direction: Vec3,
+) -> (Colour, Decimal) {
+    let frame = Frame::new(hit_record.normal);
+    let outgoing = frame.to_local(-ray_in.direction().unit());
+    let incoming = frame.to_local(direction.unit());
+    if outgoing.z() <= 0.0 || incoming.z() == 0.0 {
+        return (Colour::default(), 0.0);
+    }
+    let (near, far) = sides(ior, &hit_record);
+    let eta = far / near;
+
+    if incoming.z() > 0.0 {
+        let microfacet = (outgoing + incoming).unit();
+        let reflectance = dielectric_reflectance(near, far, film, outgoing.dot(microfacet));
+        let distribution = ggx.distribution(microfacet);
+        let value = reflectance
+            * (distribution * ggx.masking_shadowing(outgoing, incoming) / (4.0 * outgoing.z()));
+        let pdf = average(reflectance) * ggx.visible_normal_pdf(outgoing, microfacet)
+            / (4.0 * outgoing.dot(microfacet));
+        return (value, pdf);
+    }
+
+    //the microfacet that refracts one direction into the other, facing the outgoing side
+    let microfacet = -(outgoing + eta * incoming);
+    if microfacet.near_zero() {
+        return (Colour::default(), 0.0);
+    }
+    let microfacet = microfacet.unit();
+    let microfacet = if microfacet.z() < 0.0 {
+        -microfacet
+    } else {
+        microfacet
+    };
+    let (out_facet, in_facet) = (outgoing.dot(microfacet), incoming.dot(microfacet));
+    if out_facet <= 0.0 || in_facet >= 0.0 {
+        return (Colour::default(), 0.0);
+    }
+
+    let transmitted = Colour::splat(1.0) - dielectric_reflectance(near, far, film, out_facet);
+    let denominator = out_facet + eta * in_facet;
+    //scaled by eta squared, as we don't account for the change in ray density
+    let jacobian = eta * eta * in_facet.abs() / (denominator * denominator);
+    let value = transmitted
+        * (ggx.distribution(microfacet)
+            * ggx.masking_shadowing(outgoing, incoming)
+            * out_facet
+            * jacobian
+            / outgoing.z());
+    let pdf = average(transmitted) * ggx.visible_normal_pdf(outgoing, microfacet) * jacobian;
+    (value, pdf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{microfacet::ComplexIor, principled::Principled, Material};
+    use crate::primitives::{
+        collisions::HitRecord,
+        decimal_consts::{PI, TAU},
+        Colour, Decimal, Ray, Vec3,
+    };
+    use rand::{rngs::ThreadRng, thread_rng, Rng};
+
+    const SAMPLES: usize = 100_000;
+
+    ///A conductor with no real part to its index of refraction reflects everything (F = 1)
+    const MIRROR: ComplexIor =
+        ComplexIor::new(Colour::new(0.0, 0.0, 0.0), Colour::new(1.0, 1.0, 1.0));
+
+    fn average(colour: Colour) -> Decimal {
+        (colour.x() + colour.y() + colour.z()) / 3.0
+    }
+
+    ///A ray arriving at the origin from `outgoing`, and where it hits a surface facing up the z axis - below the surface, it hits the back face
+    fn hit(material: &Material, outgoing: Vec3) -> (Ray, HitRecord<'_>) {
+        let ray = Ray::new(outgoing, -outgoing);
+        (
+            ray,
+            HitRecord::new(1.0, ray, |_| Vec3::new(0.0, 0.0, 1.0), material),
+        )
+    }
+
+    ///A direction `cos_theta` away from the z axis, below it if `cos_theta` is negative
+    fn direction(cos_theta: Decimal) -> Vec3 {
+        Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta)
+    }
+
+    ///The fraction of light arriving from `outgoing` which is scattered at all, averaged over the scatter weights
+    fn scattered(material: &Material, outgoing: Vec3, rng: &mut ThreadRng) -> Decimal {
+        let (ray, hit) = hit(material, outgoing);
+        let total: Decimal = (0..SAMPLES)
+            .filter_map(|_| material.scatter(ray, hit, rng))
+            .map(|(weight, _, _)| average(weight))
+            .sum();
+        total / SAMPLES as Decimal
+    }
+
+    ///Like [`scattered`], but integrating the BSDF times the cosine from [`Material::evaluate`].
+    ///
+    ///Half the directions are from [`Material::scatter`] and half are uniform, so the estimate doesn't blow up on sharp lobes, but still relies on the pdf from [`Material::evaluate`] being right.
+    fn integrated(material: &Material, outgoing: Vec3, rng: &mut ThreadRng) -> Decimal {
+        let (ray, hit) = hit(material, outgoing);
+        let total: Decimal = (0..SAMPLES)
+            .filter_map(|_| {
+                let direction = if rng.gen::<bool>() {
+                    Vec3::random_unit_vector(rng)
                 } else {
-                    //can refract
-                    unit_direction.refract(hit_record.normal, refraction_ratio)
+                    material.scatter(ray, hit, rng)?.1.direction()
                 };
+                let (value, pdf) = material.evaluate(ray, hit, direction, rng)?;
+                Some(average(value) / (0.5 / (4.0 * PI) + 0.5 * pdf))
+            })
+            .sum();
+        total / SAMPLES as Decimal
+    }
 
-                Some((
-                    Colour::new(1.0, 1.0, 1.0),
-                    Ray::new(hit_record.point, direction),
-                ))
+    fn dielectric(roughness: Decimal) -> Material {
+        Material::MicrofacetDielectric(1.5, Colour::splat(roughness).into())
+    }
+    fn conductor(roughness: Decimal) -> Material {
+        Material::MicrofacetConductor(MIRROR, Colour::splat(roughness).into())
+    }
+
+    #[test]
+    fn conductor_white_furnace() {
+        let mut rng = thread_rng();
+
+        for roughness in [0.05, 0.2, 0.5, 1.0] {
+            for cos_theta in [0.9, 0.5, 0.2] {
+                let reflected = scattered(&conductor(roughness), direction(cos_theta), &mut rng);
+                //light bouncing between microfacets is lost, which is only noticeable when it's rough - up to two thirds of it at a roughness of 1
+                let least = if roughness <= 0.2 { 0.97 } else { 0.3 };
+                assert!(
+                    reflected <= 1.0 + 1e-9 && reflected > least,
+                    "roughness {roughness} at cos {cos_theta} reflects {reflected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dielectric_white_furnace() {
+        let mut rng = thread_rng();
+
+        for roughness in [0.05, 0.2, 0.5, 1.0] {
+            //from outside, and from inside where some light is totally internally reflected
+            for cos_theta in [0.9, 0.5, 0.2, -0.9, -0.5, -0.2] {
+                let found = scattered(&dielectric(roughness), direction(cos_theta), &mut rng);
+                let least = if roughness <= 0.05 { 0.97 } else { 0.3 };
+                assert!(
+                    found <= 1.0 + 1e-9 && found > least,
+                    "roughness {roughness} at cos {cos_theta} reflects and transmits {found}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate_integrates_to_scatter() {
+        let mut rng = thread_rng();
+
+        for material in [
+            conductor(0.5),
+            conductor(1.0),
+            dielectric(0.5),
+            dielectric(1.0),
+        ] {
+            for cos_theta in [0.8, 0.4, -0.8] {
+                let outgoing = direction(cos_theta);
+                let sampled = scattered(&material, outgoing, &mut rng);
+                let evaluated = integrated(&material, outgoing, &mut rng);
+                assert!(
+                    (sampled - evaluated).abs() < 0.03,
+                    "{material:?} at cos {cos_theta} scatters {sampled}, but evaluates to {evaluated}"
+                );
+            }
+        }
+    }
+
+    ///Checks that the weight of every direction `material` scatters light arriving from `outgoing` in is the BSDF times the cosine over the pdf from [`Material::evaluate`]
+    fn assert_weights_match_evaluate(material: &Material, outgoing: Vec3, rng: &mut ThreadRng) {
+        let (ray, hit) = hit(material, outgoing);
+        for _ in 0..1000 {
+            let Some((weight, scattered, _)) = material.scatter(ray, hit, rng) else {
+                continue;
+            };
+            let (value, pdf) = material
+                .evaluate(ray, hit, scattered.direction(), rng)
+                .expect("the material can be evaluated");
+            assert!(
+                pdf > 0.0,
+                "{material:?} sampled a direction with a pdf of 0"
+            );
+            let evaluated = value / pdf;
+            for channel in 0..3 {
+                assert!(
+                    (weight[channel] - evaluated[channel]).abs() < 1e-6 * weight[channel].max(1.0),
+                    "{material:?} from {outgoing:?} has a weight of {weight:?}, but f cos / pdf is {evaluated:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn scatter_weight_matches_evaluate() {
+        let mut rng = thread_rng();
+
+        for roughness in [0.05, 0.3, 1.0] {
+            for material in [conductor(roughness), dielectric(roughness)] {
+                for cos_theta in [0.9, 0.3, -0.6] {
+                    assert_weights_match_evaluate(&material, direction(cos_theta), &mut rng);
+                }
+            }
+        }
+    }
+
+    ///A principled material with rough enough lobes to integrate over with uniformly sampled directions
+    fn principled(metallic: Decimal, transmission: Decimal, clearcoat: Decimal) -> Material {
+        Material::Principled(Box::new(Principled {
+            base_colour: Colour::new(0.8, 0.5, 0.2).into(),
+            metallic: Colour::splat(metallic).into(),
+            roughness: Colour::splat(0.6).into(),
+            sheen: 0.5,
+            clearcoat,
+            clearcoat_roughness: 0.4,
+            transmission,
+            ..Principled::default()
+        }))
+    }
+
+    ///Each lobe on its own, and all of them at once
+    fn principled_settings() -> [Material; 5] {
+        [
+            principled(0.0, 0.0, 0.0),
+            principled(1.0, 0.0, 0.0),
+            principled(0.0, 1.0, 0.0),
+            principled(0.0, 0.0, 1.0),
+            principled(0.5, 0.5, 0.5),
+        ]
+    }
+
+    #[test]
+    fn principled_weight_matches_evaluate() {
+        let mut rng = thread_rng();
+
+        for material in principled_settings() {
+            for cos_theta in [0.9, 0.3, -0.6] {
+                assert_weights_match_evaluate(&material, direction(cos_theta), &mut rng);
+            }
+        }
+    }
+
+    #[test]
+    fn principled_pdf_normalised() {
+        let mut rng = thread_rng();
+
+        for material in principled_settings() {
+            for cos_theta in [0.8, 0.3, -0.7] {
+                let (ray, hit) = hit(&material, direction(cos_theta));
+                //half the directions are from scatter, like in `integrated`, which only gives the right answer if the pdf is what scatter samples from
+                let total: Decimal = (0..SAMPLES)
+                    .filter_map(|_| {
+                        let direction = if rng.gen::<bool>() {
+                            Vec3::random_unit_vector(&mut rng)
+                        } else {
+                            material.scatter(ray, hit, &mut rng)?.1.direction()
+                        };
+                        let (_, pdf) = material.evaluate(ray, hit, direction, &mut rng)?;
+                        Some(pdf / (0.5 / (4.0 * PI) + 0.5 * pdf))
+                    })
+                    .sum();
+                let integral = total / SAMPLES as Decimal;
+                //directions sampled on the wrong side of the surface are thrown away, so it can be a bit under 1
+                let sampled = (0..SAMPLES)
+                    .filter(|_| material.scatter(ray, hit, &mut rng).is_some())
+                    .count() as Decimal
+                    / SAMPLES as Decimal;
+
+                assert!(
+                    integral <= 1.01 && (integral - sampled).abs() < 0.01,
+                    "{material:?} at cos {cos_theta} has a pdf integrating to {integral}, but samples {sampled} of the time"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn principled_pdf_matches_histogram() {
+        //bins of equal solid angle, going down in z and around in the angle from the x axis
+        const Z_BINS: usize = 8;
+        const ANGLE_BINS: usize = 16;
+        ///points per side of the grid each bin's pdf is integrated over
+        const GRID: usize = 16;
+        let bin_z =
+            |bin: usize, offset: Decimal| 1.0 - 2.0 * (bin as Decimal + offset) / Z_BINS as Decimal;
+        let bin_angle = |bin: usize, offset: Decimal| {
+            TAU * (bin as Decimal + offset) / ANGLE_BINS as Decimal - PI
+        };
+        let solid_angle = 4.0 * PI / (Z_BINS * ANGLE_BINS) as Decimal;
+
+        let mut rng = thread_rng();
+        for material in principled_settings() {
+            for cos_theta in [0.7, -0.7] {
+                let (ray, hit) = hit(&material, direction(cos_theta));
+
+                let mut histogram = [[0.0; ANGLE_BINS]; Z_BINS];
+                for _ in 0..SAMPLES {
+                    if let Some((_, scattered, _)) = material.scatter(ray, hit, &mut rng) {
+                        let direction = scattered.direction().unit();
+                        let z_bin = ((1.0 - direction.z()) / 2.0 * Z_BINS as Decimal) as usize;
+                        let angle = direction.y().atan2(direction.x());
+                        let angle_bin = ((angle + PI) / TAU * ANGLE_BINS as Decimal) as usize;
+                        histogram[z_bin.min(Z_BINS - 1)][angle_bin.min(ANGLE_BINS - 1)] +=
+                            1.0 / SAMPLES as Decimal;
+                    }
+                }
+
+                for (z_bin, row) in histogram.iter().enumerate() {
+                    for (angle_bin, &found) in row.iter().enumerate() {
+                        let mut total = 0.0;
+                        for (i, j) in (0..GRID).flat_map(|i| (0..GRID).map(move |j| (i, j))) {
+                            let z = bin_z(z_bin, (i as Decimal + 0.5) / GRID as Decimal);
+                            let angle =
+                                bin_angle(angle_bin, (j as Decimal + 0.5) / GRID as Decimal);
+                            let radius = (1.0 - z * z).sqrt();
+                            let direction =
+                                Vec3::new(radius * angle.cos(), radius * angle.sin(), z);
+                            total += material
+                                .evaluate(ray, hit, direction, &mut rng)
+                                .map_or(0.0, |(_, pdf)| pdf);
+                        }
+                        let expected = total / (GRID * GRID) as Decimal * solid_angle;
+
+                        //four standard deviations of the count, and a bit for the integral being approximate
+                        let tolerance =
+                            4.0 * (expected / SAMPLES as Decimal).sqrt() + 0.03 * expected + 1e-4;
+                        assert!(
+                            (found - expected).abs() < tolerance,
+                            "{material:?} at cos {cos_theta} samples {found} of directions in bin ({z_bin}, {angle_bin}), but its pdf gives {expected}"
+                        );
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,126 @@
+//! Normal and bump mapping, which change the shading normal without changing the geometry
+
+use crate::primitives::{
+    collisions::HitRecord,
+    materials::{Lobe, Material},
+    textures::Texture,
+    Colour, Decimal, Ray, Vec3,
+};
+use rand::rngs::ThreadRng;
+
+///The shading normal from a tangent space normal map, where red is along `u`, green is along `v` and blue is out of the surface
+pub(super) fn normal_mapped(map: &dyn Texture, hit_record: &HitRecord) -> Vec3 {
+    let texel = map.filtered_value(
+        hit_record.u,
+        hit_record.v,
+        hit_record.point,
+        hit_record.footprint,
+    );
+    //0-1 in the texture is -1 to 1 in the frame
+    let local = 2.0 * texel - Colour::splat(1.0);
+    let (tangent, bitangent) = hit_record.tangent_frame();
+
+    let normal = local.x() * tangent + local.y() * bitangent + local.z() * hit_record.normal;
+    if normal.near_zero() {
+        hit_record.normal
+    } else {
+        normal.unit()
+    }
+}
+
+///The shading normal from a height map, as though the surface were pushed out along its normal by the height times `strength`
+pub(super) fn bump_mapped(height: &dyn Texture, strength: Decimal, hit_record: &HitRecord) -> Vec3 {
+    let (u, v, point) = (hit_record.u, hit_record.v, hit_record.point);
+    let footprint = hit_record.footprint;
+
+    //finite differences over about a pixel, or a small fixed step if we don't know how big a pixel is
+    let step = |along_x: Decimal, along_y: Decimal| {
+        let step = 0.5 * (along_x.abs() + along_y.abs());
+        if step > 0.0 {
+            step
+        } else {
+            5e-4
+        }
+    };
+    let du = step(footprint.dudx, footprint.dudy);
+    let dv = step(footprint.dvdx, footprint.dvdy);
+
+    let centre = height.scalar(u, v, point);
+    let along_u = (height.scalar(u + du, v, point + du * hit_record.dpdu) - centre) / du;
+    let along_v = (height.scalar(u, v + dv, point + dv * hit_record.dpdv) - centre) / dv;
+
+    //bumps go outwards, even when we hit the back face
+    let outward = if hit_record.front_face {
+        hit_record.normal
+    } else {
+        -hit_record.normal
+    };
+    let (tangent, bitangent) = hit_record.tangent_frame();
+    let (along_surface_u, along_surface_v) =
+        if hit_record.dpdu.near_zero() || hit_record.dpdv.near_zero() {
+            (tangent, bitangent)
+        } else {
+            (hit_record.dpdu, hit_record.dpdv)
+        };
+
+    let normal = (along_surface_u + strength * along_u * outward)
+        .cross(along_surface_v + strength * along_v * outward);
+    if normal.near_zero() {
+        return hit_record.normal;
+    }
+    let normal = normal.unit();
+    if normal.dot(hit_record.normal) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+///Bends `shading_normal` towards the viewer if we wouldn't be able to see it
+fn facing_viewer(shading_normal: Vec3, ray_in: Ray) -> Vec3 {
+    let outgoing = -ray_in.direction().unit();
+    let facing = outgoing.dot(shading_normal);
+    if facing < 1e-2 {
+        (shading_normal + (1e-2 - facing) * outgoing).unit()
+    } else {
+        shading_normal
+    }
+}
+
+///Whether `direction` is on the same side of the geometric and shading normals - if not, it would be leaking light through the surface
+fn same_side(hit_record: &HitRecord, direction: Vec3) -> bool {
+    (direction.dot(hit_record.geometric_normal) > 0.0) == (direction.dot(hit_record.normal) > 0.0)
+}
+
+///Scatters off `material` as if the surface had the normal `shading_normal`.
+///
+///Shading normals can face away from the camera, or send light through the surface, so they are bent towards the camera if needed, and rays which end up on different sides of the geometric and shading normals are thrown away.
+pub(super) fn scatter_with_normal(
+    material: &Material,
+    shading_normal: Vec3,
+    ray_in: Ray,
+    mut hit_record: HitRecord,
+    rng: &mut ThreadRng,
+) -> Option<(Colour, Ray, Lobe)> {
+    hit_record.normal = facing_viewer(shading_normal, ray_in);
+    let (weight, scattered, lobe) = material.scatter(ray_in, hit_record, rng)?;
+    same_side(&hit_record, scattered.direction()).then_some((weight, scattered, lobe))
+}
+
+///[`Material::evaluate_lobes`] with the same changes to the normal as [`scatter_with_normal`]
+pub(super) fn evaluate_with_normal(
+    material: &Material,
+    shading_normal: Vec3,
+    ray_in: Ray,
+    mut hit_record: HitRecord,
+    direction: Vec3,
+    rng: &mut ThreadRng,
+) -> Option<(Colour, Decimal, Colour)> {
+    hit_record.normal = facing_viewer(shading_normal, ray_in);
+    let (value, pdf, diffuse) = material.evaluate_lobes(ray_in, hit_record, direction, rng)?;
+    if same_side(&hit_record, direction) {
+        Some((value, pdf, diffuse))
+    } else {
+        Some((Colour::default(), pdf, Colour::default()))
+    }
+}
@@ -0,0 +1,254 @@
+//! A clear coat, like varnish or lacquer, layered over another material
+
+use super::{
+    microfacet::{fresnel_dielectric, Frame, Ggx},
+    Lobe, Material,
+};
+use crate::primitives::{collisions::HitRecord, textures::Texture, Colour, Decimal, Ray, Vec3};
+use rand::{rngs::ThreadRng, Rng};
+use std::sync::Arc;
+
+///How many times light can bounce between the base and the underside of the coat before we give up on it
+const MAX_BOUNCES: usize = 16;
+
+///Parameters for [`Material::Coated`], a dielectric layer over a `base` material.
+///
+///Light either reflects off the coat, or goes in and bounces between the base and the underside of the coat (losing some to the colour of the coat each time) until it gets back out. Everything lost to reflection on the way in is made up for by what comes back out, so nothing is created or thrown away between the layers.
+///
+///The coat is treated as smooth for light going through it, and is assumed to be on the outside of the object.
+#[derive(Debug, Clone)]
+pub struct ClearCoat {
+    pub base: Material,
+    pub ior: Decimal,
+    ///from 0-1, for the reflection off the top of the coat
+    pub roughness: Arc<dyn Texture>,
+    ///the fraction of light getting through the coat going straight down once - white is perfectly clear
+    pub colour: Arc<dyn Texture>,
+}
+
+impl ClearCoat {
+    ///A perfectly clear, glossy coat
+    #[must_use]
+    pub fn new(base: Material, ior: Decimal) -> Self {
+        Self {
+            base,
+            ior,
+            roughness: Colour::splat(0.0).into(),
+            colour: Colour::splat(1.0).into(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_roughness(mut self, roughness: Arc<dyn Texture>) -> Self {
+        self.roughness = roughness;
+        self
+    }
+    #[must_use]
+    pub fn with_colour(mut self, colour: Arc<dyn Texture>) -> Self {
+        self.colour = colour;
+        self
+    }
+}
+
+///A [`ClearCoat`] with the textures looked up at a hit, in the local [`Frame`] of the surface
+struct Layers<'a> {
+    coat: &'a ClearCoat,
+    hit_record: HitRecord<'a>,
+    frame: Frame,
+    ggx: Ggx,
+    colour: Colour,
+}
+
+impl<'a> Layers<'a> {
+    fn new(coat: &'a ClearCoat, hit_record: HitRecord<'a>) -> Self {
+        let (u, v, point) = (hit_record.u, hit_record.v, hit_record.point);
+        Self {
+            coat,
+            hit_record,
+            frame: Frame::new(hit_record.normal),
+            ggx: Ggx::new(coat.roughness.scalar(u, v, point).clamp(0.0, 1.0)),
+            colour: coat
+                .colour
+                .filtered_value(u, v, point, hit_record.footprint),
+        }
+    }
+
+    ///How much light gets through the coat along `direction`, which is longer the more slanted it is
+    fn absorption(&self, direction: Vec3) -> Colour {
+        let length = 1.0 / direction.z().abs().max(1e-4);
+        Colour::new(
+            self.colour.x().max(0.0).powf(length),
+            self.colour.y().max(0.0).powf(length),
+            self.colour.z().max(0.0).powf(length),
+        )
+    }
+
+    ///Going down into the coat from `outgoing` above it
+    fn refract_in(&self, outgoing: Vec3) -> Vec3 {
+        (-outgoing).refract(Vec3::new(0.0, 0.0, 1.0), 1.0 / self.coat.ior)
+    }
+
+    ///The base material hit by light going along `direction` inside the coat
+    fn base_ray(&self, direction: Vec3) -> Ray {
+        Ray::new(self.hit_record.point, self.frame.to_world(direction))
+    }
+
+    ///Scatters off the base, giving the weight, the direction it goes back up, and the [`Lobe`] it scattered with
+    fn scatter_base(&self, direction: Vec3, rng: &mut ThreadRng) -> Option<(Colour, Vec3, Lobe)> {
+        let (weight, scattered, lobe) =
+            self.coat
+                .base
+                .scatter(self.base_ray(direction), self.hit_record, rng)?;
+        let up = self.frame.to_local(scattered.direction().unit());
+        (up.z() > 0.0).then_some((weight, up, lobe))
+    }
+}
+
+impl ClearCoat {
+    ///Samples a direction, returning the BSDF times the cosine over the pdf, the scattered ray, and the [`Lobe`] - light which goes through the coat takes the lobe the base scattered it with first
+    pub(super) fn scatter(
+        &self,
+        direction_in: Vec3,
+        hit_record: HitRecord,
+        rng: &mut ThreadRng,
+    ) -> Option<(Colour, Ray, Lobe)> {
+        let layers = Layers::new(self, hit_record);
+        let outgoing = layers.frame.to_local(-direction_in.unit());
+        if outgoing.z() <= 0.0 {
+            return None;
+        }
+
+        //reflect off the top in proportion to the fresnel term, so it cancels out of the weight
+        let microfacet = layers
+            .ggx
+            .sample_visible_normal(outgoing, (rng.gen(), rng.gen()));
+        if rng.gen::<Decimal>() < fresnel_dielectric(outgoing.dot(microfacet), self.ior) {
+            let incoming = (-outgoing).reflect(microfacet);
+            if incoming.z() <= 0.0 {
+                return None;
+            }
+            let weight =
+                layers.ggx.masking_shadowing(outgoing, incoming) / layers.ggx.masking(outgoing);
+            return Some((
+                Colour::splat(weight),
+                Ray::new(hit_record.point, layers.frame.to_world(incoming)),
+                Lobe::Specular,
+            ));
+        }
+
+        let mut direction = layers.refract_in(outgoing);
+        let mut weight = layers.absorption(direction);
+        let mut first = None;
+        for _ in 0..MAX_BOUNCES {
+            let (base_weight, scattered, lobe) =
+                self.base
+                    .scatter(layers.base_ray(direction), hit_record, rng)?;
+            let lobe = *first.get_or_insert(lobe);
+            let up = layers.frame.to_local(scattered.direction().unit());
+            if up.z() <= 0.0 {
+                //the base let it through, so the coat makes no difference
+                return Some((weight * base_weight, scattered, lobe));
+            }
+            weight *= base_weight * layers.absorption(up);
+
+            //out through the top, or reflected back down again by the underside of the coat
+            if rng.gen::<Decimal>() >= fresnel_dielectric(up.z(), 1.0 / self.ior) {
+                let incoming = up.refract(Vec3::new(0.0, 0.0, -1.0), self.ior);
+                return Some((
+                    weight,
+                    Ray::new(hit_record.point, layers.frame.to_world(incoming)),
+                    lobe,
+                ));
+            }
+            direction = Vec3::new(up.x(), up.y(), -up.z());
+            weight *= layers.absorption(direction);
+        }
+
+        None
+    }
+
+    ///An estimate of the BSDF times the cosine for light arriving from `direction`, a pdf to weight [`ClearCoat::scatter`] with, and the part of the estimate from diffuse lobes.
+    ///
+    ///Light through the coat is found by following it around inside the same way as [`ClearCoat::scatter`], and connecting each bounce off the base to `direction`, so the estimate is right on average. Light which bounces off the base more than once is diffuse if the base scattered it diffusely first, like in [`ClearCoat::scatter`].
+    ///
+    ///Returns [`None`] for directions under the surface, or if the base can't be evaluated.
+    pub(super) fn evaluate(
+        &self,
+        direction_in: Vec3,
+        hit_record: HitRecord,
+        direction: Vec3,
+        rng: &mut ThreadRng,
+    ) -> Option<(Colour, Decimal, Colour)> {
+        let layers = Layers::new(self, hit_record);
+        let (frame, ggx) = (layers.frame, layers.ggx);
+        let outgoing = frame.to_local(-direction_in.unit());
+        let incoming = frame.to_local(direction.unit());
+        if outgoing.z() <= 0.0 {
+            return Some((Colour::default(), 0.0, Colour::default()));
+        }
+        if incoming.z() <= 0.0 {
+            return None;
+        }
+
+        let inside_out = layers.refract_in(outgoing);
+        let inside_in = -layers.refract_in(incoming);
+        let (first_value, base_pdf, first_diffuse) = self.base.evaluate_lobes(
+            layers.base_ray(inside_out),
+            hit_record,
+            frame.to_world(inside_in),
+            rng,
+        )?;
+
+        //the reflection off the top
+        let microfacet = (outgoing + incoming).unit();
+        let fresnel = fresnel_dielectric(outgoing.dot(microfacet), self.ior);
+        let reflected =
+            fresnel * ggx.distribution(microfacet) * ggx.masking_shadowing(outgoing, incoming)
+                / (4.0 * outgoing.z());
+        let reflected_pdf = fresnel * ggx.visible_normal_pdf(outgoing, microfacet)
+            / (4.0 * outgoing.dot(microfacet));
+
+        //light spreads out leaving the coat, so the change in solid angle is 1/η² times the change in cosines
+        let jacobian = incoming.z() / (self.ior * self.ior * inside_in.z());
+        let leaving = layers.absorption(inside_in)
+            * ((1.0 - fresnel_dielectric(incoming.z(), self.ior)) * jacobian);
+
+        let entering = ggx.sample_visible_normal(outgoing, (rng.gen(), rng.gen()));
+        let mut weight = layers.absorption(inside_out)
+            * (1.0 - fresnel_dielectric(outgoing.dot(entering), self.ior));
+        let mut value = Colour::splat(reflected) + weight * first_value * leaving;
+        let mut diffuse = weight * first_diffuse * leaving;
+        let mut direction = inside_out;
+        let mut first = None;
+        for _ in 1..MAX_BOUNCES {
+            let Some((base_weight, up, lobe)) = layers.scatter_base(direction, rng) else {
+                break;
+            };
+            let lobe = *first.get_or_insert(lobe);
+            weight *= base_weight * layers.absorption(up);
+            if rng.gen::<Decimal>() >= fresnel_dielectric(up.z(), 1.0 / self.ior) {
+                break;
+            }
+            direction = Vec3::new(up.x(), up.y(), -up.z());
+            weight *= layers.absorption(direction);
+
+            let Some((base_value, _)) = self.base.evaluate(
+                layers.base_ray(direction),
+                hit_record,
+                frame.to_world(inside_in),
+                rng,
+            ) else {
+                break;
+            };
+            value += weight * base_value * leaving;
+            if lobe == Lobe::Diffuse {
+                diffuse += weight * base_value * leaving;
+            }
+        }
+
+        //only the first bounce off the base, which is close enough for weighting
+        let pdf = reflected_pdf
+            + (1.0 - fresnel_dielectric(outgoing.z(), self.ior)) * base_pdf * jacobian;
+        Some((value, pdf, diffuse))
+    }
+}
@@ -0,0 +1,65 @@
+use crate::primitives::Decimal;
+
+///The wavelength of the helium d line in nanometres, which glass is usually specified at - this is what's used when we aren't rendering spectrally
+pub const D_LINE: Decimal = 587.6;
+
+///An index of refraction which can change with the wavelength, which splits white light into rainbows.
+///
+///Wavelengths in the formulae are in micrometres.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Ior {
+    ///the same at every wavelength
+    Constant(Decimal),
+    ///a, b - Cauchy's equation, `n = a + b / λ²`
+    Cauchy(Decimal, Decimal),
+    ///b, c - the Sellmeier equation, `n² = 1 + Σ bᵢλ² / (λ² - cᵢ)`
+    Sellmeier([Decimal; 3], [Decimal; 3]),
+}
+
+impl Ior {
+    ///Schott N-BK7, the most common optical glass
+    pub const BK7: Self = Self::Sellmeier(
+        [1.039_612_12, 0.231_792_344, 1.010_469_45],
+        [0.006_000_698_67, 0.020_017_914_4, 103.560_653],
+    );
+    ///Schott SF11, a dense flint glass which disperses a lot
+    pub const SF11: Self = Self::Sellmeier(
+        [1.737_596_95, 0.313_747_346, 1.898_781_01],
+        [0.013_188_707, 0.062_306_814_2, 155.236_29],
+    );
+    pub const FUSED_SILICA: Self = Self::Sellmeier(
+        [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        [0.004_679_148, 0.013_512_063, 97.934_002_5],
+    );
+    pub const DIAMOND: Self = Self::Sellmeier([4.3356, 0.3306, 0.0], [0.011_236, 0.030_625, 0.0]);
+
+    ///At `wavelength` in nanometres
+    #[must_use]
+    pub fn at(&self, wavelength: Decimal) -> Decimal {
+        let micrometres = wavelength / 1000.0;
+        let squared = micrometres * micrometres;
+
+        match *self {
+            Self::Constant(ior) => ior,
+            Self::Cauchy(a, b) => a + b / squared,
+            Self::Sellmeier(b, c) => b
+                .iter()
+                .zip(c)
+                .map(|(b, c)| b * squared / (squared - c))
+                .fold(1.0, |total, term| total + term)
+                .sqrt(),
+        }
+    }
+
+    ///Whether the index of refraction changes with the wavelength
+    #[must_use]
+    pub const fn is_dispersive(&self) -> bool {
+        !matches!(self, Self::Constant(_))
+    }
+}
+
+impl From<Decimal> for Ior {
+    fn from(ior: Decimal) -> Self {
+        Self::Constant(ior)
+    }
+}
@@ -0,0 +1,414 @@
+//! The GGX (Trowbridge-Reitz) microfacet model, and the Fresnel equations for the facets
+
+use crate::primitives::{
+    decimal_consts::{PI, TAU},
+    Colour, Decimal, Vec3,
+};
+
+///An orthonormal basis around a surface normal, so we can work with directions where the normal is +z
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frame {
+    tangent: Vec3,
+    bitangent: Vec3,
+    normal: Vec3,
+}
+
+impl Frame {
+    ///`normal` must be a unit vector
+    #[must_use]
+    pub fn new(normal: Vec3) -> Self {
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        Self {
+            tangent,
+            bitangent,
+            normal,
+        }
+    }
+
+    #[must_use]
+    pub fn to_local(&self, world: Vec3) -> Vec3 {
+        Vec3::new(
+            world.dot(self.tangent),
+            world.dot(self.bitangent),
+            world.dot(self.normal),
+        )
+    }
+    #[must_use]
+    pub fn to_world(&self, local: Vec3) -> Vec3 {
+        local.x() * self.tangent + local.y() * self.bitangent + local.z() * self.normal
+    }
+}
+
+///A complex index of refraction for each colour channel - `eta` is the real part, and `k` is how strongly the metal absorbs light
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ComplexIor {
+    pub eta: Colour,
+    pub k: Colour,
+}
+
+impl ComplexIor {
+    pub const GOLD: Self = Self::new(
+        Colour::new(0.143, 0.374, 1.442),
+        Colour::new(3.983, 2.385, 1.603),
+    );
+    pub const SILVER: Self = Self::new(
+        Colour::new(0.155, 0.117, 0.138),
+        Colour::new(4.828, 3.122, 2.147),
+    );
+    pub const COPPER: Self = Self::new(
+        Colour::new(0.200, 0.924, 1.102),
+        Colour::new(3.912, 2.452, 2.142),
+    );
+    pub const ALUMINIUM: Self = Self::new(
+        Colour::new(1.657, 0.880, 0.521),
+        Colour::new(9.224, 6.270, 4.837),
+    );
+    pub const IRON: Self = Self::new(
+        Colour::new(2.912, 2.950, 2.585),
+        Colour::new(3.089, 2.932, 2.767),
+    );
+
+    #[must_use]
+    pub const fn new(eta: Colour, k: Colour) -> Self {
+        Self { eta, k }
+    }
+
+    ///The fraction of light reflected by a conductor, for each channel
+    #[must_use]
+    pub fn fresnel(&self, cos_theta: Decimal) -> Colour {
+        let channel = |eta: Decimal, k: Decimal| {
+            let cos_squared = cos_theta * cos_theta;
+            let sin_squared = 1.0 - cos_squared;
+            let (eta_squared, k_squared) = (eta * eta, k * k);
+
+            let t0 = eta_squared - k_squared - sin_squared;
+            let a_squared_plus_b_squared = (t0 * t0 + 4.0 * eta_squared * k_squared).sqrt();
+            let a = (0.5 * (a_squared_plus_b_squared + t0)).max(0.0).sqrt();
+
+            let t1 = a_squared_plus_b_squared + cos_squared;
+            let t2 = 2.0 * cos_theta * a;
+            let perpendicular = (t1 - t2) / (t1 + t2);
+
+            let t3 = cos_squared * a_squared_plus_b_squared + sin_squared.powi(2);
+            let t4 = t2 * sin_squared;
+            let parallel = perpendicular * (t3 - t4) / (t3 + t4);
+
+            0.5 * (parallel + perpendicular)
+        };
+
+        Colour::new(
+            channel(self.eta.x(), self.k.x()),
+            channel(self.eta.y(), self.k.y()),
+            channel(self.eta.z(), self.k.z()),
+        )
+    }
+}
+
+///The fraction of light reflected at a boundary between dielectrics, where `eta` is the index of refraction on the far side divided by the one on the near side.
+///
+///Total internal reflection gives 1.
+#[must_use]
+pub fn fresnel_dielectric(cos_theta: Decimal, eta: Decimal) -> Decimal {
+    let cos_theta = cos_theta.clamp(0.0, 1.0);
+    let sin_transmitted_squared = (1.0 - cos_theta * cos_theta) / (eta * eta);
+    if sin_transmitted_squared >= 1.0 {
+        return 1.0;
+    }
+    let cos_transmitted = (1.0 - sin_transmitted_squared).sqrt();
+
+    let perpendicular = (cos_theta - eta * cos_transmitted) / (cos_theta + eta * cos_transmitted);
+    let parallel = (eta * cos_theta - cos_transmitted) / (eta * cos_theta + cos_transmitted);
+    0.5 * (perpendicular * perpendicular + parallel * parallel)
+}
+
+///The GGX normal distribution, with all directions in the local [`Frame`] where the macro-surface normal is +z
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ggx {
+    alpha: Decimal,
+}
+
+impl Ggx {
+    ///`roughness` is from 0-1, and is squared to get the width of the distribution, so it looks more linear.
+    ///
+    ///It is kept a bit above 0, as perfectly smooth surfaces break the maths.
+    #[must_use]
+    pub fn new(roughness: Decimal) -> Self {
+        Self {
+            alpha: (roughness * roughness).clamp(1e-4, 1.0),
+        }
+    }
+
+    ///The density of microfacets facing along `normal`
+    #[must_use]
+    pub fn distribution(self, normal: Vec3) -> Decimal {
+        let cos_squared = normal.z() * normal.z();
+        if normal.z() <= 0.0 {
+            return 0.0;
+        }
+        let alpha_squared = self.alpha * self.alpha;
+        let denominator = cos_squared * (alpha_squared - 1.0) + 1.0;
+        alpha_squared / (PI * denominator * denominator)
+    }
+
+    ///The probability density of [`Ggx::sample_visible_normal`] giving `normal`
+    #[must_use]
+    pub fn visible_normal_pdf(self, outgoing: Vec3, normal: Vec3) -> Decimal {
+        self.masking(outgoing) * outgoing.dot(normal).max(0.0) * self.distribution(normal)
+            / outgoing.z()
+    }
+
+    ///Smith's auxiliary function - roughly how much of the surface is hidden by other microfacets when looking from `direction`
+    fn lambda(self, direction: Vec3) -> Decimal {
+        let cos_squared = direction.z() * direction.z();
+        if cos_squared == 0.0 {
+            return Decimal::INFINITY;
+        }
+        let tan_squared = (1.0 - cos_squared).max(0.0) / cos_squared;
+        0.5 * ((1.0 + self.alpha * self.alpha * tan_squared).sqrt() - 1.0)
+    }
+
+    ///The fraction of microfacets facing `direction` that are visible from it
+    #[must_use]
+    pub fn masking(self, direction: Vec3) -> Decimal {
+        1.0 / (1.0 + self.lambda(direction))
+    }
+
+    ///The fraction of microfacets visible from both directions, using the height-correlated Smith form
+    #[must_use]
+    pub fn masking_shadowing(self, outgoing: Vec3, incoming: Vec3) -> Decimal {
+        1.0 / (1.0 + self.lambda(outgoing) + self.lambda(incoming))
+    }
+
+    ///Samples a microfacet normal in proportion to how much of it is visible from `outgoing`, which must be above the surface.
+    ///
+    ///From Heitz 2018, "Sampling the GGX Distribution of Visible Normals" - the weight of the sample is `G2 / G1(outgoing)` times the Fresnel term
+    #[must_use]
+    pub fn sample_visible_normal(self, outgoing: Vec3, sample: (Decimal, Decimal)) -> Vec3 {
+        //stretch the view direction so the distribution becomes a hemisphere
+        let stretched = Vec3::new(
+            self.alpha * outgoing.x(),
+            self.alpha * outgoing.y(),
+            outgoing.z(),
+        )
+        .unit();
+
+        let length_squared = stretched.x() * stretched.x() + stretched.y() * stretched.y();
+        let tangent = if length_squared > 0.0 {
+            Vec3::new(-stretched.y(), stretched.x(), 0.0) / length_squared.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let bitangent = stretched.cross(tangent);
+
+        //sample the projected area of the hemisphere, which is a disk with the back half squashed
+        let radius = sample.0.sqrt();
+        let (sin, cos) = (TAU * sample.1).sin_cos();
+        let x = radius * cos;
+        let squash = 0.5 * (1.0 + stretched.z());
+        let y = (1.0 - squash) * (1.0 - x * x).sqrt() + squash * radius * sin;
+
+        let hemisphere =
+            x * tangent + y * bitangent + (1.0 - x * x - y * y).max(0.0).sqrt() * stretched;
+
+        //unstretch back to the microfacet normal
+        Vec3::new(
+            self.alpha * hemisphere.x(),
+            self.alpha * hemisphere.y(),
+            hemisphere.z().max(0.0),
+        )
+        .unit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fresnel_dielectric, ComplexIor, Ggx};
+    use crate::primitives::{decimal_consts::TAU, Decimal, Vec3};
+    use rand::{rngs::ThreadRng, thread_rng, Rng};
+
+    const SAMPLES: usize = 200_000;
+    const ROUGHNESSES: [Decimal; 5] = [0.1, 0.3, 0.5, 0.7, 1.0];
+
+    ///A direction `cos_theta` away from the normal
+    fn direction(cos_theta: Decimal) -> Vec3 {
+        Vec3::new((1.0 - cos_theta * cos_theta).sqrt(), 0.0, cos_theta)
+    }
+
+    ///A microfacet normal and its probability density, independently of the visible normal sampling being tested.
+    ///
+    ///Half are in proportion to [`Ggx::distribution`] times the cosine, and half are uniform over the hemisphere, so integrals with the distribution in don't blow up near the peak or the horizon.
+    fn sample_normal(ggx: Ggx, rng: &mut ThreadRng) -> (Vec3, Decimal) {
+        let cos_theta = if rng.gen::<bool>() {
+            let sample: Decimal = rng.gen();
+            1.0 / (1.0 + ggx.alpha * ggx.alpha * sample / (1.0 - sample)).sqrt()
+        } else {
+            rng.gen()
+        };
+        let (sin, cos) = (TAU * rng.gen::<Decimal>()).sin_cos();
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let normal = Vec3::new(sin_theta * cos, sin_theta * sin, cos_theta);
+
+        let pdf = 0.5 * ggx.distribution(normal) * cos_theta + 0.5 / TAU;
+        (normal, pdf)
+    }
+
+    ///The fraction of light a conductor which reflects everything (F = 1) reflects when lit from `outgoing`, estimated by sampling visible normals
+    fn furnace_visible_normals(ggx: Ggx, outgoing: Vec3, rng: &mut ThreadRng) -> Decimal {
+        let total: Decimal = (0..SAMPLES)
+            .map(|_| {
+                let normal = ggx.sample_visible_normal(outgoing, (rng.gen(), rng.gen()));
+                let incoming = (-outgoing).reflect(normal);
+                if incoming.z() <= 0.0 {
+                    0.0
+                } else {
+                    ggx.masking_shadowing(outgoing, incoming) / ggx.masking(outgoing)
+                }
+            })
+            .sum();
+        total / SAMPLES as Decimal
+    }
+
+    ///Like [`furnace_visible_normals`], but integrating the BRDF times the cosine with normals from [`sample_normal`]
+    fn furnace_distribution(ggx: Ggx, outgoing: Vec3, rng: &mut ThreadRng) -> Decimal {
+        let total: Decimal = (0..SAMPLES)
+            .map(|_| {
+                let (normal, pdf) = sample_normal(ggx, rng);
+                let incoming = (-outgoing).reflect(normal);
+                let cos_outgoing = outgoing.dot(normal);
+                if incoming.z() <= 0.0 || cos_outgoing <= 0.0 {
+                    0.0
+                } else {
+                    //the pdf of the reflected direction is the normal's over 4 (o·m), and the BRDF times the cosine is D G2 / (4 cos θo)
+                    ggx.distribution(normal)
+                        * ggx.masking_shadowing(outgoing, incoming)
+                        * cos_outgoing
+                        / (outgoing.z() * pdf)
+                }
+            })
+            .sum();
+        total / SAMPLES as Decimal
+    }
+
+    #[test]
+    fn conductor_white_furnace() {
+        let mut rng = thread_rng();
+
+        //single scattering loses the light bouncing between microfacets, but that's tiny unless it's rough or seen at a grazing angle
+        for roughness in [0.05, 0.1, 0.2] {
+            let ggx = Ggx::new(roughness);
+            for cos_theta in [0.9, 0.5, 0.2] {
+                let reflected = furnace_visible_normals(ggx, direction(cos_theta), &mut rng);
+                assert!(
+                    reflected <= 1.0 && reflected > 0.97,
+                    "roughness {roughness} at cos {cos_theta} reflects {reflected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn visible_normal_sampling() {
+        let mut rng = thread_rng();
+
+        for roughness in ROUGHNESSES {
+            let ggx = Ggx::new(roughness);
+            for cos_theta in [0.9, 0.5, 0.2] {
+                let outgoing = direction(cos_theta);
+                let sampled = furnace_visible_normals(ggx, outgoing, &mut rng);
+                let integrated = furnace_distribution(ggx, outgoing, &mut rng);
+
+                assert!(
+                    sampled <= 1.0 && (sampled - integrated).abs() < 0.02,
+                    "roughness {roughness} at cos {cos_theta} reflects {sampled} when sampling visible normals, but {integrated} when integrating"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn projected_area() {
+        //the microfacets seen from any direction cover the same area as the surface they're on: ∫ D(m) max(0, o·m) dm = cos θ / G1(o)
+        let mut rng = thread_rng();
+
+        for roughness in ROUGHNESSES {
+            let ggx = Ggx::new(roughness);
+            for cos_theta in [1.0, 0.7, 0.3] {
+                let outgoing = direction(cos_theta);
+                let total: Decimal = (0..SAMPLES)
+                    .map(|_| {
+                        let (normal, pdf) = sample_normal(ggx, &mut rng);
+                        ggx.distribution(normal) * outgoing.dot(normal).max(0.0) / pdf
+                    })
+                    .sum();
+                let projected = total / SAMPLES as Decimal;
+                let expected = cos_theta / ggx.masking(outgoing);
+
+                assert!(
+                    (projected - expected).abs() < 0.02 * expected,
+                    "roughness {roughness} at cos {cos_theta} projects to {projected}, not {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn visible_normal_pdf_normalised() {
+        let mut rng = thread_rng();
+
+        for roughness in ROUGHNESSES {
+            let ggx = Ggx::new(roughness);
+            let outgoing = direction(0.6);
+            let total: Decimal = (0..SAMPLES)
+                .map(|_| {
+                    let (normal, pdf) = sample_normal(ggx, &mut rng);
+                    ggx.visible_normal_pdf(outgoing, normal) / pdf
+                })
+                .sum();
+            let integral = total / SAMPLES as Decimal;
+
+            assert!(
+                (integral - 1.0).abs() < 0.02,
+                "roughness {roughness} integrates to {integral}"
+            );
+        }
+    }
+
+    #[test]
+    fn dielectric_fresnel() {
+        let near = |a: Decimal, b: Decimal| (a - b).abs() < 1e-6;
+
+        //((n - 1) / (n + 1))^2 at normal incidence, from either side
+        assert!(near(fresnel_dielectric(1.0, 1.5), 0.04));
+        assert!(near(fresnel_dielectric(1.0, 1.0 / 1.5), 0.04));
+        assert!(near(
+            fresnel_dielectric(1.0, 1.33),
+            (0.33 / 2.33_f64).powi(2)
+        ));
+        //at Brewster's angle, only the perpendicular polarisation is reflected
+        let brewster = 1.5_f64.atan().cos();
+        assert!(near(fresnel_dielectric(brewster, 1.5), 0.5 * 0.147_928));
+        //total internal reflection past the critical angle, which is at sin θ = 1/1.5 going out of glass
+        let critical = (1.0 - 1.0 / (1.5 * 1.5) as Decimal).sqrt();
+        assert!(fresnel_dielectric(critical + 0.01, 1.0 / 1.5) < 1.0);
+        for cos_theta in [critical - 0.01, 0.5, 0.1, 0.0] {
+            assert!(near(fresnel_dielectric(cos_theta, 1.0 / 1.5), 1.0));
+        }
+        //everything is reflected at grazing angles
+        assert!(near(fresnel_dielectric(0.0, 1.5), 1.0));
+        //nothing is reflected without a boundary
+        assert!(near(fresnel_dielectric(0.5, 1.0), 0.0));
+    }
+
+    #[test]
+    fn conductor_fresnel() {
+        //((n - 1)^2 + k^2) / ((n + 1)^2 + k^2) at normal incidence
+        let ior = ComplexIor::GOLD;
+        let reflected = ior.fresnel(1.0);
+        for channel in 0..3 {
+            let (eta, k) = (ior.eta[channel], ior.k[channel]);
+            let expected = ((eta - 1.0).powi(2) + k * k) / ((eta + 1.0).powi(2) + k * k);
+            assert!((reflected[channel] - expected).abs() < 1e-6);
+        }
+        assert!((ior.fresnel(0.0).x() - 1.0).abs() < 1e-6);
+    }
+}
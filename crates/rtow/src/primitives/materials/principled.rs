@@ -0,0 +1,365 @@
+//! A Disney-style principled uber-material, with the parameters artists are used to
+
+use crate::primitives::{
+    collisions::HitRecord,
+    decimal_consts::{PI, TAU},
+    materials::{
+        microfacet::{fresnel_dielectric, Frame, Ggx},
+        Lobe,
+    },
+    textures::Texture,
+    Colour, Decimal, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+use std::sync::Arc;
+
+///Parameters for [`Material::Principled`](super::Material::Principled), based on the Disney BSDF.
+///
+///Everything other than the colours is from 0-1. Use `..Default::default()` to only set the ones you need.
+#[derive(Debug, Clone)]
+pub struct Principled {
+    pub base_colour: Arc<dyn Texture>,
+    ///0 for dielectrics like plastic, 1 for metals - in between is only really useful for blending
+    pub metallic: Arc<dyn Texture>,
+    pub roughness: Arc<dyn Texture>,
+    ///strength of the dielectric specular reflection - 0.5 is 4%, which is right for most materials
+    pub specular: Decimal,
+    ///how much the dielectric specular reflection takes on the base colour
+    pub specular_tint: Decimal,
+    ///soft retro-reflection at grazing angles, for cloth
+    pub sheen: Decimal,
+    ///how much the sheen takes on the base colour
+    pub sheen_tint: Decimal,
+    ///strength of a second, clear specular layer on top, like varnish or car paint
+    pub clearcoat: Decimal,
+    pub clearcoat_roughness: Decimal,
+    ///how much of the non-metallic part is glass rather than diffuse
+    pub transmission: Decimal,
+    ///index of refraction for transmission
+    pub ior: Decimal,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self {
+            base_colour: Colour::splat(0.8).into(),
+            metallic: Colour::splat(0.0).into(),
+            roughness: Colour::splat(0.5).into(),
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            transmission: 0.0,
+            ior: 1.5,
+        }
+    }
+}
+
+fn schlick(f0: Colour, cos_theta: Decimal) -> Colour {
+    f0 + (Colour::splat(1.0) - f0) * (1.0 - cos_theta).clamp(0.0, 1.0).powi(5)
+}
+
+fn luminance(colour: Colour) -> Decimal {
+    0.2126 * colour.x() + 0.7152 * colour.y() + 0.0722 * colour.z()
+}
+
+fn lerp(a: Colour, b: Colour, t: Decimal) -> Colour {
+    a * (1.0 - t) + b * t
+}
+
+///A [`Principled`] with the textures looked up at a hit, in the local [`Frame`] of the surface
+pub(crate) struct Lobes<'a> {
+    parameters: &'a Principled,
+    base_colour: Colour,
+    metallic: Decimal,
+    roughness: Decimal,
+    specular: Ggx,
+    clearcoat: Ggx,
+    ///index of refraction on the far side over the near side
+    eta: Decimal,
+    ///how much of each lobe there is
+    diffuse_weight: Decimal,
+    specular_weight: Decimal,
+    clearcoat_weight: Decimal,
+    glass_weight: Decimal,
+}
+
+impl<'a> Lobes<'a> {
+    pub(crate) fn new(parameters: &'a Principled, hit: &HitRecord) -> Self {
+        let (u, v, point) = (hit.u, hit.v, hit.point);
+        let base_colour = parameters
+            .base_colour
+            .filtered_value(u, v, point, hit.footprint);
+        let metallic = parameters.metallic.scalar(u, v, point).clamp(0.0, 1.0);
+        let roughness = parameters.roughness.scalar(u, v, point).clamp(0.0, 1.0);
+        let transmission = parameters.transmission.clamp(0.0, 1.0);
+
+        Self {
+            parameters,
+            base_colour,
+            metallic,
+            roughness,
+            specular: Ggx::new(roughness),
+            clearcoat: Ggx::new(parameters.clearcoat_roughness),
+            eta: if hit.front_face {
+                parameters.ior
+            } else {
+                1.0 / parameters.ior
+            },
+            diffuse_weight: (1.0 - metallic) * (1.0 - transmission),
+            specular_weight: 1.0 - (1.0 - metallic) * transmission,
+            clearcoat_weight: 0.25 * parameters.clearcoat,
+            glass_weight: (1.0 - metallic) * transmission,
+        }
+    }
+
+    ///Base colour with the brightness taken out, for tints
+    fn tint(&self) -> Colour {
+        let luminance = luminance(self.base_colour);
+        if luminance > 0.0 {
+            self.base_colour / luminance
+        } else {
+            Colour::splat(1.0)
+        }
+    }
+
+    ///Reflectance at normal incidence for the specular lobe
+    fn specular_f0(&self) -> Colour {
+        let dielectric = 0.08
+            * self.parameters.specular
+            * lerp(
+                Colour::splat(1.0),
+                self.tint(),
+                self.parameters.specular_tint,
+            );
+        lerp(dielectric, self.base_colour, self.metallic)
+    }
+
+    ///The base colour, with the glass lobe taken as white
+    pub(crate) fn albedo(&self) -> Colour {
+        self.base_colour * (1.0 - self.glass_weight) + Colour::splat(self.glass_weight)
+    }
+
+    ///The probabilities of sampling the diffuse, specular, clearcoat and glass lobes
+    fn selection(&self) -> [Decimal; 4] {
+        let weights = [
+            self.diffuse_weight * luminance(self.base_colour).max(0.01),
+            self.specular_weight * luminance(self.specular_f0()).max(0.01),
+            self.clearcoat_weight,
+            self.glass_weight,
+        ];
+        let total: Decimal = weights.iter().sum();
+        weights.map(|weight| weight / total)
+    }
+
+    ///The BSDF, for local directions with `outgoing` above the surface
+    pub(crate) fn evaluate(&self, outgoing: Vec3, incoming: Vec3) -> Colour {
+        self.evaluate_diffuse(outgoing, incoming) + self.evaluate_specular(outgoing, incoming)
+    }
+
+    ///The part of the BSDF from the diffuse and sheen lobes
+    fn evaluate_diffuse(&self, outgoing: Vec3, incoming: Vec3) -> Colour {
+        if incoming.z() < 0.0 || self.diffuse_weight == 0.0 {
+            return Colour::default();
+        }
+
+        let cos_half = incoming.dot((outgoing + incoming).unit());
+        //Burley's diffuse, which gets brighter at grazing angles on rough surfaces
+        let retro = 0.5 + 2.0 * self.roughness * cos_half * cos_half;
+        let fresnel = |cos: Decimal| 1.0 + (retro - 1.0) * (1.0 - cos).powi(5);
+        let diffuse = self.base_colour / PI * fresnel(incoming.z()) * fresnel(outgoing.z());
+
+        let sheen_colour = lerp(Colour::splat(1.0), self.tint(), self.parameters.sheen_tint);
+        let sheen = self.parameters.sheen * sheen_colour * (1.0 - cos_half).powi(5);
+
+        self.diffuse_weight * (diffuse + sheen)
+    }
+
+    ///The part of the BSDF from the specular, clearcoat and glass lobes
+    fn evaluate_specular(&self, outgoing: Vec3, incoming: Vec3) -> Colour {
+        if incoming.z() < 0.0 {
+            return self.evaluate_transmission(outgoing, incoming);
+        }
+
+        let half = (outgoing + incoming).unit();
+        let (cos_out, cos_in, cos_half) = (outgoing.z(), incoming.z(), incoming.dot(half));
+        let mut total = Colour::default();
+
+        let microfacet = |ggx: Ggx| {
+            ggx.distribution(half) * ggx.masking_shadowing(outgoing, incoming)
+                / (4.0 * cos_out * cos_in)
+        };
+        total += self.specular_weight
+            * schlick(self.specular_f0(), cos_half)
+            * microfacet(self.specular);
+        total += self.clearcoat_weight
+            * schlick(Colour::splat(0.04), cos_half)
+            * microfacet(self.clearcoat);
+        //only the light going through the glass is tinted
+        total += Colour::splat(
+            self.glass_weight
+                * fresnel_dielectric(outgoing.dot(half), self.eta)
+                * microfacet(self.specular),
+        );
+
+        total
+    }
+
+    ///The half vector for refraction, pointing to the `outgoing` side
+    fn refraction_half(&self, outgoing: Vec3, incoming: Vec3) -> Option<Vec3> {
+        let half = -(outgoing + self.eta * incoming);
+        if half.near_zero() {
+            return None;
+        }
+        let half = half.unit();
+        let half = if half.z() < 0.0 { -half } else { half };
+        //the microfacet must face both directions the right way
+        (outgoing.dot(half) > 0.0 && incoming.dot(half) < 0.0).then_some(half)
+    }
+
+    fn evaluate_transmission(&self, outgoing: Vec3, incoming: Vec3) -> Colour {
+        let Some(half) = self.refraction_half(outgoing, incoming) else {
+            return Colour::default();
+        };
+        if self.glass_weight == 0.0 {
+            return Colour::default();
+        }
+
+        let (out_half, in_half) = (outgoing.dot(half), incoming.dot(half));
+        let denominator = out_half + self.eta * in_half;
+        let transmitted = 1.0 - fresnel_dielectric(out_half, self.eta);
+
+        //scaled by eta squared, so like the other dielectrics we don't account for the change in ray density
+        self.glass_weight
+            * self.base_colour
+            * (transmitted
+                * self.eta
+                * self.eta
+                * self.specular.distribution(half)
+                * self.specular.masking_shadowing(outgoing, incoming)
+                * (out_half * in_half).abs()
+                / (outgoing.z() * incoming.z() * denominator * denominator).abs())
+    }
+
+    ///The probability density of [`Lobes::sample`] giving `incoming`
+    pub(crate) fn pdf(&self, outgoing: Vec3, incoming: Vec3) -> Decimal {
+        let [diffuse, specular, clearcoat, glass] = self.selection();
+
+        if incoming.z() < 0.0 {
+            let Some(half) = self.refraction_half(outgoing, incoming) else {
+                return 0.0;
+            };
+            let (out_half, in_half) = (outgoing.dot(half), incoming.dot(half));
+            let denominator = out_half + self.eta * in_half;
+            let jacobian = self.eta * self.eta * in_half.abs() / (denominator * denominator);
+            return glass
+                * (1.0 - fresnel_dielectric(out_half, self.eta))
+                * self.specular.visible_normal_pdf(outgoing, half)
+                * jacobian;
+        }
+
+        let half = (outgoing + incoming).unit();
+        let reflection =
+            |ggx: Ggx| ggx.visible_normal_pdf(outgoing, half) / (4.0 * outgoing.dot(half));
+
+        diffuse * incoming.z() / PI
+            + specular * reflection(self.specular)
+            + clearcoat * reflection(self.clearcoat)
+            + glass * fresnel_dielectric(outgoing.dot(half), self.eta) * reflection(self.specular)
+    }
+
+    ///Picks a lobe, and samples a direction from it
+    pub(crate) fn sample(&self, outgoing: Vec3, rng: &mut ThreadRng) -> Option<(Vec3, Lobe)> {
+        let [diffuse, specular, clearcoat, _] = self.selection();
+        let choice: Decimal = rng.gen();
+        let sample: (Decimal, Decimal) = (rng.gen(), rng.gen());
+
+        let (incoming, lobe) = if choice < diffuse {
+            //cosine weighted hemisphere, which sheen shares
+            let radius = Decimal::sqrt(sample.0);
+            let (sin, cos) = (TAU * sample.1).sin_cos();
+            let incoming = Vec3::new(radius * cos, radius * sin, (1.0 - sample.0).max(0.0).sqrt());
+            (incoming, Lobe::Diffuse)
+        } else if choice < diffuse + specular {
+            let half = self.specular.sample_visible_normal(outgoing, sample);
+            ((-outgoing).reflect(half), Lobe::Specular)
+        } else if choice < diffuse + specular + clearcoat {
+            let half = self.clearcoat.sample_visible_normal(outgoing, sample);
+            ((-outgoing).reflect(half), Lobe::Specular)
+        } else {
+            let half = self.specular.sample_visible_normal(outgoing, sample);
+            if rng.gen::<Decimal>() < fresnel_dielectric(outgoing.dot(half), self.eta) {
+                ((-outgoing).reflect(half), Lobe::Specular)
+            } else {
+                (
+                    (-outgoing).refract(half, 1.0 / self.eta),
+                    Lobe::Transmission,
+                )
+            }
+        };
+
+        //directions which end up on the wrong side of the surface would have hit another microfacet
+        let valid = if lobe == Lobe::Transmission {
+            incoming.z() < 0.0
+        } else {
+            incoming.z() > 0.0
+        };
+        valid.then_some((incoming, lobe))
+    }
+}
+
+impl Principled {
+    ///Samples a direction, returning the BSDF times the cosine over the pdf, the scattered ray, and the [`Lobe`] it was sampled from
+    pub(super) fn scatter(
+        &self,
+        direction_in: Vec3,
+        hit_record: &HitRecord,
+        rng: &mut ThreadRng,
+    ) -> Option<(Colour, Ray, Lobe)> {
+        let lobes = Lobes::new(self, hit_record);
+        let frame = Frame::new(hit_record.normal);
+        let outgoing = frame.to_local(-direction_in.unit());
+        if outgoing.z() <= 0.0 {
+            return None;
+        }
+
+        let (incoming, lobe) = lobes.sample(outgoing, rng)?;
+        let pdf = lobes.pdf(outgoing, incoming);
+        if pdf <= 0.0 || !pdf.is_finite() {
+            return None;
+        }
+
+        let weight = lobes.evaluate(outgoing, incoming) * (incoming.z().abs() / pdf);
+        Some((
+            weight,
+            Ray::new(hit_record.point, frame.to_world(incoming)),
+            lobe,
+        ))
+    }
+
+    ///The BSDF times the cosine for light arriving from `direction`, the pdf of [`Principled::scatter`] picking it, and the part of the first from the diffuse and sheen lobes
+    pub(super) fn evaluate(
+        &self,
+        direction_in: Vec3,
+        hit_record: &HitRecord,
+        direction: Vec3,
+    ) -> (Colour, Decimal, Colour) {
+        let lobes = Lobes::new(self, hit_record);
+        let frame = Frame::new(hit_record.normal);
+        let outgoing = frame.to_local(-direction_in.unit());
+        let incoming = frame.to_local(direction.unit());
+        if outgoing.z() <= 0.0 || incoming.z() == 0.0 {
+            return (Colour::default(), 0.0, Colour::default());
+        }
+
+        let cos_theta = incoming.z().abs();
+        let diffuse = lobes.evaluate_diffuse(outgoing, incoming) * cos_theta;
+        (
+            diffuse + lobes.evaluate_specular(outgoing, incoming) * cos_theta,
+            lobes.pdf(outgoing, incoming),
+            diffuse,
+        )
+    }
+}
@@ -0,0 +1,221 @@
+//! Thin film interference, which makes soap bubbles, oil slicks and anodised metals iridescent
+
+use super::microfacet::ComplexIor;
+use crate::primitives::{
+    decimal_consts::TAU,
+    spectrum::{cie_xyz, illuminant::Illuminant, xyz_to_linear_srgb},
+    Colour, Decimal,
+};
+use std::{
+    ops::{Add, Div, Mul, Sub},
+    sync::LazyLock,
+};
+
+///A complex number, for the amplitudes and phases of light waves
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Complex {
+    re: Decimal,
+    im: Decimal,
+}
+
+impl Complex {
+    const fn new(re: Decimal, im: Decimal) -> Self {
+        Self { re, im }
+    }
+    const fn real(re: Decimal) -> Self {
+        Self::new(re, 0.0)
+    }
+
+    fn norm_squared(self) -> Decimal {
+        self.re * self.re + self.im * self.im
+    }
+
+    ///The root with a non-negative imaginary part, which is the one for waves dying away rather than growing
+    fn sqrt(self) -> Self {
+        let norm = self.re.hypot(self.im);
+        let re = (0.5 * (norm + self.re)).max(0.0).sqrt();
+        let im = (0.5 * (norm - self.re)).max(0.0).sqrt();
+        Self::new(re, if self.im < 0.0 { -im } else { im })
+    }
+
+    ///`e^(i self)`
+    fn exp_i(self) -> Self {
+        let (sin, cos) = self.re.sin_cos();
+        let scale = (-self.im).exp();
+        Self::new(scale * cos, scale * sin)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+impl Div for Complex {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        let norm_squared = other.norm_squared();
+        Self::new(
+            (self.re * other.re + self.im * other.im) / norm_squared,
+            (self.im * other.re - self.re * other.im) / norm_squared,
+        )
+    }
+}
+
+///Wavelengths the reflectance is worked out at, and how much each adds to the red, green and blue of a white surface
+static WEIGHTS: LazyLock<Vec<(Decimal, Colour)>> = LazyLock::new(|| {
+    const FIRST: Decimal = 380.0;
+    const STEP: Decimal = 12.5;
+    const SAMPLES: usize = 33;
+
+    let white = Illuminant::d65();
+    let weights = (0..SAMPLES)
+        .map(|sample| {
+            let wavelength = FIRST + sample as Decimal * STEP;
+            let rgb = xyz_to_linear_srgb(cie_xyz(wavelength) * white.at(wavelength));
+            (wavelength, rgb)
+        })
+        .collect::<Vec<_>>();
+
+    //so a film which reflects everything is exactly white
+    let total = weights
+        .iter()
+        .fold(Colour::default(), |total, (_, rgb)| total + *rgb);
+    weights
+        .into_iter()
+        .map(|(wavelength, rgb)| {
+            let rgb = Colour::new(
+                rgb.x() / total.x(),
+                rgb.y() / total.y(),
+                rgb.z() / total.z(),
+            );
+            (wavelength, rgb)
+        })
+        .collect()
+});
+
+///Wavelengths in nanometres that the channels of a [`ComplexIor`] stand for, so it can be interpolated
+const CHANNEL_WAVELENGTHS: [Decimal; 3] = [630.0, 532.0, 465.0];
+
+///A channel of `colour` at `wavelength`, interpolated between [`CHANNEL_WAVELENGTHS`]
+fn channel_at(colour: Colour, wavelength: Decimal) -> Decimal {
+    let [red, green, blue] = CHANNEL_WAVELENGTHS;
+    if wavelength >= red {
+        colour.x()
+    } else if wavelength >= green {
+        let t = (wavelength - green) / (red - green);
+        colour.y() + (colour.x() - colour.y()) * t
+    } else if wavelength >= blue {
+        let t = (wavelength - blue) / (green - blue);
+        colour.z() + (colour.y() - colour.z()) * t
+    } else {
+        colour.z()
+    }
+}
+
+///A layer of transparent material a few hundred nanometres thick on top of a surface.
+///
+///Light reflecting off the top and bottom of the film interferes, so some wavelengths are reflected more than others, and the colour changes with the angle and thickness.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ThinFilm {
+    ///in nanometres
+    thickness: Decimal,
+    ior: Decimal,
+}
+
+impl ThinFilm {
+    ///`thickness` is in nanometres - films more than a micrometre or so thick just look clear
+    #[must_use]
+    pub const fn new(thickness: Decimal, ior: Decimal) -> Self {
+        Self { thickness, ior }
+    }
+
+    ///The fraction of light reflected for each colour channel, when the film is on a dielectric.
+    ///
+    ///Light comes from a medium with index of refraction `outside`, and the film is on one with `inside`.
+    #[must_use]
+    pub fn over_dielectric(&self, cos_theta: Decimal, outside: Decimal, inside: Decimal) -> Colour {
+        self.reflectance(cos_theta, outside, |_| Complex::real(inside))
+    }
+
+    ///The fraction of light reflected for each colour channel, when the film is on a conductor in air
+    #[must_use]
+    pub fn over_conductor(&self, cos_theta: Decimal, base: ComplexIor) -> Colour {
+        self.reflectance(cos_theta, 1.0, |wavelength| {
+            Complex::new(
+                channel_at(base.eta, wavelength),
+                channel_at(base.k, wavelength),
+            )
+        })
+    }
+
+    ///The reflectance spectrum, turned into a colour - the strongest colours are outside of sRGB, so they are clipped
+    fn reflectance(
+        &self,
+        cos_theta: Decimal,
+        outside: Decimal,
+        base: impl Fn(Decimal) -> Complex,
+    ) -> Colour {
+        let cos_theta = cos_theta.clamp(0.0, 1.0);
+        let rgb = WEIGHTS
+            .iter()
+            .fold(Colour::default(), |total, &(wavelength, weight)| {
+                total + weight * self.at(cos_theta, outside, base(wavelength), wavelength)
+            });
+        Colour::new(
+            rgb.x().clamp(0.0, 1.0),
+            rgb.y().clamp(0.0, 1.0),
+            rgb.z().clamp(0.0, 1.0),
+        )
+    }
+
+    ///The fraction of light reflected at one wavelength, adding up every reflection inside the film (the Airy summation)
+    fn at(
+        &self,
+        cos_theta: Decimal,
+        outside: Decimal,
+        base: Complex,
+        wavelength: Decimal,
+    ) -> Decimal {
+        let (n1, n2, n3) = (Complex::real(outside), Complex::real(self.ior), base);
+        //Snell's law says n sin θ is the same in every layer, so n cos θ = sqrt(n² - (n sin θ)²)
+        let sin_squared = Complex::real(outside * outside * (1.0 - cos_theta * cos_theta));
+        let q1 = Complex::real(outside * cos_theta);
+        let q2 = (n2 * n2 - sin_squared).sqrt();
+        let q3 = (n3 * n3 - sin_squared).sqrt();
+
+        //the extra distance travelled by light going down and back up through the film
+        let phase = q2 * Complex::real(2.0 * TAU * self.thickness / wavelength);
+        let shift = phase.exp_i();
+        let one = Complex::real(1.0);
+        let airy = |top: Complex, bottom: Complex| {
+            ((top + bottom * shift) / (one + top * bottom * shift)).norm_squared()
+        };
+
+        let perpendicular = |qi: Complex, qj: Complex| (qi - qj) / (qi + qj);
+        let parallel = |ni: Complex, qi: Complex, nj: Complex, qj: Complex| {
+            let (a, b) = (nj * nj * qi, ni * ni * qj);
+            (a - b) / (a + b)
+        };
+
+        let s = airy(perpendicular(q1, q2), perpendicular(q2, q3));
+        let p = airy(parallel(n1, q1, n2, q2), parallel(n2, q2, n3, q3));
+        (0.5 * (s + p)).clamp(0.0, 1.0)
+    }
+}
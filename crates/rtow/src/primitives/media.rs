@@ -0,0 +1,175 @@
+//! Participating media like fog, smoke and milk, which absorb and scatter light as it travels through them rather than at a surface
+
+use crate::primitives::{
+    decimal_consts::{PI, TAU},
+    Colour, Decimal, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+use std::fmt::Debug;
+
+pub mod grid;
+
+///How light is scattered by particles in a medium
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PhaseFunction {
+    ///equally in every direction
+    #[default]
+    Isotropic,
+    ///asymmetry from -1 to 1 - positive values mostly scatter forwards (like fog and clouds), and negative values back towards the light
+    HenyeyGreenstein(Decimal),
+}
+
+impl PhaseFunction {
+    ///The probability density of light travelling along `direction_in` being scattered into `direction_out`, which are both unit vectors
+    #[must_use]
+    pub fn evaluate(self, direction_in: Vec3, direction_out: Vec3) -> Decimal {
+        match self {
+            Self::Isotropic => 1.0 / (4.0 * PI),
+            Self::HenyeyGreenstein(asymmetry) => {
+                let cos_theta = direction_in.dot(direction_out);
+                let denominator = 1.0 + asymmetry * asymmetry - 2.0 * asymmetry * cos_theta;
+                (1.0 - asymmetry * asymmetry) / (4.0 * PI * denominator * denominator.sqrt())
+            }
+        }
+    }
+
+    ///A unit direction for light travelling along the unit vector `direction_in` to be scattered into, distributed exactly like [`PhaseFunction::evaluate`]
+    #[must_use]
+    pub fn sample(self, direction_in: Vec3, rng: &mut ThreadRng) -> Vec3 {
+        let asymmetry = match self {
+            Self::HenyeyGreenstein(asymmetry) if asymmetry.abs() > 1e-3 => asymmetry,
+            //close enough to isotropic that the formula below loses all its precision
+            _ => return Vec3::random_unit_vector(rng),
+        };
+
+        let sample: Decimal = rng.gen();
+        let squared = (1.0 - asymmetry * asymmetry) / (1.0 - asymmetry + 2.0 * asymmetry * sample);
+        let cos_theta = ((1.0 + asymmetry * asymmetry - squared * squared) / (2.0 * asymmetry))
+            .clamp(-1.0, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = rng.gen_range(0.0..TAU);
+
+        let (tangent, bitangent) = direction_in.orthonormal_basis();
+        sin_theta * (phi.cos() * tangent + phi.sin() * bitangent) + cos_theta * direction_in
+    }
+}
+
+///Where a ray got to in a medium, from [`Medium::sample_distance`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DistanceSample {
+    ///what the light coming from further along the path is multiplied by
+    pub weight: Colour,
+    ///light given off by the medium along the way, which isn't multiplied by the weight
+    pub emitted: Colour,
+    ///the `t` of the scattering, or [`None`] if the ray got through
+    pub scattered_at: Option<Decimal>,
+}
+
+///Something which fills a volume of space and interacts with light travelling through it
+pub trait Medium: Debug + Send + Sync {
+    fn phase(&self) -> PhaseFunction;
+
+    ///Samples where along `ray` it gets scattered, if before `t_max`
+    fn sample_distance(&self, ray: Ray, t_max: Decimal, rng: &mut ThreadRng) -> DistanceSample;
+
+    ///The fraction of light which makes it along `ray` up to `t_max` without being absorbed or scattered away - this can be a random estimate
+    fn transmittance(&self, ray: Ray, t_max: Decimal, rng: &mut ThreadRng) -> Colour;
+}
+
+///A medium with the same density everywhere.
+///
+///The coefficients are per unit of distance, for each colour channel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Homogeneous {
+    absorption: Colour,
+    scattering: Colour,
+    phase: PhaseFunction,
+}
+
+impl Homogeneous {
+    #[must_use]
+    pub const fn new(absorption: Colour, scattering: Colour, phase: PhaseFunction) -> Self {
+        Self {
+            absorption,
+            scattering,
+            phase,
+        }
+    }
+
+    ///A medium where `density` is how much light interacts with it per unit of distance, and `albedo` is how much of that is scattered rather than absorbed
+    #[must_use]
+    pub fn from_density(density: Decimal, albedo: Colour, phase: PhaseFunction) -> Self {
+        Self::new(
+            density * (Colour::splat(1.0) - albedo),
+            density * albedo,
+            phase,
+        )
+    }
+
+    fn extinction(&self) -> Colour {
+        self.absorption + self.scattering
+    }
+
+    fn transmittance_over(&self, distance: Decimal) -> Colour {
+        let extinction = self.extinction();
+        let channel = |coefficient: Decimal| {
+            if coefficient > 0.0 {
+                (-coefficient * distance).exp()
+            } else {
+                1.0
+            }
+        };
+        Colour::new(
+            channel(extinction.x()),
+            channel(extinction.y()),
+            channel(extinction.z()),
+        )
+    }
+}
+
+impl Medium for Homogeneous {
+    fn phase(&self) -> PhaseFunction {
+        self.phase
+    }
+
+    ///The distance is sampled using one channel picked at random, weighted against all of them so coloured media don't get noisy
+    fn sample_distance(&self, ray: Ray, t_max: Decimal, rng: &mut ThreadRng) -> DistanceSample {
+        let extinction = self.extinction();
+        let speed = ray.direction().magnitude();
+        let coefficient = extinction[rng.gen_range(0..3)];
+
+        let distance = if coefficient > 0.0 {
+            -(1.0 - rng.gen::<Decimal>()).ln() / coefficient
+        } else {
+            Decimal::INFINITY
+        };
+        let scattered = distance < t_max * speed;
+        let distance = distance.min(t_max * speed);
+        let transmittance = self.transmittance_over(distance);
+        let average = |colour: Colour| (colour.x() + colour.y() + colour.z()) / 3.0;
+
+        let (weight, scattered_at) = if scattered {
+            let pdf = average(extinction * transmittance);
+            (
+                self.scattering * transmittance / pdf,
+                Some(distance / speed),
+            )
+        } else {
+            let pdf = average(transmittance);
+            if pdf > 0.0 {
+                (transmittance / pdf, None)
+            } else {
+                (Colour::default(), None)
+            }
+        };
+        DistanceSample {
+            weight,
+            emitted: Colour::default(),
+            scattered_at,
+        }
+    }
+
+    fn transmittance(&self, ray: Ray, t_max: Decimal, _rng: &mut ThreadRng) -> Colour {
+        self.transmittance_over(t_max * ray.direction().magnitude())
+    }
+}
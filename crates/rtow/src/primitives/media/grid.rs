@@ -0,0 +1,419 @@
+//! Media where the density changes through space, stored in voxel grids
+
+use crate::primitives::{
+    media::{DistanceSample, Medium, PhaseFunction},
+    spectrum::blackbody_radiance,
+    Colour, Decimal, Point3, Ray, Vec3,
+};
+use rand::{rngs::ThreadRng, Rng};
+
+///A 3D grid of values, which covers 0-1 on each axis and is trilinearly interpolated between the voxel centres.
+///
+///Values are stored with x changing fastest, then y, then z.
+#[derive(Debug, Clone)]
+pub struct DensityGrid {
+    size: [usize; 3],
+    values: Vec<Decimal>,
+}
+
+impl DensityGrid {
+    /// # Panics
+    /// If there aren't exactly as many values as voxels
+    #[must_use]
+    pub fn new(size: [usize; 3], values: Vec<Decimal>) -> Self {
+        assert_eq!(
+            values.len(),
+            size.iter().product::<usize>(),
+            "wrong number of voxels"
+        );
+        Self { size, values }
+    }
+
+    ///Fills the grid with `density` at the centre of every voxel - eg. with [`Perlin`](crate::primitives::textures::noise::Perlin) noise
+    #[must_use]
+    pub fn from_fn(size: [usize; 3], density: impl Fn(Point3) -> Decimal) -> Self {
+        let [width, height, depth] = size;
+        let values = (0..depth)
+            .flat_map(|z| (0..height).flat_map(move |y| (0..width).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| {
+                density(Point3::new(
+                    (x as Decimal + 0.5) / width as Decimal,
+                    (y as Decimal + 0.5) / height as Decimal,
+                    (z as Decimal + 0.5) / depth as Decimal,
+                ))
+            })
+            .collect();
+        Self { size, values }
+    }
+
+    #[must_use]
+    pub const fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    ///The largest value in the grid
+    #[must_use]
+    pub fn max(&self) -> Decimal {
+        self.values.iter().copied().fold(0.0, Decimal::max)
+    }
+
+    ///The voxel at the given indices, clamped to the edges of the grid
+    fn voxel(&self, x: isize, y: isize, z: isize) -> Decimal {
+        let clamp = |index: isize, size: usize| usize::try_from(index).unwrap_or(0).min(size - 1);
+        let [width, height, depth] = self.size;
+        self.values[(clamp(z, depth) * height + clamp(y, height)) * width + clamp(x, width)]
+    }
+
+    ///The interpolated value at `point` - 0 outside of the grid
+    #[must_use]
+    pub fn value(&self, point: Point3) -> Decimal {
+        if self.values.is_empty() || (0..3).any(|axis| !(0.0..=1.0).contains(&point[axis])) {
+            return 0.0;
+        }
+
+        //voxel centres are at half coordinates
+        let coordinate = |axis: usize| point[axis] * self.size[axis] as Decimal - 0.5;
+        let (x, y, z) = (coordinate(0), coordinate(1), coordinate(2));
+        let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+        let (fx, fy, fz) = (x - x0, y - y0, z - z0);
+        let (x0, y0, z0) = (x0 as isize, y0 as isize, z0 as isize);
+
+        let lerp = |a: Decimal, b: Decimal, t: Decimal| a + (b - a) * t;
+        let row = |y: isize, z: isize| lerp(self.voxel(x0, y, z), self.voxel(x0 + 1, y, z), fx);
+        let slice = |z: isize| lerp(row(y0, z), row(y0 + 1, z), fy);
+        lerp(slice(z0), slice(z0 + 1), fz)
+    }
+
+    ///The largest value which can be interpolated anywhere between `min` and `max`
+    fn max_between(&self, min: Point3, max: Point3) -> Decimal {
+        //every voxel with a centre within one voxel of the region
+        let range = |axis: usize| {
+            let size = self.size[axis] as Decimal;
+            let low = (min[axis] * size - 0.5).floor() as isize;
+            let high = (max[axis] * size - 0.5).ceil() as isize;
+            low..=high
+        };
+
+        let mut largest: Decimal = 0.0;
+        for z in range(2) {
+            for y in range(1) {
+                for x in range(0) {
+                    largest = largest.max(self.voxel(x, y, z));
+                }
+            }
+        }
+        largest
+    }
+}
+
+///Light given off by a volume, from a grid of values which are each turned into a colour
+#[derive(Debug, Clone)]
+pub struct Emission {
+    grid: DensityGrid,
+    ///the radiance for grid values evenly spaced from 0 to `max`, which is linearly interpolated
+    radiance: Vec<Colour>,
+    max: Decimal,
+}
+
+impl Emission {
+    ///Emission where the grid is the brightness of `colour`
+    #[must_use]
+    pub fn new(grid: DensityGrid, colour: Colour) -> Self {
+        let max = grid.max();
+        Self {
+            grid,
+            radiance: vec![Colour::default(), colour * max],
+            max,
+        }
+    }
+
+    ///Emission where the grid is a temperature in kelvin, and the colour and brightness come from Planck's law - for fire.
+    ///
+    ///The radiance is in physical units, so `scale` needs to be tiny - with 1e-5, 3000K has a brightness of about 0.5, and cooler parts are much dimmer.
+    #[must_use]
+    pub fn blackbody(temperature: DensityGrid, scale: Decimal) -> Self {
+        const STEPS: usize = 256;
+
+        let max = temperature.max();
+        let radiance = (0..=STEPS)
+            .map(|step| scale * blackbody_radiance(max * step as Decimal / STEPS as Decimal))
+            .collect();
+        Self {
+            grid: temperature,
+            radiance,
+            max,
+        }
+    }
+
+    fn radiance(&self, point: Point3) -> Colour {
+        if self.max <= 0.0 {
+            return Colour::default();
+        }
+
+        let position = self.grid.value(point).clamp(0.0, self.max) / self.max
+            * (self.radiance.len() - 1) as Decimal;
+        let index = (position.floor() as usize).min(self.radiance.len() - 2);
+        let fraction = position - index as Decimal;
+        self.radiance[index] * (1.0 - fraction) + self.radiance[index + 1] * fraction
+    }
+}
+
+///The largest density in blocks of voxels, so we can take big steps through the thin parts of a grid
+#[derive(Debug, Clone)]
+struct Majorants {
+    size: [usize; 3],
+    values: Vec<Decimal>,
+}
+
+impl Majorants {
+    ///Blocks are at most this many per axis
+    const RESOLUTION: usize = 16;
+
+    fn new(density: &DensityGrid, scale: Decimal) -> Self {
+        let size = density.size.map(|size| size.clamp(1, Self::RESOLUTION));
+        let [width, height, depth] = size;
+
+        let values = (0..depth)
+            .flat_map(|z| (0..height).flat_map(move |y| (0..width).map(move |x| (x, y, z))))
+            .map(|(x, y, z)| {
+                let corner = |x: usize, y: usize, z: usize| {
+                    Point3::new(
+                        x as Decimal / width as Decimal,
+                        y as Decimal / height as Decimal,
+                        z as Decimal / depth as Decimal,
+                    )
+                };
+                scale * density.max_between(corner(x, y, z), corner(x + 1, y + 1, z + 1))
+            })
+            .collect();
+
+        Self { size, values }
+    }
+
+    fn get(&self, [x, y, z]: [usize; 3]) -> Decimal {
+        let [width, height, _] = self.size;
+        self.values[(z * height + y) * width + x]
+    }
+}
+
+///A medium with the density from a [`DensityGrid`], stretched over a box in world space.
+///
+///The medium only exists inside the object it is on, so that should usually be a box the same size as the grid.
+#[derive(Debug, Clone)]
+pub struct Heterogeneous {
+    density: DensityGrid,
+    min: Point3,
+    max: Point3,
+    ///extinction per unit of distance for a density of 1
+    scale: Decimal,
+    albedo: Colour,
+    phase: PhaseFunction,
+    emission: Option<Emission>,
+    majorants: Majorants,
+}
+
+impl Heterogeneous {
+    ///`scale` is how much light interacts with the medium per unit of distance where the density is 1, and `albedo` is how much of that is scattered rather than absorbed
+    #[must_use]
+    pub fn new(
+        density: DensityGrid,
+        min: Point3,
+        max: Point3,
+        scale: Decimal,
+        albedo: Colour,
+        phase: PhaseFunction,
+    ) -> Self {
+        let majorants = Majorants::new(&density, scale);
+        Self {
+            density,
+            min,
+            max,
+            scale,
+            albedo,
+            phase,
+            emission: None,
+            majorants,
+        }
+    }
+
+    ///Makes the medium glow, from an emission grid covering the same box.
+    ///
+    ///Only the absorbing parts of the medium give off light, so there has to be some density where the emission is.
+    #[must_use]
+    pub fn with_emission(mut self, emission: Emission) -> Self {
+        self.emission = Some(emission);
+        self
+    }
+
+    ///World space to 0-1 across the grid
+    fn local(&self, point: Point3) -> Point3 {
+        let offset = point - self.min;
+        let size = self.max - self.min;
+        Point3::new(
+            offset.x() / size.x(),
+            offset.y() / size.y(),
+            offset.z() / size.z(),
+        )
+    }
+
+    ///Walks along `ray` through the majorant blocks with a 3D DDA, calling `visit` with the start and end `t` of each one and its majorant, until it returns something
+    fn march<T>(
+        &self,
+        ray: Ray,
+        t_max: Decimal,
+        mut visit: impl FnMut(Decimal, Decimal, Decimal) -> Option<T>,
+    ) -> Option<T> {
+        let size = self.majorants.size;
+        let scale = |point: Vec3| {
+            Vec3::new(
+                point.x() * size[0] as Decimal,
+                point.y() * size[1] as Decimal,
+                point.z() * size[2] as Decimal,
+            )
+        };
+        //a ray in block coordinates, with the same t
+        let origin = scale(self.local(ray.origin()));
+        let direction =
+            scale(self.local(ray.origin() + ray.direction()) - self.local(ray.origin()));
+
+        let (mut start, mut end) = (0.0, t_max);
+        for axis in 0..3 {
+            let inverse = 1.0 / direction[axis];
+            let mut near = -origin[axis] * inverse;
+            let mut far = (size[axis] as Decimal - origin[axis]) * inverse;
+            if inverse < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+            if near.is_nan() || far.is_nan() {
+                //parallel to this axis, and exactly on a face
+                continue;
+            }
+            start = Decimal::max(start, near);
+            end = Decimal::min(end, far);
+        }
+        if start >= end {
+            return None;
+        }
+
+        let entry = origin + direction * start;
+        let mut cell = [0; 3];
+        let mut next = [Decimal::INFINITY; 3];
+        let mut delta = [Decimal::INFINITY; 3];
+        for axis in 0..3 {
+            cell[axis] = (entry[axis].floor().max(0.0) as usize).min(size[axis] - 1);
+            if direction[axis] > 0.0 {
+                next[axis] = ((cell[axis] + 1) as Decimal - origin[axis]) / direction[axis];
+                delta[axis] = 1.0 / direction[axis];
+            } else if direction[axis] < 0.0 {
+                next[axis] = (cell[axis] as Decimal - origin[axis]) / direction[axis];
+                delta[axis] = -1.0 / direction[axis];
+            }
+        }
+
+        let mut t = start;
+        loop {
+            let axis = (0..3)
+                .min_by(|&a, &b| next[a].total_cmp(&next[b]))
+                .unwrap_or_default();
+            let exit = next[axis].min(end);
+
+            if exit > t {
+                if let Some(result) = visit(t, exit, self.majorants.get(cell)) {
+                    return Some(result);
+                }
+            }
+            if exit >= end {
+                return None;
+            }
+
+            t = exit;
+            if direction[axis] > 0.0 {
+                cell[axis] += 1;
+                if cell[axis] >= size[axis] {
+                    return None;
+                }
+            } else {
+                if cell[axis] == 0 {
+                    return None;
+                }
+                cell[axis] -= 1;
+            }
+            next[axis] += delta[axis];
+        }
+    }
+}
+
+impl Medium for Heterogeneous {
+    fn phase(&self) -> PhaseFunction {
+        self.phase
+    }
+
+    ///Delta tracking - tentative collisions are sampled against the majorant, and each is real in proportion to the actual density there
+    fn sample_distance(&self, ray: Ray, t_max: Decimal, rng: &mut ThreadRng) -> DistanceSample {
+        let speed = ray.direction().magnitude();
+        let mut emitted = Colour::default();
+
+        let scattered_at = self.march(ray, t_max, |start, end, majorant| {
+            if majorant <= 0.0 {
+                return None;
+            }
+
+            let mut t = start;
+            loop {
+                t -= (1.0 - rng.gen::<Decimal>()).ln() / (majorant * speed);
+                if t >= end {
+                    return None;
+                }
+
+                let local = self.local(ray.at(t));
+                let extinction = self.scale * self.density.value(local);
+                if let Some(emission) = &self.emission {
+                    //adding the emission at every tentative collision is much less noisy than only at real ones
+                    let absorption = extinction * (Colour::splat(1.0) - self.albedo);
+                    emitted += absorption * emission.radiance(local) / majorant;
+                }
+                if rng.gen::<Decimal>() * majorant < extinction {
+                    return Some(t);
+                }
+            }
+        });
+
+        DistanceSample {
+            weight: if scattered_at.is_some() {
+                self.albedo
+            } else {
+                Colour::splat(1.0)
+            },
+            emitted,
+            scattered_at,
+        }
+    }
+
+    ///Ratio tracking - the transmittance is multiplied by the chance of each tentative collision being fake
+    fn transmittance(&self, ray: Ray, t_max: Decimal, rng: &mut ThreadRng) -> Colour {
+        let speed = ray.direction().magnitude();
+        let mut transmittance = 1.0;
+
+        self.march(ray, t_max, |start, end, majorant| {
+            if majorant <= 0.0 {
+                return None;
+            }
+
+            let mut t = start;
+            loop {
+                t -= (1.0 - rng.gen::<Decimal>()).ln() / (majorant * speed);
+                if t >= end {
+                    return None;
+                }
+
+                let extinction = self.scale * self.density.value(self.local(ray.at(t)));
+                transmittance *= 1.0 - (extinction / majorant).min(1.0);
+                if transmittance <= 0.0 {
+                    return Some(());
+                }
+            }
+        });
+
+        Colour::splat(transmittance)
+    }
+}
@@ -1,16 +1,44 @@
-use crate::primitives::{collisions::Hittable, Colour, Decimal, Vec3};
-use rand::rngs::ThreadRng;
+use crate::primitives::{spectrum::Wavelengths, Colour, Decimal, Point3, Vec3};
+
+///Rays offset by one pixel in x and y on the image, used to work out how much of a surface a pixel covers
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayDifferentials {
+    pub x_origin: Vec3,
+    pub x_direction: Vec3,
+    pub y_origin: Vec3,
+    pub y_direction: Vec3,
+}
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Ray {
     origin: Vec3,
     direction: Vec3,
+    ///only camera rays have these - scattered rays are looked up without any filtering footprint
+    differentials: Option<RayDifferentials>,
+    ///only in spectral mode, where every ray on a path carries the wavelengths from the camera
+    wavelengths: Option<Wavelengths>,
 }
 
 impl Ray {
     #[must_use]
     pub const fn new(origin: Vec3, direction: Vec3) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            differentials: None,
+            wavelengths: None,
+        }
+    }
+    #[must_use]
+    pub const fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
+    #[must_use]
+    pub const fn with_wavelengths(mut self, wavelengths: Wavelengths) -> Self {
+        self.wavelengths = Some(wavelengths);
+        self
     }
 
     #[must_use]
@@ -22,26 +50,35 @@ impl Ray {
         self.direction
     }
     #[must_use]
+    pub const fn differentials(&self) -> Option<RayDifferentials> {
+        self.differentials
+    }
+    #[must_use]
+    pub const fn wavelengths(&self) -> Option<Wavelengths> {
+        self.wavelengths
+    }
+    #[must_use]
     pub fn at(&self, t: Decimal) -> Vec3 {
         self.origin + self.direction * t
     }
 
-    #[must_use]
-    pub fn colour(&self, world: &dyn Hittable, depth: usize, rng: &mut ThreadRng) -> Colour {
-        if depth == 0 {
-            return Colour::default();
-        }
-
-        if let Some(hit) = world.hit(*self, 0.00001, Decimal::INFINITY) {
-            return if let Some((attenuation, scattered)) = hit.material.scatter(*self, hit, rng) {
-                attenuation * scattered.colour(world, depth - 1, rng)
-            } else {
-                Colour::default()
-            };
-        }
-
-        let unit = self.direction.unit();
-        let t = 0.5 * (unit.y() + 1.0);
-        Colour::new(1.0, 1.0, 1.0) * (1.0 - t) + Colour::new(0.5, 0.7, 1.0) * t
+    ///Something which scales light, like an albedo, for this ray's wavelengths if it has them
+    pub(super) fn reflectance(&self, colour: Colour) -> Colour {
+        self.wavelengths
+            .map_or(colour, |wavelengths| wavelengths.reflectance(colour))
+    }
+    ///Light, like from the sky, for this ray's wavelengths if it has them
+    pub(super) fn light(&self, colour: Colour) -> Colour {
+        self.wavelengths
+            .map_or(colour, |wavelengths| wavelengths.illuminant(colour))
+    }
+    ///A ray carrying on along the same path, with the same wavelengths
+    pub(super) const fn continued(&self, mut ray: Self) -> Self {
+        ray.wavelengths = self.wavelengths;
+        ray
+    }
+    ///The same ray starting again from `origin`, like after going through a hole
+    pub(super) const fn restarted_at(&self, origin: Point3) -> Self {
+        Self { origin, ..*self }
     }
 }
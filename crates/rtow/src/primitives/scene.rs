@@ -0,0 +1,207 @@
+use crate::primitives::{
+    collisions::{Hittable, Sampleable},
+    environment::{Environment, SkyGradient},
+    lights::LightList,
+    materials::{Lobe, Material},
+    Decimal,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Write},
+    hash::{DefaultHasher, Hasher},
+    sync::Arc,
+};
+
+///Everything that gets rendered, other than the camera
+#[derive(Debug)]
+pub struct Scene {
+    world: Box<dyn Hittable>,
+    ///lights the scene
+    environment: Arc<dyn Environment>,
+    ///what camera rays see if they don't hit anything, if it isn't the environment
+    background: Option<Arc<dyn Environment>>,
+    lights: LightList,
+    ///emissive surfaces which are also in the world, for tracing paths from
+    area_lights: Vec<Arc<dyn Sampleable>>,
+    ///how many bounces before paths can be ended early
+    russian_roulette: Option<usize>,
+    ///the brightest light a single sample can find after bouncing
+    clamp: Option<Decimal>,
+    lobe_depths: LobeDepths,
+    ///the ID of each material in the world, by its address
+    material_ids: HashMap<usize, usize>,
+}
+
+///The most bounces of each [`Lobe`] a path can take, on top of the maximum depth for all of them - eg. few diffuse bounces but lots of transmission for glass.
+///
+///Lights are still sampled at the surface where a path ends, so a diffuse depth of 0 gives direct lighting only.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct LobeDepths {
+    pub diffuse: usize,
+    pub specular: usize,
+    pub transmission: usize,
+}
+
+impl Default for LobeDepths {
+    ///No limits, other than the maximum depth
+    fn default() -> Self {
+        Self {
+            diffuse: usize::MAX,
+            specular: usize::MAX,
+            transmission: usize::MAX,
+        }
+    }
+}
+
+impl LobeDepths {
+    #[must_use]
+    pub const fn get(&self, lobe: Lobe) -> usize {
+        match lobe {
+            Lobe::Diffuse => self.diffuse,
+            Lobe::Specular => self.specular,
+            Lobe::Transmission => self.transmission,
+        }
+    }
+}
+
+impl Scene {
+    ///A scene lit by the default [`SkyGradient`], with russian roulette after 3 bounces
+    #[must_use]
+    pub fn new(world: impl Hittable + 'static) -> Self {
+        let world: Box<dyn Hittable> = Box::new(world);
+        Self {
+            material_ids: material_ids(world.as_ref()),
+            world,
+            environment: Arc::new(SkyGradient::default()),
+            background: None,
+            lights: LightList::default(),
+            area_lights: vec![],
+            russian_roulette: Some(3),
+            clamp: None,
+            lobe_depths: LobeDepths::default(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_environment(mut self, environment: Arc<dyn Environment>) -> Self {
+        self.environment = environment;
+        self
+    }
+    ///Shows something else behind the scene, without changing the lighting - eg. a plain colour for product shots
+    #[must_use]
+    pub fn with_background(mut self, background: Arc<dyn Environment>) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    ///Lights which aren't in the world, so can only be found by sampling them
+    #[must_use]
+    pub fn with_lights(mut self, lights: LightList) -> Self {
+        self.lights = lights;
+        self
+    }
+
+    ///Emissive surfaces (which need to be in the world too) that integrators tracing paths from lights can start from.
+    ///
+    ///Other emissive surfaces still light the scene, but are only found by paths from the camera.
+    #[must_use]
+    pub fn with_area_lights(mut self, area_lights: Vec<Arc<dyn Sampleable>>) -> Self {
+        self.area_lights = area_lights;
+        self
+    }
+
+    ///Randomly ends paths after `bounces` bounces, more often the less light they can carry, which saves time without changing the average.
+    ///
+    ///[`None`] follows every path to the maximum depth.
+    #[must_use]
+    pub const fn with_russian_roulette(mut self, bounces: Option<usize>) -> Self {
+        self.russian_roulette = bounces;
+        self
+    }
+    ///Limits how bright the light found by a single sample after bouncing can be, which gets rid of fireflies but makes the image darker than it should be
+    #[must_use]
+    pub const fn with_clamp(mut self, clamp: Option<Decimal>) -> Self {
+        self.clamp = clamp;
+        self
+    }
+
+    ///Limits how many bounces of each kind paths can take
+    #[must_use]
+    pub const fn with_lobe_depths(mut self, lobe_depths: LobeDepths) -> Self {
+        self.lobe_depths = lobe_depths;
+        self
+    }
+
+    #[must_use]
+    pub fn world(&self) -> &dyn Hittable {
+        self.world.as_ref()
+    }
+    ///The ID given to a material in the world when the scene was made, which is the same for identical materials and from one run to the next.
+    ///
+    ///[`None`] for materials which aren't directly on an object, like the ones inside a [`Material::Mix`].
+    #[must_use]
+    pub fn material_id(&self, material: &Material) -> Option<usize> {
+        self.material_ids
+            .get(&std::ptr::from_ref(material).addr())
+            .copied()
+    }
+    #[must_use]
+    pub fn environment(&self) -> &dyn Environment {
+        self.environment.as_ref()
+    }
+    ///The environment seen by camera rays
+    #[must_use]
+    pub fn background(&self) -> &dyn Environment {
+        self.background
+            .as_deref()
+            .unwrap_or_else(|| self.environment())
+    }
+    #[must_use]
+    pub const fn lights(&self) -> &LightList {
+        &self.lights
+    }
+    #[must_use]
+    pub fn area_lights(&self) -> &[Arc<dyn Sampleable>] {
+        &self.area_lights
+    }
+    #[must_use]
+    pub const fn russian_roulette(&self) -> Option<usize> {
+        self.russian_roulette
+    }
+    #[must_use]
+    pub const fn clamp(&self) -> Option<Decimal> {
+        self.clamp
+    }
+    #[must_use]
+    pub const fn lobe_depths(&self) -> LobeDepths {
+        self.lobe_depths
+    }
+}
+
+///Feeds what would have been written straight into a hasher, so big materials don't have to be written out to a string
+struct HashWriter(DefaultHasher);
+
+impl Write for HashWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+///Numbers the materials in `world` in the order they're found, going by what's in them so that identical materials share an ID
+fn material_ids(world: &dyn Hittable) -> HashMap<usize, usize> {
+    let mut ids = HashMap::new();
+    let mut by_contents = HashMap::new();
+    for material in world.materials() {
+        let address = std::ptr::from_ref(material).addr();
+        if ids.contains_key(&address) {
+            continue;
+        }
+        let mut contents = HashWriter(DefaultHasher::new());
+        write!(contents, "{material:?}").expect("hashing can't fail");
+        let next = by_contents.len();
+        let id = *by_contents.entry(contents.0.finish()).or_insert(next);
+        ids.insert(address, id);
+    }
+    ids
+}
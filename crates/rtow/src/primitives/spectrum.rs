@@ -0,0 +1,232 @@
+//! Light as a spectrum of wavelengths, and converting it to colours
+
+use crate::primitives::{Colour, Decimal, Vec3};
+use illuminant::Illuminant;
+use rand::{rngs::ThreadRng, Rng};
+use std::sync::LazyLock;
+
+pub mod illuminant;
+
+///The visible range in nanometres
+pub const MIN_WAVELENGTH: Decimal = 360.0;
+pub const MAX_WAVELENGTH: Decimal = 830.0;
+
+///The CIE 1931 colour matching functions, as XYZ, at a wavelength in nanometres.
+///
+///Uses the multi-lobe fit from Wyman, Sloan and Shirley 2013, "Simple Analytic Approximations to the CIE XYZ Color Matching Functions".
+#[must_use]
+pub fn cie_xyz(wavelength: Decimal) -> Vec3 {
+    //a gaussian with a different width on each side of the peak
+    let lobe = |mean: Decimal, below: Decimal, above: Decimal| {
+        let width = if wavelength < mean { below } else { above };
+        let x = (wavelength - mean) / width;
+        (-0.5 * x * x).exp()
+    };
+
+    Vec3::new(
+        1.056 * lobe(599.8, 37.9, 31.0) + 0.362 * lobe(442.0, 16.0, 26.7)
+            - 0.065 * lobe(501.1, 20.4, 26.2),
+        0.821 * lobe(568.8, 46.9, 40.5) + 0.286 * lobe(530.9, 16.3, 31.1),
+        1.217 * lobe(437.0, 11.8, 36.0) + 0.681 * lobe(459.0, 26.0, 13.8),
+    )
+}
+
+///Converts CIE XYZ to linear sRGB, with a D65 white point - colours outside of sRGB get negative channels
+#[must_use]
+pub fn xyz_to_linear_srgb(xyz: Vec3) -> Colour {
+    Colour::new(
+        3.240_6 * xyz.x() - 1.537_2 * xyz.y() - 0.498_6 * xyz.z(),
+        -0.968_9 * xyz.x() + 1.875_8 * xyz.y() + 0.041_5 * xyz.z(),
+        0.055_7 * xyz.x() - 0.204_0 * xyz.y() + 1.057_0 * xyz.z(),
+    )
+}
+
+///Planck's law - the spectral radiance of a black body at `kelvin`, in watts per steradian per square metre per nanometre
+#[must_use]
+pub fn planck(wavelength: Decimal, kelvin: Decimal) -> Decimal {
+    const PLANCK: Decimal = 6.626_070_15e-34;
+    const LIGHT_SPEED: Decimal = 2.997_924_58e8;
+    const BOLTZMANN: Decimal = 1.380_649e-23;
+
+    if kelvin <= 0.0 {
+        return 0.0;
+    }
+    let metres = wavelength * 1e-9;
+    let radiance = 2.0 * PLANCK * LIGHT_SPEED * LIGHT_SPEED
+        / (metres.powi(5) * ((PLANCK * LIGHT_SPEED / (metres * BOLTZMANN * kelvin)).exp_m1()));
+    radiance * 1e-9
+}
+
+///The radiance of a black body at `kelvin` as a colour, where 1 in XYZ's Y is a watt per steradian per square metre of light weighted by how bright it looks
+#[must_use]
+pub fn blackbody_radiance(kelvin: Decimal) -> Colour {
+    const STEP: Decimal = 5.0;
+    const STEPS: usize = 94;
+
+    let xyz = (0..=STEPS)
+        .map(|step| {
+            let wavelength = MIN_WAVELENGTH + step as Decimal * STEP;
+            cie_xyz(wavelength) * (planck(wavelength, kelvin) * STEP)
+        })
+        .fold(Vec3::default(), |total, xyz| total + xyz);
+
+    //very hot and very cool black bodies are just outside of sRGB
+    let colour = xyz_to_linear_srgb(xyz);
+    Colour::new(
+        colour.x().max(0.0),
+        colour.y().max(0.0),
+        colour.z().max(0.0),
+    )
+}
+
+///The colour of a black body at `kelvin`, scaled to a luminance of 1 - for colour temperatures
+#[must_use]
+pub fn blackbody(kelvin: Decimal) -> Colour {
+    let colour = blackbody_radiance(kelvin);
+    let luminance = 0.2126 * colour.x() + 0.7152 * colour.y() + 0.0722 * colour.z();
+    if luminance > 0.0 {
+        colour / luminance
+    } else {
+        Colour::default()
+    }
+}
+
+///Linearly interpolates evenly spaced samples from `first` to `last` nanometres, clamping outside of them
+fn interpolate(samples: &[Decimal], first: Decimal, last: Decimal, wavelength: Decimal) -> Decimal {
+    let position =
+        ((wavelength - first) / (last - first)).clamp(0.0, 1.0) * (samples.len() - 1) as Decimal;
+    let index = (position as usize).min(samples.len() - 2);
+    let t = position - index as Decimal;
+    samples[index] * (1.0 - t) + samples[index + 1] * t
+}
+
+///The spectra for Smits' RGB to spectrum conversion, in ten bins from 380 to 720 nanometres - these are the middles of the first and last
+mod smits {
+    use crate::primitives::Decimal;
+
+    pub const FIRST: Decimal = 397.0;
+    pub const LAST: Decimal = 703.0;
+
+    pub const WHITE: [Decimal; 10] = [
+        1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000,
+    ];
+    pub const CYAN: [Decimal; 10] = [
+        0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000,
+    ];
+    pub const MAGENTA: [Decimal; 10] = [
+        1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959,
+    ];
+    pub const YELLOW: [Decimal; 10] = [
+        0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840,
+    ];
+    pub const RED: [Decimal; 10] = [
+        0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149,
+    ];
+    pub const GREEN: [Decimal; 10] = [
+        0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025,
+    ];
+    pub const BLUE: [Decimal; 10] = [
+        1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496,
+    ];
+}
+
+///A smooth reflectance spectrum for a linear sRGB colour, at `wavelength`.
+///
+///Uses Smits 1999, "An RGB to Spectrum Conversion for Reflectances", which keeps colours from 0-1 from 0-1 at every wavelength.
+#[must_use]
+pub fn upsample(colour: Colour, wavelength: Decimal) -> Decimal {
+    let spectrum =
+        |samples: &[Decimal]| interpolate(samples, smits::FIRST, smits::LAST, wavelength);
+    let (r, g, b) = (
+        colour.x().max(0.0),
+        colour.y().max(0.0),
+        colour.z().max(0.0),
+    );
+
+    //the white part, then the two colours which make up the rest, from the smallest channel up
+    if r <= g && r <= b {
+        r * spectrum(&smits::WHITE)
+            + if g <= b {
+                (g - r) * spectrum(&smits::CYAN) + (b - g) * spectrum(&smits::BLUE)
+            } else {
+                (b - r) * spectrum(&smits::CYAN) + (g - b) * spectrum(&smits::GREEN)
+            }
+    } else if g <= r && g <= b {
+        g * spectrum(&smits::WHITE)
+            + if r <= b {
+                (r - g) * spectrum(&smits::MAGENTA) + (b - r) * spectrum(&smits::BLUE)
+            } else {
+                (b - g) * spectrum(&smits::MAGENTA) + (r - b) * spectrum(&smits::RED)
+            }
+    } else {
+        b * spectrum(&smits::WHITE)
+            + if r <= g {
+                (r - b) * spectrum(&smits::YELLOW) + (g - r) * spectrum(&smits::GREEN)
+            } else {
+                (g - b) * spectrum(&smits::YELLOW) + (r - g) * spectrum(&smits::RED)
+            }
+    }
+}
+
+///The wavelengths a path carries in spectral mode, in nanometres.
+///
+///Uses hero wavelength sampling (Wilkie et al. 2014) - the first is picked at random, and the others are spread evenly from it across the visible range. Light for them is stored in each channel of a [`Colour`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Wavelengths([Decimal; 3]);
+
+impl Wavelengths {
+    #[must_use]
+    pub fn sample(rng: &mut ThreadRng) -> Self {
+        const RANGE: Decimal = MAX_WAVELENGTH - MIN_WAVELENGTH;
+
+        let hero = rng.gen_range(MIN_WAVELENGTH..MAX_WAVELENGTH);
+        Self([0.0, 1.0, 2.0].map(|index| {
+            MIN_WAVELENGTH + (hero - MIN_WAVELENGTH + RANGE * index / 3.0).rem_euclid(RANGE)
+        }))
+    }
+
+    ///The wavelength which decides the path when it can only follow one, like when glass splits light up
+    #[must_use]
+    pub const fn hero(&self) -> Decimal {
+        self.0[0]
+    }
+
+    ///A spectrum at each wavelength
+    pub fn spectrum(&self, spectrum: impl Fn(Decimal) -> Decimal) -> Colour {
+        let [first, second, third] = self.0.map(spectrum);
+        Colour::new(first, second, third)
+    }
+
+    ///A reflectance (or anything else which scales light) from linear sRGB
+    #[must_use]
+    pub fn reflectance(&self, colour: Colour) -> Colour {
+        self.spectrum(|wavelength| upsample(colour, wavelength))
+    }
+    ///Light from linear sRGB, so white gives [`Illuminant::d65`]
+    #[must_use]
+    pub fn illuminant(&self, colour: Colour) -> Colour {
+        static WHITE: LazyLock<Illuminant> = LazyLock::new(Illuminant::d65);
+        self.spectrum(|wavelength| upsample(colour, wavelength) * WHITE.at(wavelength))
+    }
+
+    ///What light for the wavelengths gets multiplied by when only the hero can carry on - the others are dropped, and it makes up for them
+    #[must_use]
+    pub const fn hero_only() -> Colour {
+        Colour::new(3.0, 0.0, 0.0)
+    }
+
+    ///Converts light at these wavelengths to linear sRGB, as a sample of the whole spectrum
+    #[must_use]
+    pub fn to_rgb(&self, light: Colour) -> Colour {
+        let light = [light.x(), light.y(), light.z()];
+        let xyz = self
+            .0
+            .iter()
+            .zip(light)
+            .fold(Vec3::default(), |xyz, (&wavelength, light)| {
+                xyz + cie_xyz(wavelength) * light
+            });
+        //divided by the chance of picking each wavelength
+        xyz_to_linear_srgb(xyz * ((MAX_WAVELENGTH - MIN_WAVELENGTH) / 3.0))
+    }
+}
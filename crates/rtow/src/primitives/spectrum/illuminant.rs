@@ -0,0 +1,112 @@
+//! Spectra of standard light sources
+
+use super::{cie_xyz, interpolate, planck, xyz_to_linear_srgb, MAX_WAVELENGTH, MIN_WAVELENGTH};
+use crate::primitives::{Colour, Decimal, Vec3};
+
+///CIE standard illuminant D65, which is average daylight and the white of sRGB, from 360 to 830 nanometres in steps of 10
+const D65: [Decimal; 48] = [
+    46.6383, 52.0891, 49.9755, 54.6482, 82.7549, 91.4860, 93.4318, 86.6823, 104.865, 117.008,
+    117.812, 114.861, 115.923, 108.811, 109.354, 107.802, 104.790, 107.689, 104.405, 104.046,
+    100.000, 96.3342, 95.7880, 88.6856, 90.0062, 89.5991, 87.6987, 83.2886, 83.6992, 80.0268,
+    80.2146, 82.2778, 78.2842, 69.7213, 71.6091, 74.3490, 61.6040, 69.8856, 75.0870, 63.5927,
+    46.4182, 66.8054, 63.3828, 64.3040, 59.4519, 51.9590, 57.4406, 60.3125,
+];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Kind {
+    EqualEnergy,
+    Daylight,
+    Blackbody(Decimal),
+}
+
+impl Kind {
+    fn at(self, wavelength: Decimal) -> Decimal {
+        match self {
+            Self::EqualEnergy => 1.0,
+            Self::Daylight => interpolate(&D65, MIN_WAVELENGTH, MAX_WAVELENGTH, wavelength),
+            Self::Blackbody(kelvin) => planck(wavelength, kelvin),
+        }
+    }
+
+    ///The colour, with the same scale as the spectrum
+    fn xyz(self) -> Vec3 {
+        const STEP: Decimal = 5.0;
+        const STEPS: usize = 94;
+
+        (0..=STEPS)
+            .map(|step| {
+                let wavelength = MIN_WAVELENGTH + step as Decimal * STEP;
+                cie_xyz(wavelength) * (self.at(wavelength) * STEP)
+            })
+            .fold(Vec3::default(), |total, xyz| total + xyz)
+    }
+}
+
+///The spectrum of a light source, scaled so its luminance (Y in XYZ) is 1 unless it's been changed
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Illuminant {
+    kind: Kind,
+    ///turns the spectrum into the right luminance
+    scale: Decimal,
+    ///for when we aren't rendering spectrally
+    rgb: Colour,
+}
+
+impl Illuminant {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            scale: 0.0,
+            rgb: Colour::default(),
+        }
+        .with_luminance(1.0)
+    }
+
+    ///CIE standard illuminant E, the same at every wavelength
+    #[must_use]
+    pub fn e() -> Self {
+        Self::new(Kind::EqualEnergy)
+    }
+    ///CIE standard illuminant A, a tungsten filament bulb
+    #[must_use]
+    pub fn a() -> Self {
+        Self::blackbody(2856.0)
+    }
+    ///CIE standard illuminant D65, average daylight
+    #[must_use]
+    pub fn d65() -> Self {
+        Self::new(Kind::Daylight)
+    }
+    ///A perfect black body glowing at `kelvin`
+    #[must_use]
+    pub fn blackbody(kelvin: Decimal) -> Self {
+        Self::new(Kind::Blackbody(kelvin))
+    }
+
+    #[must_use]
+    pub fn with_luminance(mut self, luminance: Decimal) -> Self {
+        let xyz = self.kind.xyz();
+        self.scale = if xyz.y() > 0.0 {
+            luminance / xyz.y()
+        } else {
+            0.0
+        };
+
+        //very cool or hot black bodies are just outside of sRGB
+        let rgb = xyz_to_linear_srgb(xyz * self.scale);
+        self.rgb = Colour::new(rgb.x().max(0.0), rgb.y().max(0.0), rgb.z().max(0.0));
+        self
+    }
+
+    ///The spectral radiance at `wavelength` in nanometres
+    #[must_use]
+    pub fn at(&self, wavelength: Decimal) -> Decimal {
+        self.kind.at(wavelength) * self.scale
+    }
+
+    ///As a linear sRGB colour
+    #[must_use]
+    pub const fn rgb(&self) -> Colour {
+        self.rgb
+    }
+}
@@ -0,0 +1,68 @@
+//! Textures, which let material parameters vary over a surface
+
+pub mod checker;
+pub mod combinators;
+pub mod gradient;
+pub mod image;
+pub mod noise;
+pub mod procedural;
+
+use crate::primitives::{Colour, Decimal, Point3};
+use std::{fmt::Debug, sync::Arc};
+
+///How much `u` and `v` change between neighbouring pixels, so textures can be averaged over the whole pixel rather than aliasing
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Footprint {
+    pub dudx: Decimal,
+    pub dvdx: Decimal,
+    pub dudy: Decimal,
+    pub dvdy: Decimal,
+}
+
+impl Footprint {
+    ///The longer side of the footprint, in UV space
+    #[must_use]
+    pub fn width(&self) -> Decimal {
+        let x = self.dudx.hypot(self.dvdx);
+        let y = self.dudy.hypot(self.dvdy);
+        x.max(y)
+    }
+}
+
+pub trait Texture: Debug + Send + Sync {
+    ///The colour at surface coordinates `u` and `v`, which are at `point` in the world
+    fn value(&self, u: Decimal, v: Decimal, point: Point3) -> Colour;
+
+    ///The colour averaged over `footprint` - only textures which alias need to override this
+    fn filtered_value(
+        &self,
+        u: Decimal,
+        v: Decimal,
+        point: Point3,
+        _footprint: Footprint,
+    ) -> Colour {
+        self.value(u, v, point)
+    }
+
+    ///The texture as a single number, for parameters like fuzz which aren't colours
+    fn scalar(&self, u: Decimal, v: Decimal, point: Point3) -> Decimal {
+        let colour = self.value(u, v, point);
+        (colour.x() + colour.y() + colour.z()) / 3.0
+    }
+}
+
+///The same colour everywhere
+#[derive(Debug, Copy, Clone)]
+pub struct SolidColour(pub Colour);
+
+impl Texture for SolidColour {
+    fn value(&self, _u: Decimal, _v: Decimal, _point: Point3) -> Colour {
+        self.0
+    }
+}
+
+impl From<Colour> for Arc<dyn Texture> {
+    fn from(colour: Colour) -> Self {
+        Arc::new(SolidColour(colour))
+    }
+}
@@ -0,0 +1,60 @@
+use crate::primitives::{textures::Texture, Colour, Decimal, Point3};
+use std::sync::Arc;
+
+///Alternates between two textures in squares across the surface coordinates.
+///
+///`scale` is the number of squares along each of `u` and `v`.
+#[derive(Debug, Clone)]
+pub struct Checker2D {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    scale: Decimal,
+}
+
+impl Checker2D {
+    #[must_use]
+    pub const fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, scale: Decimal) -> Self {
+        Self { even, odd, scale }
+    }
+}
+
+impl Texture for Checker2D {
+    fn value(&self, u: Decimal, v: Decimal, point: Point3) -> Colour {
+        let sum = (u * self.scale).floor() + (v * self.scale).floor();
+        if sum.rem_euclid(2.0) < 1.0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
+
+///Alternates between two textures in cubes through space, so it works on surfaces without good UVs.
+///
+///`size` is the length of the side of each cube.
+#[derive(Debug, Clone)]
+pub struct Checker3D {
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+    size: Decimal,
+}
+
+impl Checker3D {
+    #[must_use]
+    pub const fn new(even: Arc<dyn Texture>, odd: Arc<dyn Texture>, size: Decimal) -> Self {
+        Self { even, odd, size }
+    }
+}
+
+impl Texture for Checker3D {
+    fn value(&self, u: Decimal, v: Decimal, point: Point3) -> Colour {
+        let sum = (point.x() / self.size).floor()
+            + (point.y() / self.size).floor()
+            + (point.z() / self.size).floor();
+        if sum.rem_euclid(2.0) < 1.0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::primitives::{
+    textures::{Footprint, Texture},
+    Colour, Decimal, Point3,
+};
+use std::sync::Arc;
+
+///Multiplies another texture by a constant colour, eg. to tint or darken it
+#[derive(Debug, Clone)]
+pub struct Scaled {
+    inner: Arc<dyn Texture>,
+    factor: Colour,
+}
+
+impl Scaled {
+    #[must_use]
+    pub const fn new(inner: Arc<dyn Texture>, factor: Colour) -> Self {
+        Self { inner, factor }
+    }
+}
+
+impl Texture for Scaled {
+    fn value(&self, u: Decimal, v: Decimal, point: Point3) -> Colour {
+        self.inner.value(u, v, point) * self.factor
+    }
+
+    fn filtered_value(
+        &self,
+        u: Decimal,
+        v: Decimal,
+        point: Point3,
+        footprint: Footprint,
+    ) -> Colour {
+        self.inner.filtered_value(u, v, point, footprint) * self.factor
+    }
+}
+
+///Blends between two textures, using the scalar value of a third as the amount of `b`
+#[derive(Debug, Clone)]
+pub struct Mix {
+    a: Arc<dyn Texture>,
+    b: Arc<dyn Texture>,
+    amount: Arc<dyn Texture>,
+}
+
+impl Mix {
+    #[must_use]
+    pub const fn new(a: Arc<dyn Texture>, b: Arc<dyn Texture>, amount: Arc<dyn Texture>) -> Self {
+        Self { a, b, amount }
+    }
+}
+
+impl Texture for Mix {
+    fn value(&self, u: Decimal, v: Decimal, point: Point3) -> Colour {
+        let t = self.amount.scalar(u, v, point).clamp(0.0, 1.0);
+        self.a.value(u, v, point) * (1.0 - t) + self.b.value(u, v, point) * t
+    }
+
+    fn filtered_value(
+        &self,
+        u: Decimal,
+        v: Decimal,
+        point: Point3,
+        footprint: Footprint,
+    ) -> Colour {
+        let amount = self.amount.filtered_value(u, v, point, footprint);
+        let t = ((amount.x() + amount.y() + amount.z()) / 3.0).clamp(0.0, 1.0);
+        self.a.filtered_value(u, v, point, footprint) * (1.0 - t)
+            + self.b.filtered_value(u, v, point, footprint) * t
+    }
+}
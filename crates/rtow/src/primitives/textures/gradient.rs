@@ -0,0 +1,33 @@
+use crate::primitives::{textures::Texture, Colour, Decimal, Point3};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GradientAxis {
+    U,
+    V,
+}
+
+///Linearly blends from `start` to `end` along one of the surface coordinates
+#[derive(Debug, Copy, Clone)]
+pub struct Gradient {
+    start: Colour,
+    end: Colour,
+    axis: GradientAxis,
+}
+
+impl Gradient {
+    #[must_use]
+    pub const fn new(start: Colour, end: Colour, axis: GradientAxis) -> Self {
+        Self { start, end, axis }
+    }
+}
+
+impl Texture for Gradient {
+    fn value(&self, u: Decimal, v: Decimal, _point: Point3) -> Colour {
+        let t = match self.axis {
+            GradientAxis::U => u,
+            GradientAxis::V => v,
+        }
+        .clamp(0.0, 1.0);
+        self.start * (1.0 - t) + self.end * t
+    }
+}
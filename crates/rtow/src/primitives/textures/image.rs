@@ -0,0 +1,230 @@
+use crate::{
+    io::images::{self, LoadedImage},
+    primitives::{
+        textures::{Footprint, Texture},
+        Colour, Decimal, Point3,
+    },
+};
+use std::{io, path::Path};
+
+///What to do with UVs outside of 0-1
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    ///Tile the image
+    #[default]
+    Repeat,
+    ///Stretch the edge pixels out forever
+    Clamp,
+    ///Tile the image, flipping every other tile so the edges line up
+    Mirror,
+}
+
+impl WrapMode {
+    ///Wraps a whole-numbered texel coordinate into `0..size`
+    fn wrap(self, coordinate: Decimal, size: usize) -> usize {
+        let size = size as Decimal;
+        let wrapped = match self {
+            Self::Repeat => coordinate.rem_euclid(size),
+            Self::Clamp => coordinate.clamp(0.0, size - 1.0),
+            Self::Mirror => {
+                let tile = coordinate.rem_euclid(2.0 * size);
+                if tile < size {
+                    tile
+                } else {
+                    2.0 * size - 1.0 - tile
+                }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+///How pixels are looked up
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Filter {
+    ///The closest pixel in the full size image - blocky up close, and aliases from far away
+    Nearest,
+    ///Blends the 4 closest pixels in the full size image - smooth up close, but still aliases from far away
+    Bilinear,
+    ///Blends bilinear lookups from the two MIP levels closest to the size of the pixel footprint
+    #[default]
+    Trilinear,
+}
+
+#[derive(Debug, Clone)]
+struct MipLevel {
+    width: usize,
+    height: usize,
+    pixels: Vec<Colour>,
+}
+
+impl MipLevel {
+    ///Half the size (rounded down) of `self`, with every pixel being the average of the ones it covers
+    fn downsample(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                //odd sizes mean some pixels cover 3 source pixels instead of 2
+                let xs = x * self.width / width..(x + 1) * self.width / width;
+                let ys = y * self.height / height..(y + 1) * self.height / height;
+                let count = (xs.len() * ys.len()) as Decimal;
+
+                ys.flat_map(|sy| xs.clone().map(move |sx| (sx, sy)))
+                    .map(|(sx, sy)| self.pixels[sy * self.width + sx])
+                    .fold(Colour::default(), |acc, pixel| acc + pixel)
+                    / count
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn texel(&self, x: Decimal, y: Decimal, wrap: WrapMode) -> Colour {
+        self.pixels[wrap.wrap(y, self.height) * self.width + wrap.wrap(x, self.width)]
+    }
+
+    fn nearest(&self, u: Decimal, v: Decimal, wrap: WrapMode) -> Colour {
+        //v goes up the image, but rows go down
+        self.texel(
+            (u * self.width as Decimal).floor(),
+            ((1.0 - v) * self.height as Decimal).floor(),
+            wrap,
+        )
+    }
+
+    fn bilinear(&self, u: Decimal, v: Decimal, wrap: WrapMode) -> Colour {
+        //pixel centres are at half coordinates
+        let x = u * self.width as Decimal - 0.5;
+        let y = (1.0 - v) * self.height as Decimal - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+
+        let top = self.texel(x0, y0, wrap) * (1.0 - fx) + self.texel(x0 + 1.0, y0, wrap) * fx;
+        let bottom =
+            self.texel(x0, y0 + 1.0, wrap) * (1.0 - fx) + self.texel(x0 + 1.0, y0 + 1.0, wrap) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+}
+
+///A texture backed by a grid of linear pixels, with a MIP pyramid for filtering lookups over a [`Footprint`].
+///
+///Defaults to [`WrapMode::Repeat`] and [`Filter::Trilinear`].
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    ///full size first, down to 1x1
+    levels: Vec<MipLevel>,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    ///Makes a texture from raw linear pixels, stored in row-major configuration from the top left.
+    ///
+    /// # Panics
+    /// If there aren't exactly `width * height` pixels
+    #[must_use]
+    pub fn new(width: usize, height: usize, pixels: Vec<Colour>) -> Self {
+        assert_eq!(pixels.len(), width * height, "wrong number of pixels");
+
+        let mut levels = vec![MipLevel {
+            width,
+            height,
+            pixels,
+        }];
+        while let Some(last) = levels.last().filter(|last| last.width * last.height > 1) {
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        Self {
+            levels,
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+        }
+    }
+
+    ///Makes a texture from an image, decoding it to linear if needed
+    #[must_use]
+    pub fn from_image(image: LoadedImage) -> Self {
+        let LoadedImage {
+            width,
+            height,
+            pixels,
+            ..
+        } = image.into_linear();
+        Self::new(width, height, pixels)
+    }
+
+    ///Loads a PNG, PPM or HDR file - see [`images::load`]
+    ///
+    /// # Errors
+    /// If the image can't be read
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        images::load(path).map(Self::from_image)
+    }
+
+    #[must_use]
+    pub const fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+    #[must_use]
+    pub const fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    fn lookup(&self, u: Decimal, v: Decimal, footprint: Footprint) -> Colour {
+        let base = &self.levels[0];
+        if base.pixels.is_empty() {
+            return Colour::default();
+        }
+
+        match self.filter {
+            Filter::Nearest => base.nearest(u, v, self.wrap),
+            Filter::Bilinear => base.bilinear(u, v, self.wrap),
+            Filter::Trilinear => {
+                //the level where one pixel is about the size of the footprint
+                let texels = footprint.width() * base.width.max(base.height) as Decimal;
+                let level = texels.log2().clamp(0.0, (self.levels.len() - 1) as Decimal);
+                if level.is_nan() {
+                    return base.bilinear(u, v, self.wrap);
+                }
+
+                let lower = level.floor();
+                let fraction = level - lower;
+                let lower = lower as usize;
+                let colour = self.levels[lower].bilinear(u, v, self.wrap);
+                if fraction > 0.0 {
+                    colour * (1.0 - fraction)
+                        + self.levels[lower + 1].bilinear(u, v, self.wrap) * fraction
+                } else {
+                    colour
+                }
+            }
+        }
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: Decimal, v: Decimal, _point: Point3) -> Colour {
+        self.lookup(u, v, Footprint::default())
+    }
+
+    fn filtered_value(
+        &self,
+        u: Decimal,
+        v: Decimal,
+        _point: Point3,
+        footprint: Footprint,
+    ) -> Colour {
+        self.lookup(u, v, footprint)
+    }
+}
@@ -0,0 +1,178 @@
+//! Deterministic noise functions, used by the procedural textures
+
+use crate::primitives::{Decimal, Point3, Vec3};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+///Gradient noise from random unit vectors at the integer lattice points, smoothly interpolated between them.
+///
+///The same seed always gives the same noise.
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    ///one shuffled table of lattice indices per axis, which are xor-ed together to hash a lattice point
+    permutations: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    const POINT_COUNT: usize = 256;
+
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let gradients = (0..Self::POINT_COUNT)
+            .map(|_| {
+                //rejection sample so the directions are uniform
+                loop {
+                    let gradient = Vec3::new(
+                        rng.gen_range(-1.0..=1.0),
+                        rng.gen_range(-1.0..=1.0),
+                        rng.gen_range(-1.0..=1.0),
+                    );
+                    let length = gradient.magnitude_squared();
+                    if length > 1e-6 && length <= 1.0 {
+                        break gradient.unit();
+                    }
+                }
+            })
+            .collect();
+
+        let mut permutation = || {
+            let mut indices: Vec<usize> = (0..Self::POINT_COUNT).collect();
+            indices.shuffle(&mut rng);
+            indices
+        };
+        let permutations = [permutation(), permutation(), permutation()];
+
+        Self {
+            gradients,
+            permutations,
+        }
+    }
+
+    fn gradient(&self, x: i64, y: i64, z: i64) -> Vec3 {
+        let [px, py, pz] = &self.permutations;
+        self.gradients[px[(x & 255) as usize] ^ py[(y & 255) as usize] ^ pz[(z & 255) as usize]]
+    }
+
+    ///Noise at `point`, from about -1 to 1 - it is 0 at every lattice point
+    #[must_use]
+    pub fn noise(&self, point: Point3) -> Decimal {
+        let floor = Vec3::new(point.x().floor(), point.y().floor(), point.z().floor());
+        let fraction = point - floor;
+        let (x, y, z) = (floor.x() as i64, floor.y() as i64, floor.z() as i64);
+
+        //hermite smoothing, so the noise has no creases at the lattice cell boundaries
+        let smooth = |t: Decimal| t * t * (3.0 - 2.0 * t);
+        let (sx, sy, sz) = (
+            smooth(fraction.x()),
+            smooth(fraction.y()),
+            smooth(fraction.z()),
+        );
+
+        let mut total = 0.0;
+        for corner in 0..8_u8 {
+            let (cx, cy, cz) = (corner & 1, (corner >> 1) & 1, (corner >> 2) & 1);
+            let weight = |offset: u8, t: Decimal| if offset == 1 { t } else { 1.0 - t };
+            let towards_point =
+                fraction - Vec3::new(Decimal::from(cx), Decimal::from(cy), Decimal::from(cz));
+
+            total += weight(cx, sx)
+                * weight(cy, sy)
+                * weight(cz, sz)
+                * self
+                    .gradient(x + i64::from(cx), y + i64::from(cy), z + i64::from(cz))
+                    .dot(towards_point);
+        }
+        total
+    }
+
+    ///Fractional Brownian motion - `octaves` layers of noise, each at double the frequency and half the amplitude of the last
+    #[must_use]
+    pub fn fbm(&self, point: Point3, octaves: usize) -> Decimal {
+        self.octaves(point, octaves, Self::noise)
+    }
+
+    ///Like [`Perlin::fbm`], but summing the absolute value of each layer, which gives sharp creases
+    #[must_use]
+    pub fn turbulence(&self, point: Point3, octaves: usize) -> Decimal {
+        self.octaves(point, octaves, |perlin, point| perlin.noise(point).abs())
+    }
+
+    fn octaves(
+        &self,
+        mut point: Point3,
+        octaves: usize,
+        layer: impl Fn(&Self, Point3) -> Decimal,
+    ) -> Decimal {
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        for _ in 0..octaves {
+            total += amplitude * layer(self, point);
+            amplitude *= 0.5;
+            point *= 2.0;
+        }
+        total
+    }
+}
+
+///Cellular noise - every lattice cell has one random feature point, and the noise is based on the distances to the closest ones.
+///
+///The same seed always gives the same noise.
+#[derive(Debug, Copy, Clone)]
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    #[must_use]
+    pub const fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    ///The feature point in the cell with the minimum corner at `(x, y, z)`
+    fn feature_point(self, x: i64, y: i64, z: i64) -> Point3 {
+        //splitmix64, so we don't need to store anything per cell
+        let mix = |mut value: u64| {
+            value = value.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            value ^ (value >> 31)
+        };
+        let hash = mix(self.seed ^ mix(x as u64 ^ mix(y as u64 ^ mix(z as u64))));
+
+        //21 bits for each axis
+        let axis = |shift: u32| ((hash >> shift) & 0x1F_FFFF) as Decimal / 2_097_152.0;
+        Vec3::new(
+            x as Decimal + axis(0),
+            y as Decimal + axis(21),
+            z as Decimal + axis(42),
+        )
+    }
+
+    ///The distances to the closest and second closest feature points
+    #[must_use]
+    pub fn distances(self, point: Point3) -> (Decimal, Decimal) {
+        let (x, y, z) = (
+            point.x().floor() as i64,
+            point.y().floor() as i64,
+            point.z().floor() as i64,
+        );
+
+        //feature points are inside their cell, so the closest is always in the 3x3x3 block around us, and the second closest almost always is
+        let mut closest = (Decimal::INFINITY, Decimal::INFINITY);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let distance = (self.feature_point(x + dx, y + dy, z + dz) - point).magnitude();
+                    if distance < closest.0 {
+                        closest = (distance, closest.0);
+                    } else if distance < closest.1 {
+                        closest.1 = distance;
+                    }
+                }
+            }
+        }
+        closest
+    }
+}
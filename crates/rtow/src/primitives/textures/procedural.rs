@@ -0,0 +1,197 @@
+//! Solid textures made from [`noise`](super::noise), which are evaluated at the world position rather than the UVs.
+//!
+//!They blend between two colours, and `scale` is the frequency of the pattern - bigger numbers give smaller details.
+
+use crate::primitives::{
+    textures::{
+        noise::{Perlin, Worley},
+        Texture,
+    },
+    Colour, Decimal, Point3,
+};
+
+fn blend(low: Colour, high: Colour, t: Decimal) -> Colour {
+    let t = t.clamp(0.0, 1.0);
+    low * (1.0 - t) + high * t
+}
+
+///Plain Perlin noise
+#[derive(Debug, Clone)]
+pub struct PerlinTexture {
+    perlin: Perlin,
+    scale: Decimal,
+    low: Colour,
+    high: Colour,
+}
+
+impl PerlinTexture {
+    #[must_use]
+    pub fn new(seed: u64, scale: Decimal, low: Colour, high: Colour) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            scale,
+            low,
+            high,
+        }
+    }
+}
+
+impl Texture for PerlinTexture {
+    fn value(&self, _u: Decimal, _v: Decimal, point: Point3) -> Colour {
+        let noise = self.perlin.noise(self.scale * point);
+        blend(self.low, self.high, 0.5 * (1.0 + noise))
+    }
+}
+
+///Turbulent fBm noise, which looks like smoke or clouds
+#[derive(Debug, Clone)]
+pub struct Turbulence {
+    perlin: Perlin,
+    scale: Decimal,
+    octaves: usize,
+    low: Colour,
+    high: Colour,
+}
+
+impl Turbulence {
+    #[must_use]
+    pub fn new(seed: u64, scale: Decimal, octaves: usize, low: Colour, high: Colour) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            scale,
+            octaves,
+            low,
+            high,
+        }
+    }
+}
+
+impl Texture for Turbulence {
+    fn value(&self, _u: Decimal, _v: Decimal, point: Point3) -> Colour {
+        blend(
+            self.low,
+            self.high,
+            self.perlin.turbulence(self.scale * point, self.octaves),
+        )
+    }
+}
+
+///Veins along the z-axis, pushed around by turbulence
+#[derive(Debug, Clone)]
+pub struct Marble {
+    perlin: Perlin,
+    scale: Decimal,
+    ///how much the turbulence distorts the veins
+    distortion: Decimal,
+    vein: Colour,
+    base: Colour,
+}
+
+impl Marble {
+    #[must_use]
+    pub fn new(seed: u64, scale: Decimal, distortion: Decimal, vein: Colour, base: Colour) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            scale,
+            distortion,
+            vein,
+            base,
+        }
+    }
+}
+
+impl Texture for Marble {
+    fn value(&self, _u: Decimal, _v: Decimal, point: Point3) -> Colour {
+        let point = self.scale * point;
+        let phase = point.z() + self.distortion * self.perlin.turbulence(point, 7);
+        blend(self.vein, self.base, 0.5 * (1.0 + phase.sin()))
+    }
+}
+
+///Growth rings around the y-axis, wobbled by noise so they aren't perfect circles
+#[derive(Debug, Clone)]
+pub struct Wood {
+    perlin: Perlin,
+    ///number of rings per unit
+    scale: Decimal,
+    ///how much the noise moves the rings
+    distortion: Decimal,
+    early: Colour,
+    late: Colour,
+}
+
+impl Wood {
+    #[must_use]
+    pub fn new(
+        seed: u64,
+        scale: Decimal,
+        distortion: Decimal,
+        early: Colour,
+        late: Colour,
+    ) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            scale,
+            distortion,
+            early,
+            late,
+        }
+    }
+}
+
+impl Texture for Wood {
+    fn value(&self, _u: Decimal, _v: Decimal, point: Point3) -> Colour {
+        let point = self.scale * point;
+        let radius = point.x().hypot(point.z()) + self.distortion * self.perlin.fbm(point, 4);
+        //the late wood is a thinner, sharper band at the end of each ring
+        blend(self.early, self.late, radius.fract().abs().powi(4))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CellularPattern {
+    ///Distance to the closest feature point - blobs that are darkest in the middle
+    Distance,
+    ///Difference between the closest two distances - dark lines between the cells
+    Edges,
+}
+
+///Worley noise, which gives cells like stone tiles, scales or foam
+#[derive(Debug, Copy, Clone)]
+pub struct Cellular {
+    worley: Worley,
+    scale: Decimal,
+    pattern: CellularPattern,
+    low: Colour,
+    high: Colour,
+}
+
+impl Cellular {
+    #[must_use]
+    pub const fn new(
+        seed: u64,
+        scale: Decimal,
+        pattern: CellularPattern,
+        low: Colour,
+        high: Colour,
+    ) -> Self {
+        Self {
+            worley: Worley::new(seed),
+            scale,
+            pattern,
+            low,
+            high,
+        }
+    }
+}
+
+impl Texture for Cellular {
+    fn value(&self, _u: Decimal, _v: Decimal, point: Point3) -> Colour {
+        let (closest, second) = self.worley.distances(self.scale * point);
+        let t = match self.pattern {
+            CellularPattern::Distance => closest,
+            CellularPattern::Edges => 2.0 * (second - closest),
+        };
+        blend(self.low, self.high, t)
+    }
+}
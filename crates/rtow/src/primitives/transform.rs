@@ -0,0 +1,116 @@
+use crate::primitives::{Decimal, Mat4, Point3, Ray, RayDifferentials, Vec3};
+
+///An affine transform, which keeps its inverse around so we can cheaply go between world space and object space
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    ///object space to world space
+    matrix: Mat4,
+    ///world space to object space
+    inverse: Mat4,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        matrix: Mat4::IDENTITY,
+        inverse: Mat4::IDENTITY,
+    };
+
+    ///Makes a transform from an arbitrary matrix.
+    ///
+    ///Returns [`None`] if the matrix cannot be inverted.
+    #[must_use]
+    pub fn new(matrix: Mat4) -> Option<Self> {
+        Some(Self {
+            matrix,
+            inverse: matrix.inverse()?,
+        })
+    }
+
+    #[must_use]
+    pub const fn translation(by: Vec3) -> Self {
+        Self {
+            matrix: Mat4::translation(by),
+            inverse: Mat4::translation(Vec3::new(-by.x(), -by.y(), -by.z())),
+        }
+    }
+    ///Non-uniform scale - none of the components should be 0
+    #[must_use]
+    pub fn scale(by: Vec3) -> Self {
+        Self {
+            matrix: Mat4::scale(by),
+            inverse: Mat4::scale(Vec3::new(1.0 / by.x(), 1.0 / by.y(), 1.0 / by.z())),
+        }
+    }
+    ///Rotation of `degrees` anti-clockwise around `axis`
+    #[must_use]
+    pub fn rotation(axis: Vec3, degrees: Decimal) -> Self {
+        let matrix = Mat4::rotation(axis, degrees);
+        Self {
+            matrix,
+            //rotation matrices are orthogonal
+            inverse: matrix.transpose(),
+        }
+    }
+    #[must_use]
+    pub fn rotation_x(degrees: Decimal) -> Self {
+        Self::rotation(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+    #[must_use]
+    pub fn rotation_y(degrees: Decimal) -> Self {
+        Self::rotation(Vec3::UP, degrees)
+    }
+    #[must_use]
+    pub fn rotation_z(degrees: Decimal) -> Self {
+        Self::rotation(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    ///Makes a transform that applies `self`, and then `next`
+    #[must_use]
+    pub fn then(self, next: Self) -> Self {
+        Self {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+    #[must_use]
+    pub const fn inverse(self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    #[must_use]
+    pub const fn matrix(&self) -> Mat4 {
+        self.matrix
+    }
+
+    #[must_use]
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.matrix.transform_point(p)
+    }
+    #[must_use]
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+    ///Normals need the inverse transpose to stay perpendicular under non-uniform scales.
+    ///
+    ///The result is not normalised.
+    #[must_use]
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.inverse.transpose().transform_vector(n)
+    }
+    ///Transforms the ray without normalising the direction, so `t` values are the same in both spaces
+    #[must_use]
+    pub fn ray(&self, ray: Ray) -> Ray {
+        let transformed = Ray::new(self.point(ray.origin()), self.vector(ray.direction()));
+        ray.differentials().map_or(transformed, |differentials| {
+            transformed.with_differentials(RayDifferentials {
+                x_origin: self.point(differentials.x_origin),
+                x_direction: self.vector(differentials.x_direction),
+                y_origin: self.point(differentials.y_origin),
+                y_direction: self.vector(differentials.y_direction),
+            })
+        })
+    }
+}
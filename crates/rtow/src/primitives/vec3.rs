@@ -29,6 +29,11 @@ impl Vec3 {
     pub const fn new(x: Decimal, y: Decimal, z: Decimal) -> Self {
         Self { x, y, z }
     }
+    ///All components set to `value`
+    #[must_use]
+    pub const fn splat(value: Decimal) -> Self {
+        Self::new(value, value, value)
+    }
 
     #[must_use]
     pub const fn x(&self) -> Decimal {
@@ -68,6 +73,20 @@ impl Vec3 {
             self.x * y - self.y * x,
         )
     }
+    ///Two unit vectors perpendicular to this one (and each other), assuming this is a unit vector.
+    ///
+    ///Uses the branchless method from Duff et al. 2017
+    #[must_use]
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let sign = 1.0_f64.copysign(self.z);
+        let a = -1.0 / (sign + self.z);
+        let b = self.x * self.y * a;
+
+        (
+            Self::new(1.0 + sign * self.x * self.x * a, sign * b, -sign * self.x),
+            Self::new(b, sign + self.y * self.y * a, -self.y),
+        )
+    }
 
     #[must_use]
     ///0 to 1
@@ -106,7 +125,7 @@ impl Vec3 {
     }
     pub fn random_in_unit_disk(rng: &mut ThreadRng) -> Self {
         loop {
-            let p = Self::new(rng.gen_range(0.0..=1.0), rng.gen_range(0.0..=1.0), 0.0);
+            let p = Self::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), 0.0);
             if p.magnitude_squared() < 1.0 {
                 return p;
             }